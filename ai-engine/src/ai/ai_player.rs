@@ -1,29 +1,283 @@
 use std::{
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
     time::{Duration, Instant},
 };
 
 use rayon::{iter::IntoParallelRefIterator, prelude::ParallelIterator};
 
 use crate::{
-    common::{contants::INVALID_BOARD_POSITION, enums::PieceColor, piece::Piece, piece_move::PieceMove},
+    common::{
+        board_utils::{get_position_rank, get_uci_move_notation},
+        contants::{INVALID_BOARD_POSITION, KING_WORTH, PAWN_WORTH},
+        enums::{PieceColor, PieceType},
+        piece::Piece,
+        piece_move::PieceMove,
+        piece_utils::{get_piece_type, get_piece_worth, is_white_piece},
+    },
     game::board::Board,
 };
 
 use super::{
-    ai_utils::{get_board_value, get_sorted_moves},
-    transposition_table::{TranspositionTable, TranspositionTableEntry},
+    ai_utils::{get_board_value, get_sorted_moves, get_static_exchange_value},
+    constants::{
+        ASPIRATION_WIDENING, ASPIRATION_WINDOW, LATE_MOVE_INDEX, LATE_MOVE_MIN_DEPTH, LATE_MOVE_REDUCTION_BASE,
+        LATE_MOVE_REDUCTION_DIVISOR, MAX_ASPIRATION_WINDOW, MAX_QUIESCENCE_PLIES, NULL_MOVE_DEPTH_DIVISOR,
+        NULL_MOVE_MIN_DEPTH, NULL_MOVE_REDUCTION, NULL_MOVE_VERIFICATION_PIECES, NULL_WINDOW,
+        QUIESCENCE_DELTA_MARGIN,
+    },
+    endgame_tables::{Dtm, EndgameTables, MAX_MATE_PLIES},
+    opening_book::{BookMode, OpeningBook},
+    syzygy::{Tablebase, Wdl, DEFAULT_PROBE_DEPTH, TABLEBASE_WIN_SCORE},
+    search_context::SearchContext,
+    transposition_table::{Bound, TranspositionTable, TranspositionTableEntry},
 };
 
-pub struct AIPlayer {}
+#[derive(Debug, Clone, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub infinite: bool,
+    pub move_time: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+    pub fn from_time_to_think(time_to_think: u64) -> Self {
+        SearchLimits {
+            move_time: Some(Duration::new(time_to_think, 0)),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    // Exact once the iteration is complete, a bound when it failed its aspiration window
+    pub bound: Bound,
+    pub depth: u8,
+    pub elapsed: Duration,
+    pub nodes: u64,
+    pub pv: Vec<PieceMove>,
+    // Searches of the iteration repeated with a wider window
+    pub researches: u32,
+    pub score: f32,
+    pub table_entries: usize,
+    pub table_hits: u64,
+    pub table_size_kb: usize,
+}
+
+impl SearchInfo {
+    pub fn get_nps(&self) -> u64 {
+        let elapsed = self.elapsed.as_millis() as u64;
+
+        if elapsed == 0 {
+            return self.nodes * 1000;
+        }
+
+        self.nodes * 1000 / elapsed
+    }
+
+    /// Moves until the checkmate, negative when the side to move is the one mated. Found
+    /// checkmates score more the less deep they are, distance to mate tables score more
+    /// the fewer plies they take. Tablebase wins without a distance aren't mates.
+    pub fn get_mate_moves(&self) -> Option<i32> {
+        let score = self.score.abs();
+
+        let plies = if score >= KING_WORTH * 10.0 {
+            let depth_left = (score / (KING_WORTH * 10.0)).round() as u8;
+
+            self.depth.saturating_sub(depth_left).max(1) as i32
+        } else if score > TABLEBASE_WIN_SCORE {
+            MAX_MATE_PLIES as i32 + 1 - (score - TABLEBASE_WIN_SCORE).round() as i32
+        } else {
+            return None;
+        };
+
+        let moves = (plies + 1) / 2;
+
+        Some(if self.score > 0.0 { moves } else { -moves })
+    }
+}
+
+// State shared by every branch of a single search
+struct SearchControl {
+    aborted: AtomicBool,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    nodes: AtomicU64,
+    stop: Arc<AtomicBool>,
+}
+
+impl SearchControl {
+    fn count_node(&self) {
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn get_nodes(&self) -> u64 {
+        self.nodes.load(Ordering::Relaxed)
+    }
+
+    fn should_stop(&self) -> bool {
+        if self.aborted.load(Ordering::Relaxed) || self.stop.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        let out_of_nodes = self
+            .node_limit
+            .is_some_and(|node_limit| self.get_nodes() >= node_limit);
+
+        let out_of_time = self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline);
+
+        if out_of_nodes || out_of_time {
+            self.aborted.store(true, Ordering::Relaxed);
+
+            return true;
+        }
+
+        false
+    }
+}
+
+pub struct AIPlayer {
+    book: Option<OpeningBook>,
+    book_mode: BookMode,
+    endgame_tables: Option<EndgameTables>,
+    // Whether the quiescence search answers checks with every move instead of standing pat
+    quiescence_checks: bool,
+    // Whether null move cutoffs in positions with few pieces are confirmed by a reduced search
+    null_move_verification: bool,
+    // Positions with as many pieces as the largest tables are only probed this deep
+    probe_depth: u8,
+    stop: Arc<AtomicBool>,
+    tablebase: Option<Tablebase>,
+}
 
 impl AIPlayer {
     pub fn new() -> Self {
-        AIPlayer {}
+        AIPlayer {
+            book: None,
+            book_mode: BookMode::default(),
+            endgame_tables: None,
+            quiescence_checks: true,
+            null_move_verification: true,
+            probe_depth: DEFAULT_PROBE_DEPTH,
+            stop: Arc::new(AtomicBool::new(false)),
+            tablebase: None,
+        }
     }
 
-    pub fn get_move(&mut self, board: &mut Board, time_to_think: u64) -> (u128, u8, f32, PieceMove) {
-        let mut depth = 1;
+    pub fn set_book(&mut self, book: Option<OpeningBook>) {
+        self.book = book;
+    }
+
+    pub fn set_book_mode(&mut self, book_mode: BookMode) {
+        self.book_mode = book_mode;
+    }
+
+    pub fn set_endgame_tables(&mut self, endgame_tables: Option<EndgameTables>) {
+        self.endgame_tables = endgame_tables;
+    }
+
+    pub fn set_null_move_verification(&mut self, null_move_verification: bool) {
+        self.null_move_verification = null_move_verification;
+    }
+
+    pub fn set_quiescence_checks(&mut self, quiescence_checks: bool) {
+        self.quiescence_checks = quiescence_checks;
+    }
+
+    pub fn set_tablebase(&mut self, tablebase: Option<Tablebase>) {
+        self.tablebase = tablebase;
+    }
+
+    pub fn set_probe_depth(&mut self, probe_depth: u8) {
+        self.probe_depth = probe_depth;
+    }
+
+    /// Move of the opening book for the position, if a book is loaded and has one
+    pub fn get_book_move(&self, board: &mut Board) -> Option<PieceMove> {
+        self.book
+            .as_ref()?
+            .get_move(board, self.book_mode, &mut rand::thread_rng())
+    }
+
+    /// Flag that interrupts a running search once set. It is never cleared by
+    /// the search itself, so whoever sets it must reset it before the next one.
+    pub fn get_stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    pub fn get_move(&self, board: &mut Board, time_to_think: u64) -> (u128, u8, f32, PieceMove) {
+        let start_time = Instant::now();
+
+        if let Some(book_move) = self.get_book_move(board) {
+            println!("Book move {}", get_uci_move_notation(&book_move));
+
+            return (start_time.elapsed().as_millis(), 0, 0.0, book_move);
+        }
+
+        let (depth, evaluation, best_move) = self.search(
+            board,
+            &SearchLimits::from_time_to_think(time_to_think),
+            |info| {
+                println!(
+                    "Transposition table size on depth {}: ~{}kb",
+                    info.depth, info.table_size_kb
+                );
+
+                println!(
+                    "Evaluated {} states ({} nodes) in {}ms with depth of {} and {} hits in the table. Best move eval: {} ({:?} after {} re-searches)",
+                    info.table_entries,
+                    info.nodes,
+                    info.elapsed.as_millis(),
+                    info.depth,
+                    info.table_hits,
+                    info.score,
+                    info.bound,
+                    info.researches
+                )
+            },
+        );
+
+        (start_time.elapsed().as_millis(), depth, evaluation, best_move)
+    }
+
+    /// Iterative deepening search bounded by `limits`. `on_iteration` is called
+    /// after every completed depth. Returns the last completed depth, its score
+    /// and the best move found.
+    pub fn search<F>(
+        &self,
+        board: &mut Board,
+        limits: &SearchLimits,
+        mut on_iteration: F,
+    ) -> (u8, f32, PieceMove)
+    where
+        F: FnMut(&SearchInfo),
+    {
+        let start_time = Instant::now();
+
+        let control = SearchControl {
+            aborted: AtomicBool::new(false),
+            deadline: if limits.infinite {
+                None
+            } else {
+                limits.move_time.map(|move_time| start_time + move_time)
+            },
+            node_limit: if limits.infinite { None } else { limits.nodes },
+            nodes: AtomicU64::new(0),
+            stop: self.stop.clone(),
+        };
+
+        let max_depth = if limits.infinite {
+            u8::MAX
+        } else {
+            limits.depth.unwrap_or(u8::MAX)
+        };
+
         let best_move = Arc::new(Mutex::new(PieceMove::new(
             INVALID_BOARD_POSITION,
             0,
@@ -31,95 +285,402 @@ impl AIPlayer {
         )));
 
         let transposition_table: Arc<Mutex<TranspositionTable>> = Arc::new(Mutex::new(TranspositionTable::new()));
-        
-        let mut max = true;
-        
-        let alpha = Arc::new(Mutex::new(f32::MIN));
 
-        let start_time = Instant::now();
+        // Won and lost table positions are played from the tables, only the drawing
+        // moves are searched in drawn ones
+        let mut drawing_moves = None;
+
+        if let Some(mut root_moves) = self.probe_root_tables(board) {
+            root_moves.sort_by_key(|(_, rank, _)| std::cmp::Reverse(*rank));
+
+            if let Some((piece_move, _, score)) = root_moves.first().filter(|(_, rank, _)| *rank != 0) {
+                on_iteration(&SearchInfo {
+                    bound: Bound::Exact,
+                    depth: 1,
+                    elapsed: start_time.elapsed(),
+                    nodes: 0,
+                    pv: vec![piece_move.clone()],
+                    researches: 0,
+                    score: *score,
+                    table_entries: 0,
+                    table_hits: 0,
+                    table_size_kb: 0,
+                });
+
+                return (1, *score, piece_move.clone());
+            }
+
+            drawing_moves = Some(
+                root_moves
+                    .into_iter()
+                    .filter(|(_, rank, _)| *rank == 0)
+                    .map(|(piece_move, _, _)| piece_move)
+                    .collect::<Vec<PieceMove>>(),
+            );
+        }
+
+        // Each thread keeps its own killers and history for the whole search
+        let contexts: Vec<Mutex<SearchContext>> = (0..rayon::current_num_threads())
+            .map(|_| Mutex::new(SearchContext::new()))
+            .collect();
+
+        let mut completed_depth = 0;
+        let mut evaluation: f32 = 0.0;
+        let mut depth = 1;
 
-        while Instant::now().duration_since(start_time) < Duration::new(time_to_think, 0) 
-            && depth < u8::MAX {
+        while depth <= max_depth && !control.should_stop() {
+            let previous_best_move = best_move.lock().unwrap().to_owned();
 
-            let current_best_move = best_move.clone();
+            contexts.iter().for_each(|context| context.lock().unwrap().age_history());
 
             let pieces: Vec<Piece> = board.get_pieces();
 
-            let best_move_guard = current_best_move.lock().unwrap();
+            let mut moves: Vec<PieceMove> =
+                get_sorted_moves(&Some(previous_best_move.clone()), board, true, &pieces, None, 0);
 
-            let moves: Vec<PieceMove> =
-                get_sorted_moves(&Some(best_move_guard.to_owned()), board, true, &pieces);
+            if let Some(drawing_moves) = &drawing_moves {
+                moves.retain(|_move| {
+                    drawing_moves.iter().any(|drawing_move| drawing_move.is_same_move(_move))
+                });
+            }
 
-            drop(best_move_guard);
+            if moves.is_empty() {
+                break;
+            }
 
-            moves.par_iter().for_each(|_move| {
-                let mut new_board = board.clone();
+            // Makes sure there is a move to play even if the first iteration is interrupted
+            if completed_depth == 0 {
+                *best_move.lock().unwrap() = moves[0].clone();
+            }
 
-                let _ = new_board.move_piece(&_move);
+            // The search starts with a window around the previous iteration's score, it
+            // is widened on the failing side and searched again when the score falls outside
+            let mut window = ASPIRATION_WINDOW;
+            let mut researches = 0;
 
-                let score = -self.negamax(
-                    &mut new_board,
-                    -f32::MAX,
-                    -*alpha.lock().unwrap(),
-                    !max,
-                    depth,
-                    &transposition_table,
-                );
+            // Mate and tablebase scores change with the depth, they are searched unbounded
+            let (mut lower, mut upper) = if completed_depth > 0 && evaluation.abs() < KING_WORTH {
+                (evaluation - window, evaluation + window)
+            } else {
+                (f32::MIN, f32::MAX)
+            };
+
+            let score = loop {
+                let alpha = Mutex::new(lower);
+
+                let search_root_move = |first: bool, _move: &PieceMove| {
+                    let mut context = contexts[rayon::current_thread_index().unwrap_or(0)].lock().unwrap();
+
+                    let mut new_board = board.clone();
+
+                    let _ = new_board.move_piece(_move);
+
+                    let move_alpha = *alpha.lock().unwrap();
+
+                    let mut search = |alpha: f32, beta: f32| {
+                        -self.negamax(
+                            &mut new_board,
+                            -beta,
+                            -alpha,
+                            depth - 1,
+                            1,
+                            &transposition_table,
+                            &control,
+                            &mut context,
+                        )
+                    };
+
+                    // Like in the rest of the tree, the moves after the first one only have
+                    // to be proven worse with a null window, and are searched again if not
+                    let mut score = if first {
+                        search(move_alpha, upper)
+                    } else {
+                        search(move_alpha, move_alpha + NULL_WINDOW)
+                    };
+
+                    if !first && score > move_alpha && !control.should_stop() {
+                        score = search(move_alpha, upper);
+                    }
+
+                    if control.should_stop() {
+                        return;
+                    }
+
+                    let mut alpha_guard = alpha.lock().unwrap();
+
+                    if score > *alpha_guard {
+                        *alpha_guard = score;
 
-                let mut alpha_guard = alpha.lock().unwrap();
+                        let mut best_move_guard = best_move.lock().unwrap();
 
-                if score > *alpha_guard {
-                    *alpha_guard = score;
-                    
-                    let mut best_move_guard = current_best_move.lock().unwrap();
-                    
-                    *best_move_guard = _move.clone();
+                        *best_move_guard = _move.clone();
+                    }
+
+                    drop(alpha_guard);
+                };
+
+                // The first move sets the bound the others are scouted against
+                search_root_move(true, &moves[0]);
+
+                moves[1..].par_iter().for_each(|_move| search_root_move(false, _move));
+
+                let score = *alpha.lock().unwrap();
+
+                let bound = Bound::from_window(score, lower, upper);
+
+                if control.should_stop() || bound == Bound::Exact || (lower == f32::MIN && upper == f32::MAX) {
+                    break score;
                 }
 
-                drop(alpha_guard);
-            });
+                researches += 1;
+                window *= ASPIRATION_WIDENING;
 
-            depth += 1;
-            max = !max;
+                let unbounded = window > MAX_ASPIRATION_WINDOW;
 
-            let _transposition_table = transposition_table.lock().unwrap();
+                if bound == Bound::Upper {
+                    lower = if unbounded { f32::MIN } else { evaluation - window };
+                } else {
+                    upper = if unbounded { f32::MAX } else { evaluation + window };
+                }
 
-            let current_best_move = current_best_move.lock().unwrap();
+                on_iteration(&self.get_search_info(
+                    board,
+                    &best_move,
+                    &transposition_table,
+                    &control,
+                    start_time,
+                    depth,
+                    score,
+                    bound,
+                    researches,
+                ));
+            };
 
-            drop(current_best_move);
+            // An interrupted iteration may have only seen part of the moves
+            if control.should_stop() && completed_depth > 0 {
+                *best_move.lock().unwrap() = previous_best_move;
 
-            println!(
-                "Transposition table size on depth {}: ~{}kb",
+                break;
+            }
+
+            completed_depth = depth;
+            evaluation = score;
+
+            on_iteration(&self.get_search_info(
+                board,
+                &best_move,
+                &transposition_table,
+                &control,
+                start_time,
                 depth,
-                _transposition_table.estimated_memory_usage_kb()
-            );
+                score,
+                Bound::Exact,
+                researches,
+            ));
+
+            if control.should_stop() || depth == u8::MAX {
+                break;
+            }
 
-            drop(_transposition_table)
+            depth += 1;
         }
 
+        let best_move = best_move.lock().unwrap().to_owned();
+
+        (completed_depth, evaluation, best_move)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn get_search_info(
+        &self,
+        board: &Board,
+        best_move: &Arc<Mutex<PieceMove>>,
+        transposition_table: &Arc<Mutex<TranspositionTable>>,
+        control: &SearchControl,
+        start_time: Instant,
+        depth: u8,
+        score: f32,
+        bound: Bound,
+        researches: u32,
+    ) -> SearchInfo {
+        let current_best_move = best_move.lock().unwrap().to_owned();
+
+        let pv = self.get_principal_variation(board, &current_best_move, transposition_table, depth);
+
         let _transposition_table = transposition_table.lock().unwrap();
 
-        let locked_alpha = alpha.lock().unwrap();
+        SearchInfo {
+            bound,
+            depth,
+            elapsed: start_time.elapsed(),
+            nodes: control.get_nodes(),
+            pv,
+            researches,
+            score,
+            table_entries: _transposition_table.len(),
+            table_hits: _transposition_table.get_hits(),
+            table_size_kb: _transposition_table.estimated_memory_usage_kb(),
+        }
+    }
+
+    fn get_principal_variation(
+        &self,
+        board: &Board,
+        best_move: &PieceMove,
+        transposition_table: &Arc<Mutex<TranspositionTable>>,
+        depth: u8,
+    ) -> Vec<PieceMove> {
+        let mut pv = Vec::with_capacity(depth as usize);
+
+        if best_move.get_from_position() == INVALID_BOARD_POSITION {
+            return pv;
+        }
+
+        let mut board = board.clone();
+        let mut visited_hashes = vec![board.get_zobrist_hash()];
+        let mut next_move = Some(best_move.clone());
+
+        while let Some(piece_move) = next_move {
+            if pv.len() >= depth as usize || board.move_piece(&piece_move).is_err() {
+                break;
+            }
+
+            pv.push(piece_move);
+
+            let hash = board.get_zobrist_hash();
+
+            if visited_hashes.contains(&hash) {
+                break;
+            }
+
+            visited_hashes.push(hash);
+
+            let mut _transposition_table = transposition_table.lock().unwrap();
+
+            next_move = _transposition_table
+                .retrieve(hash)
+                .and_then(|entry| entry.best_move.clone());
+
+            drop(_transposition_table);
+
+            // Entries may come from another position with the same hash
+            if let Some(candidate) = &next_move {
+                let is_legal = board.get_pieces().iter().any(|piece| {
+                    piece
+                        .get_moves_reference()
+                        .iter()
+                        .any(|_move| _move.get_from_position() == candidate.get_from_position()
+                            && _move.get_to_position() == candidate.get_to_position())
+                });
+
+                if !is_legal {
+                    break;
+                }
+            }
+        }
 
-        let locked_best_move = best_move.lock().unwrap();
+        pv
+    }
+
+    /// Legal moves of the root position with their rank and score, from the distance to
+    /// mate tables when they have the position and from the Syzygy tables otherwise
+    fn probe_root_tables(&self, board: &mut Board) -> Option<Vec<(PieceMove, i32, f32)>> {
+        if let Some(root_moves) = self.endgame_tables.as_ref().and_then(|tables| tables.probe_root(board)) {
+            return Some(
+                root_moves
+                    .into_iter()
+                    .map(|(piece_move, dtm)| (piece_move, dtm.get_rank(), dtm.get_score()))
+                    .collect(),
+            );
+        }
 
-        let best_move = locked_best_move.to_owned();
+        let root_moves = self.tablebase.as_ref()?.probe_root(board)?;
 
-        println!("Evaluated {} states in {}ms with depth of {} and {} hits in the table. Best move eval: {}", 
-            _transposition_table.len(), start_time.elapsed().as_millis(), depth, _transposition_table.get_hits(), best_move.get_move_worth());
+        Some(
+            root_moves
+                .into_iter()
+                .map(|root_move| {
+                    let score = root_move.get_score();
 
-        (start_time.elapsed().as_millis(), depth, locked_alpha.to_owned(), best_move)
+                    (root_move.piece_move, root_move.rank, score)
+                })
+                .collect(),
+        )
     }
 
+    /// Exact score of the position from the tables. The distance to mate tables are
+    /// always probed, the Syzygy ones only right after a capture or a pawn move since
+    /// they don't know how many moves are left before the fifty move rule.
+    fn probe_tablebase(&self, board: &mut Board, depth: u8) -> Option<f32> {
+        let dtm = self
+            .endgame_tables
+            .as_ref()
+            .and_then(|tables| tables.probe(board.get_state_reference()));
+
+        // Wins closer to the root are preferred
+        if let Some(dtm) = dtm {
+            return Some(match dtm {
+                Dtm::Win(_) => dtm.get_score() + depth as f32,
+                Dtm::Loss(_) => dtm.get_score() - depth as f32,
+                Dtm::Draw => dtm.get_score(),
+            });
+        }
+
+        let wdl = self.probe_syzygy(board, depth)?;
+
+        Some(match wdl {
+            Wdl::Win => wdl.get_score() + depth as f32,
+            Wdl::Loss => wdl.get_score() - depth as f32,
+            _ => wdl.get_score(),
+        })
+    }
+
+    fn probe_syzygy(&self, board: &mut Board, depth: u8) -> Option<Wdl> {
+        let tablebase = self.tablebase.as_ref()?;
+
+        let board_state = board.get_state_reference();
+
+        let piece_count = board_state.get_occupancy().count_ones() as usize;
+
+        if board_state.get_half_moves() != 0
+            || piece_count > tablebase.get_max_pieces()
+            || (piece_count == tablebase.get_max_pieces() && depth < self.probe_depth)
+        {
+            return None;
+        }
+
+        tablebase.probe_wdl(board)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn negamax(
         &self,
         board: &mut Board,
         alpha: f32,
         beta: f32,
-        max: bool,
         depth: u8,
+        ply: u8,
         transposition_table: &Arc<Mutex<TranspositionTable>>,
+        control: &SearchControl,
+        context: &mut SearchContext,
     ) -> f32 {
+        // Captures are resolved past the horizon so it never stops in the middle of an exchange
+        if depth == 0 {
+            return self.quiescence(board, alpha, beta, 0, transposition_table, control);
+        }
+
+        control.count_node();
+
+        if control.should_stop() {
+            return 0.0;
+        }
+
+        // The opponent can always repeat the position again, so it is scored as a draw.
+        // This is done before probing the table since its values ignore the move history.
+        if board.is_repeated_position() {
+            return 0.0;
+        }
+
         // Check if the position is already in the table
         let mut _transposition_table = transposition_table.lock().unwrap();
 
@@ -128,17 +689,37 @@ impl AIPlayer {
         if let Some(entry) = _transposition_table.retrieve(board.get_zobrist_hash()) {
             best_move = entry.best_move.clone();
 
-            if entry.depth >= depth {
+            if entry.is_usable(depth, alpha, beta) {
                 return entry.value;
             }
         }
 
         drop(_transposition_table);
 
+        if let Some(value) = self.probe_tablebase(board, depth) {
+            let mut _transposition_table = transposition_table.lock().unwrap();
+
+            _transposition_table.store(
+                board.get_zobrist_hash(),
+                TranspositionTableEntry {
+                    depth,
+                    value,
+                    bound: Bound::Exact,
+                    best_move,
+                },
+            );
+
+            return value;
+        }
+
         let pieces: Vec<Piece> = board.get_pieces();
 
-        if depth == 0 || board.is_game_finished() {
-            let value: f32 = get_board_value(board, max, &pieces);
+        if board.is_game_finished() {
+            // A checkmate only scores against the side to move with max unset
+            let value: f32 = get_board_value(board, false, &pieces);
+
+            // Favors checkmates the require less moves
+            let value = if depth > 1 { value * depth as f32 } else { value };
 
             let mut _transposition_table = transposition_table.lock().unwrap();
 
@@ -147,57 +728,211 @@ impl AIPlayer {
                 TranspositionTableEntry {
                     depth,
                     value,
+                    bound: Bound::Exact,
                     best_move,
                 },
             );
 
-            // Favors checkmates the require less moves
-            return if board.is_game_finished() && depth > 1 {
-                value * depth as f32
-            } else {
-                value
-            };
+            return value;
         }
 
-        let moves: Vec<PieceMove> = get_sorted_moves(&best_move, board, max, &pieces);
+        let in_check = if board.is_white_move() {
+            board.is_white_king_in_check()
+        } else {
+            board.is_black_king_in_check()
+        };
 
-        let mut alpha = alpha;
-        for (i, _move) in moves.iter().enumerate() {
-            let _ = board.move_piece(_move);
+        // If passing the turn still fails high, a real move would most likely too. It is
+        // wrong in zugzwang, so not done without pieces to move besides pawns and the king.
+        let non_pawn_pieces = count_non_pawn_pieces(board, &pieces);
 
-            let mut new_depth = depth - 1;
+        if depth >= NULL_MOVE_MIN_DEPTH
+            && !in_check
+            && !context.is_verifying()
+            && non_pawn_pieces > 0
+            && beta.abs() < KING_WORTH
+            && !board.is_after_null_move()
+        {
+            let reduction = NULL_MOVE_REDUCTION + depth / NULL_MOVE_DEPTH_DIVISOR;
 
-            // Considering the move sorting is good: we could decrease
-            // the depth from the 5th move forward.
-            if i >= 4 && !_move.is_capture() && depth >= 2 {
-                new_depth = 1;
-            }
+            board.make_null_move();
 
             let score = -self.negamax(
                 board,
                 -beta,
-                -alpha,
-                !max,
-                new_depth,
+                -beta + NULL_WINDOW,
+                (depth - 1).saturating_sub(reduction),
+                ply + 1,
                 transposition_table,
+                control,
+                context,
             );
 
+            board.undo_last_move();
+
+            if control.should_stop() {
+                return alpha;
+            }
+
+            if score >= beta {
+                let verified = !self.null_move_verification
+                    || non_pawn_pieces > NULL_MOVE_VERIFICATION_PIECES
+                    || self.verify_null_move(
+                        board,
+                        beta,
+                        depth - reduction,
+                        ply,
+                        transposition_table,
+                        control,
+                        context,
+                    );
+
+                if verified {
+                    return beta;
+                }
+            }
+        }
+
+        // Moves are worth the same from the side to move point of view at every ply
+        let moves: Vec<PieceMove> = get_sorted_moves(&best_move, board, true, &pieces, Some(context), ply);
+
+        let original_alpha = alpha;
+
+        let single_reply = moves.len() == 1;
+
+        // Quiet moves that didn't cause a cutoff lose history when a later one does
+        let mut searched_quiet_moves: Vec<&PieceMove> = Vec::new();
+
+        let mut alpha = alpha;
+        for (i, _move) in moves.iter().enumerate() {
+            let _ = board.move_piece(_move);
+
+            // Found after the move, its flag misses discovered checks, castling and promotions
+            let gives_check = board.is_side_to_move_in_check();
+
+            // Forcing moves are followed further while the line has extensions left
+            let extension = if context.can_extend() {
+                get_extension(board, _move, gives_check, single_reply)
+            } else {
+                0
+            };
+
+            context.extend(extension);
+
+            let quiet = !_move.is_capture() && !_move.is_promotion();
+
+            let full_depth = depth - 1 + extension;
+
+            let mut new_depth = full_depth;
+
+            // Considering the move sorting is good, the late quiet moves are searched with
+            // less depth. Moves that check, escape a check or were good elsewhere aren't.
+            if i >= LATE_MOVE_INDEX
+                && depth >= LATE_MOVE_MIN_DEPTH
+                && quiet
+                && !gives_check
+                && extension == 0
+                && !in_check
+                && !context.is_killer(ply, _move)
+            {
+                new_depth = new_depth.saturating_sub(get_late_move_reduction(depth, i)).max(1);
+            }
+
+            let mut score = if i == 0 {
+                -self.negamax(
+                    board,
+                    -beta,
+                    -alpha,
+                    new_depth,
+                    ply + 1,
+                    transposition_table,
+                    control,
+                    context,
+                )
+            } else {
+                // The first move is expected to be the best, the others only have to be
+                // proven worse with a null window
+                -self.negamax(
+                    board,
+                    -alpha - NULL_WINDOW,
+                    -alpha,
+                    new_depth,
+                    ply + 1,
+                    transposition_table,
+                    control,
+                    context,
+                )
+            };
+
+            // A reduced move that turns out better is searched again at full depth
+            if new_depth < full_depth && score > alpha {
+                score = -self.negamax(
+                    board,
+                    -alpha - NULL_WINDOW,
+                    -alpha,
+                    full_depth,
+                    ply + 1,
+                    transposition_table,
+                    control,
+                    context,
+                );
+            }
+
+            // And with the full window when it is still better
+            if i > 0 && score > alpha && score < beta {
+                score = -self.negamax(
+                    board,
+                    -beta,
+                    -alpha,
+                    full_depth,
+                    ply + 1,
+                    transposition_table,
+                    control,
+                    context,
+                );
+            }
+
             let game_finished = board.is_game_finished();
 
             let draw = board.get_winner() == (PieceColor::Black.value() | PieceColor::White.value());
 
             board.undo_last_move();
 
+            context.retract(extension);
+
+            if control.should_stop() {
+                return alpha;
+            }
+
             if score > alpha {
                 alpha = score;
 
                 best_move = Some(_move.clone());
 
                 if alpha >= beta {
+                    if quiet {
+                        let bonus = depth as i32 * depth as i32;
+
+                        context.add_killer(ply, _move);
+                        context.update_history(_move, bonus);
+
+                        for searched_move in &searched_quiet_moves {
+                            context.update_history(searched_move, -bonus);
+                        }
+
+                        if let Some(previous_move) = board.get_move_history().last() {
+                            context.set_countermove(previous_move, _move);
+                        }
+                    }
+
                     break;
                 }
             }
 
+            if quiet {
+                searched_quiet_moves.push(_move);
+            }
+
             if game_finished && !draw {
                 break;
             }
@@ -210,6 +945,182 @@ impl AIPlayer {
             TranspositionTableEntry {
                 depth,
                 value: alpha,
+                bound: Bound::from_window(alpha, original_alpha, beta),
+                best_move,
+            },
+        );
+
+        alpha
+    }
+
+    /// Searches the position without null moves, at the reduced depth, to confirm the null
+    /// move cutoff wasn't caused by a zugzwang
+    #[allow(clippy::too_many_arguments)]
+    fn verify_null_move(
+        &self,
+        board: &mut Board,
+        beta: f32,
+        depth: u8,
+        ply: u8,
+        transposition_table: &Arc<Mutex<TranspositionTable>>,
+        control: &SearchControl,
+        context: &mut SearchContext,
+    ) -> bool {
+        context.set_verifying(true);
+
+        let score = self.negamax(
+            board,
+            beta - NULL_WINDOW,
+            beta,
+            depth.max(1),
+            ply,
+            transposition_table,
+            control,
+            context,
+        );
+
+        context.set_verifying(false);
+
+        score >= beta
+    }
+
+    /// Searches only the captures and promotions, and every move when in check, until the
+    /// position is quiet. The side to move can always stand pat with the static evaluation
+    /// unless it is in check. Captures that lose material or can't raise the score above
+    /// alpha are skipped. Scores are always from the side to move point of view.
+    fn quiescence(
+        &self,
+        board: &mut Board,
+        alpha: f32,
+        beta: f32,
+        ply: u8,
+        transposition_table: &Arc<Mutex<TranspositionTable>>,
+        control: &SearchControl,
+    ) -> f32 {
+        control.count_node();
+
+        if control.should_stop() {
+            return 0.0;
+        }
+
+        if board.is_repeated_position() {
+            return 0.0;
+        }
+
+        // Any entry was searched at least as deep as the quiescence search goes
+        let mut _transposition_table = transposition_table.lock().unwrap();
+
+        let entry = _transposition_table.retrieve(board.get_zobrist_hash());
+
+        if let Some(entry) = entry.filter(|entry| entry.is_usable(0, alpha, beta)) {
+            return entry.value;
+        }
+
+        // Entries of the main search are kept even when their bound doesn't help here
+        let keep_entry = entry.is_some_and(|entry| entry.depth > 0);
+
+        drop(_transposition_table);
+
+        if let Some(value) = self.probe_tablebase(board, 0) {
+            return value;
+        }
+
+        let pieces: Vec<Piece> = board.get_pieces();
+
+        // A checkmate only scores against the side to move with max unset
+        let stand_pat = get_board_value(board, !board.is_game_finished(), &pieces);
+
+        let in_check = if board.is_white_move() {
+            board.is_white_king_in_check()
+        } else {
+            board.is_black_king_in_check()
+        };
+
+        let evading = in_check && self.quiescence_checks && !board.is_game_finished();
+
+        if board.is_game_finished() || ply >= MAX_QUIESCENCE_PLIES || (!evading && stand_pat >= beta) {
+            return stand_pat;
+        }
+
+        let original_alpha = alpha;
+
+        let mut alpha = alpha;
+
+        if !evading {
+            alpha = alpha.max(stand_pat);
+        }
+
+        let board_state = board.get_state_reference().clone();
+
+        let moves: Vec<PieceMove> = get_sorted_moves(&None, board, true, &pieces, None, 0)
+            .into_iter()
+            .filter(|_move| {
+                if evading {
+                    return true;
+                }
+
+                if !_move.is_capture() && !_move.is_promotion() {
+                    return false;
+                }
+
+                let captured_worth = if _move.is_en_passant() {
+                    PAWN_WORTH
+                } else {
+                    get_piece_worth(board_state.get_piece(_move.get_to_position())) as f32
+                };
+
+                // Delta pruning, promotions can swing the score too much to be skipped
+                if !_move.is_promotion() && stand_pat + captured_worth + QUIESCENCE_DELTA_MARGIN < alpha {
+                    return false;
+                }
+
+                get_static_exchange_value(&board_state, _move) >= 0
+            })
+            .collect();
+
+        let mut best_move = None;
+
+        for _move in moves.iter() {
+            let _ = board.move_piece(_move);
+
+            let score = -self.quiescence(
+                board,
+                -beta,
+                -alpha,
+                ply + 1,
+                transposition_table,
+                control,
+            );
+
+            board.undo_last_move();
+
+            if control.should_stop() {
+                return alpha;
+            }
+
+            if score > alpha {
+                alpha = score;
+
+                best_move = Some(_move.clone());
+
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        if keep_entry {
+            return alpha;
+        }
+
+        let mut _transposition_table = transposition_table.lock().unwrap();
+
+        _transposition_table.store(
+            board.get_zobrist_hash(),
+            TranspositionTableEntry {
+                depth: 0,
+                value: alpha,
+                bound: Bound::from_window(alpha, original_alpha, beta),
                 best_move,
             },
         );
@@ -217,3 +1128,240 @@ impl AIPlayer {
         alpha
     }
 }
+
+/// Pieces of the side to move that aren't pawns or the king
+fn count_non_pawn_pieces(board: &Board, pieces: &[Piece]) -> usize {
+    pieces
+        .iter()
+        .filter(|piece| piece.is_white() == board.is_white_move())
+        .filter(|piece| !matches!(get_piece_type(piece.get_value()), PieceType::Pawn | PieceType::King))
+        .count()
+}
+
+/// One more ply for moves that give check, are the only legal reply, recapture on the
+/// square of the last capture or push a pawn to the seventh rank, once they are played
+fn get_extension(board: &Board, piece_move: &PieceMove, gives_check: bool, single_reply: bool) -> u8 {
+    // The move was already played, the one before it is the opponent's
+    let recapture = piece_move.is_capture()
+        && board
+            .get_move_history()
+            .iter()
+            .rev()
+            .nth(1)
+            .is_some_and(|last_move| last_move.is_capture() && last_move.get_to_position() == piece_move.get_to_position());
+
+    let piece_value = piece_move.get_piece_value();
+
+    let seventh_rank = get_piece_type(piece_value) == PieceType::Pawn
+        && get_position_rank(piece_move.get_to_position()) == if is_white_piece(piece_value) { 7 } else { 2 };
+
+    (gives_check || single_reply || recapture || seventh_rank) as u8
+}
+
+/// Plies taken from the search of a late quiet move, growing slowly with the depth left
+/// and the position of the move in the sorted order
+fn get_late_move_reduction(depth: u8, move_index: usize) -> u8 {
+    let reduction = LATE_MOVE_REDUCTION_BASE
+        + (depth as f32).ln() * (move_index as f32).ln() / LATE_MOVE_REDUCTION_DIVISOR;
+
+    reduction as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ai::transposition_table::Bound,
+        common::{board_utils::get_position_from_notation, piece_move::PieceMove},
+        game::board::Board,
+    };
+
+    use super::{get_extension, get_late_move_reduction, AIPlayer, SearchInfo, SearchLimits, ASPIRATION_WINDOW};
+
+    fn get_move(board: &mut Board, from: &str, to: &str) -> PieceMove {
+        let from = get_position_from_notation(from).unwrap();
+        let to = get_position_from_notation(to).unwrap();
+
+        board
+            .get_pieces()
+            .iter()
+            .flat_map(|piece| piece.get_moves_clone())
+            .find(|piece_move| piece_move.get_from_position() == from && piece_move.get_to_position() == to)
+            .unwrap()
+    }
+
+    fn get_best_move(fen: &str, depth: u8) -> (i8, i8) {
+        let mut board = Board::new();
+
+        board.load_position(fen).unwrap();
+
+        let limits = SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        };
+
+        let (_, _, best_move) = AIPlayer::new().search(&mut board, &limits, |_| {});
+
+        (best_move.get_from_position(), best_move.get_to_position())
+    }
+
+    #[test]
+    fn test_quiescence_resolves_captures_at_the_horizon() {
+        let queen = get_position_from_notation("d2").unwrap();
+        let pawn = get_position_from_notation("d5").unwrap();
+
+        // The pawn is defended, taking it at the last ply loses the queen
+        assert_ne!(get_best_move("4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1", 1), (queen, pawn));
+
+        assert_eq!(get_best_move("4k3/8/8/3p4/8/8/3Q4/4K3 w - - 0 1", 1), (queen, pawn));
+    }
+
+    #[test]
+    fn test_mate_is_found_at_every_depth() {
+        let rook = get_position_from_notation("a1").unwrap();
+        let mate = get_position_from_notation("a8").unwrap();
+
+        for depth in 1..=4 {
+            assert_eq!(get_best_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", depth), (rook, mate), "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn test_aspiration_windows_report_researches() {
+        let mut board = Board::new();
+
+        // The pawn queens out of reach of the black king. The third iteration sees the new
+        // queen above the window, the fourth one loses sight of it and falls below.
+        board.load_position("8/8/8/5P2/8/8/k7/4K3 w - - 0 1").unwrap();
+
+        let limits = SearchLimits {
+            depth: Some(4),
+            ..Default::default()
+        };
+
+        let mut infos: Vec<SearchInfo> = Vec::new();
+
+        AIPlayer::new().search(&mut board, &limits, |info| infos.push(info.clone()));
+
+        let completed: Vec<&SearchInfo> = infos.iter().filter(|info| info.bound == Bound::Exact).collect();
+
+        assert_eq!(completed.iter().map(|info| info.depth).collect::<Vec<u8>>(), vec![1, 2, 3, 4]);
+
+        let fail_high = infos.iter().find(|info| info.bound == Bound::Lower).unwrap();
+        let fail_low = infos.iter().find(|info| info.bound == Bound::Upper).unwrap();
+
+        assert_eq!((fail_high.depth, fail_high.researches), (3, 1));
+        assert_eq!((fail_low.depth, fail_low.researches), (4, 1));
+
+        // Outside the window around the score of the previous iteration
+        assert!(fail_high.score >= completed[1].score + ASPIRATION_WINDOW);
+        assert!(fail_low.score <= completed[2].score - ASPIRATION_WINDOW);
+
+        // A failed window is always searched again at the same depth
+        for (info, next_info) in infos.iter().zip(infos.iter().skip(1)) {
+            if info.bound != Bound::Exact {
+                assert_eq!(next_info.depth, info.depth);
+                assert_eq!(next_info.researches, info.researches + (next_info.bound != Bound::Exact) as u32);
+            }
+        }
+
+        assert!(completed[3].researches > 0);
+    }
+
+    #[test]
+    fn test_late_move_reduction() {
+        assert_eq!(get_late_move_reduction(3, 4), 1);
+        assert_eq!(get_late_move_reduction(6, 20), 3);
+
+        // Deeper searches and later moves are never reduced less
+        for depth in 3..20 {
+            for move_index in 4..60 {
+                assert!(get_late_move_reduction(depth + 1, move_index) >= get_late_move_reduction(depth, move_index));
+                assert!(get_late_move_reduction(depth, move_index + 1) >= get_late_move_reduction(depth, move_index));
+            }
+        }
+    }
+
+    #[test]
+    fn test_extensions() {
+        let mut board = Board::new();
+
+        // Extension of the move once it is played, like the search gets it
+        let get_played_extension = |board: &mut Board, piece_move: &PieceMove, single_reply: bool| {
+            board.move_piece(piece_move).unwrap();
+
+            let extension = get_extension(board, piece_move, board.is_side_to_move_in_check(), single_reply);
+
+            board.undo_last_move();
+
+            extension
+        };
+
+        board.load_position("4k3/8/3p2P1/4p3/3P4/8/8/R3K3 w - - 0 1").unwrap();
+
+        let check = get_move(&mut board, "a1", "a8");
+        let seventh_rank = get_move(&mut board, "g6", "g7");
+        let capture = get_move(&mut board, "d4", "e5");
+        let quiet = get_move(&mut board, "a1", "a2");
+
+        assert_eq!(get_played_extension(&mut board, &check, false), 1);
+        assert_eq!(get_played_extension(&mut board, &seventh_rank, false), 1);
+        assert_eq!(get_played_extension(&mut board, &capture, false), 0);
+        assert_eq!(get_played_extension(&mut board, &quiet, false), 0);
+        assert_eq!(get_played_extension(&mut board, &quiet, true), 1);
+
+        board.move_piece(&capture).unwrap();
+
+        let recapture = get_move(&mut board, "d6", "e5");
+
+        assert_eq!(get_played_extension(&mut board, &recapture, false), 1);
+
+        // The knight uncovers the rook
+        board.load_position("4k3/8/8/8/4N3/8/8/4R1K1 w - - 0 1").unwrap();
+
+        let discovered_check = get_move(&mut board, "e4", "c3");
+
+        assert_eq!(get_played_extension(&mut board, &discovered_check, false), 1);
+
+        // Castling puts the rook in front of the king
+        board.load_position("5k2/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+
+        let castling_check = get_move(&mut board, "e1", "g1");
+
+        assert_eq!(get_played_extension(&mut board, &castling_check, false), 1);
+    }
+
+    #[test]
+    fn test_mate_moves() {
+        let mut board = Board::new();
+
+        board.load_position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+
+        for depth in 1..=3 {
+            let limits = SearchLimits {
+                depth: Some(depth),
+                ..Default::default()
+            };
+
+            let mut infos: Vec<SearchInfo> = Vec::new();
+
+            AIPlayer::new().search(&mut board, &limits, |info| infos.push(info.clone()));
+
+            assert_eq!(infos.last().unwrap().get_mate_moves(), Some(1), "depth {}", depth);
+        }
+
+        let info = SearchInfo {
+            bound: Bound::Exact,
+            depth: 4,
+            elapsed: Default::default(),
+            nodes: 0,
+            pv: Vec::new(),
+            researches: 0,
+            score: 35.0,
+            table_entries: 0,
+            table_hits: 0,
+            table_size_kb: 0,
+        };
+
+        assert_eq!(info.get_mate_moves(), None);
+    }
+}
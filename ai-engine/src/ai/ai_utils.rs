@@ -1,17 +1,38 @@
 use crate::{
     common::{
-        board_utils::{get_position_column, get_position_rank}, contants::{BISHOP_WORTH, EMPTY_PIECE, KING_WORTH, PAWN_WORTH, QUEEN_WORTH, ROOK_WORTH}, enums::PieceType, piece::Piece, piece_move::PieceMove, piece_utils::{get_piece_type, get_piece_worth, get_promotion_options, is_white_piece}
+        board_utils::{get_position_column, get_position_rank}, contants::{BISHOP_WORTH, EMPTY_PIECE, KING_WORTH, PAWN_WORTH, QUEEN_WORTH, ROOK_WORTH}, enums::PieceType, piece::Piece, piece_move::PieceMove, piece_utils::{get_piece_type, get_piece_value, get_piece_worth, get_promotion_options, is_white_piece}
+    },
+    game::{
+        bitboard::{
+            get_bishop_attacks, get_king_attacks, get_knight_attacks, get_pawn_attacks, get_rook_attacks,
+            get_square_bit, pop_first_position, Bitboard, EMPTY_BITBOARD,
+        },
+        board::Board,
+        board_state::{get_en_passant_capture_position, BoardState},
+        move_generator_helper::get_adjacent_position,
     },
-    game::{board::Board, board_state::BoardState, move_generator_helper::get_adjacent_position},
 };
 
-use super::constants::{
-    BLACK_BISHOP_SQUARE_TABLE, BLACK_KING_SQUARE_TABLE_END_GAME,
-    BLACK_KING_SQUARE_TABLE_MIDDLE_GAME, BLACK_KNIGHT_SQUARE_TABLE, BLACK_PAWN_SQUARE_TABLE,
-    BLACK_ROOK_SQUARE_TABLE, END_GAME_PIECES_THRESHOLD, QUEEN_SQUARE_TABLE,
-    WHITE_BISHOP_SQUARE_TABLE, WHITE_KING_SQUARE_TABLE_END_GAME,
-    WHITE_KING_SQUARE_TABLE_MIDDLE_GAME, WHITE_KNIGHT_SQUARE_TABLE, WHITE_PAWN_SQUARE_TABLE,
-    WHITE_ROOK_SQUARE_TABLE,
+// Cheapest pieces first, the order attackers join an exchange
+const EXCHANGE_PIECE_TYPES: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+use super::{
+    constants::{
+        BLACK_BISHOP_SQUARE_TABLE, BLACK_KING_SQUARE_TABLE_END_GAME,
+        BLACK_KING_SQUARE_TABLE_MIDDLE_GAME, BLACK_KNIGHT_SQUARE_TABLE, BLACK_PAWN_SQUARE_TABLE,
+        BLACK_ROOK_SQUARE_TABLE, COUNTERMOVE_BONUS, END_GAME_PIECES_THRESHOLD, HISTORY_MOVE_BONUS,
+        KILLER_MOVE_BONUS, MAX_HISTORY, QUEEN_SQUARE_TABLE, WHITE_BISHOP_SQUARE_TABLE,
+        WHITE_KING_SQUARE_TABLE_END_GAME, WHITE_KING_SQUARE_TABLE_MIDDLE_GAME,
+        WHITE_KNIGHT_SQUARE_TABLE, WHITE_PAWN_SQUARE_TABLE, WHITE_ROOK_SQUARE_TABLE,
+    },
+    search_context::SearchContext,
 };
 
 pub fn get_sorted_moves(
@@ -19,32 +40,28 @@ pub fn get_sorted_moves(
     board: &Board,
     max: bool,
     pieces: &[Piece],
+    context: Option<&SearchContext>,
+    ply: u8,
 ) -> Vec<PieceMove> {
-    let (mut moves, attacked_positions) = get_friendly_moves_and_attacked_positions(pieces, board);
+    let mut moves = get_friendly_moves(pieces, board);
 
     let board_state = board.get_state_reference();
 
     let end_game = is_end_game(pieces);
 
+    let previous_move = board.get_move_history().last();
+
     moves.iter_mut().for_each(|_move| {
         let moving_piece = _move.get_piece_value();
 
-        if _move.is_capture() {
-            let target_piece = board_state.get_piece(_move.get_to_position());
-
-            _move.sum_to_move_worth(
-                (get_piece_worth(target_piece)) - get_piece_worth(moving_piece),
-            );
-        }
+        // Material won or lost once the exchanges on the target position are over, quiet
+        // moves to defended positions lose the moving piece
+        _move.sum_to_move_worth(get_static_exchange_value(board_state, _move));
 
         if _move.is_promotion() {
             _move.sum_to_move_worth(_move.get_promotion_value() as i32);
         }
 
-        if attacked_positions.contains(&_move.get_to_position()) {
-            _move.sum_to_move_worth(-get_piece_worth(moving_piece))
-        }
-
         _move.sum_to_move_worth(get_position_value(
             _move.get_to_position(),
             _move.get_piece_value(),
@@ -55,9 +72,18 @@ pub fn get_sorted_moves(
         if end_game && get_piece_type(moving_piece) == PieceType::King {
             _move.sum_to_move_worth(get_end_game_move_worth(board.clone(), max, _move));
         }
-    });
 
-    // TODO order also based on the hashmap with previous generated states
+        // Quiet moves all look alike statically, the search tells which ones were good
+        if let Some(context) = context.filter(|_| !_move.is_capture() && !_move.is_promotion()) {
+            if context.is_killer(ply, _move) {
+                _move.sum_to_move_worth(KILLER_MOVE_BONUS);
+            } else if previous_move.is_some_and(|previous_move| context.is_countermove(previous_move, _move)) {
+                _move.sum_to_move_worth(COUNTERMOVE_BONUS);
+            }
+
+            _move.sum_to_move_worth(context.get_history(_move) * HISTORY_MOVE_BONUS / MAX_HISTORY);
+        }
+    });
 
     if max {
         moves.sort_by_key(|k| std::cmp::Reverse(k.get_move_worth()));
@@ -68,13 +94,11 @@ pub fn get_sorted_moves(
     if best_move.is_some() {
         let best_move = best_move.clone().unwrap();
 
-        for (i, _move) in moves.iter().enumerate() {
-            if _move.eq(&best_move) {
-                moves.remove(i);
-                moves.insert(0, best_move);
+        // The worth stored with the move depends on the context it was sorted with
+        if let Some(i) = moves.iter().position(|_move| _move.is_same_move(&best_move)) {
+            let best_move = moves.remove(i);
 
-                break;
-            }
+            moves.insert(0, best_move);
         }
     }
 
@@ -110,7 +134,7 @@ fn get_end_game_move_worth(mut board: Board, max: bool, piece_move: &PieceMove)
     let opponent_king_dst_from_center =
         opponent_king_dst_to_center_file + opponent_king_dst_to_center_rank;
 
-    evaluation += opponent_king_dst_from_center as f32;
+    evaluation += opponent_king_dst_from_center;
 
     let friendly_king_rank = get_position_rank(friendly_king_position) as f32;
     let friendly_king_file = get_position_column(friendly_king_position) as f32;
@@ -120,34 +144,21 @@ fn get_end_game_move_worth(mut board: Board, max: bool, piece_move: &PieceMove)
 
     let dst_between_kings = dst_between_kings_file + dst_between_kings_rank;
 
-    evaluation += 14.0 - (dst_between_kings as f32);
+    evaluation += 14.0 - dst_between_kings;
 
-    return (evaluation
+    (evaluation
         * 10.0
         * calculate_end_game_weight(&board.get_pieces())
-        * if max { 1.0 } else { -1.0 }) as i32;
+        * if max { 1.0 } else { -1.0 }) as i32
 }
 
-fn get_friendly_moves_and_attacked_positions(
-    pieces: &[Piece],
-    board: &Board,
-) -> (Vec<PieceMove>, Vec<i8>) {
+fn get_friendly_moves(pieces: &[Piece], board: &Board) -> Vec<PieceMove> {
     let mut moves: Vec<PieceMove> = pieces
         .iter()
         .filter(|piece| piece.is_white() == board.is_white_move())
         .flat_map(|piece| piece.get_moves_clone())
         .collect();
 
-    let attacked_positions: Vec<i8> = pieces
-        .iter()
-        .filter(|piece| {
-            piece.is_white() != board.is_white_move()
-                && get_piece_type(piece.get_value()) != PieceType::Pawn
-        })
-        .flat_map(|piece| piece.get_moves_reference())
-        .map(|_move| _move.get_to_position())
-        .collect();
-
     let mut promotion_moves: Vec<PieceMove> = Vec::with_capacity(4);
 
     moves
@@ -168,7 +179,7 @@ fn get_friendly_moves_and_attacked_positions(
         moves.extend(promotion_moves);
     }
 
-    (moves, attacked_positions)
+    moves
 }
 
 fn get_position_value(position: i8, piece_value: u8, end_game: bool, white_piece: bool) -> f32 {
@@ -247,6 +258,11 @@ pub fn get_board_value(board: &mut Board, max: bool, pieces: &[Piece]) -> f32 {
         };
     }
 
+    // Neither side is able to force a mate
+    if board.has_insufficient_material() || board.has_drawish_material() {
+        return 0.0;
+    }
+
     // The evaluation
     // f(p) = 200(K-K') -> always 0 since the two kings are always present
     //         + 9(Q-Q')
@@ -255,11 +271,13 @@ pub fn get_board_value(board: &mut Board, max: bool, pieces: &[Piece]) -> f32 {
     //         + 1(P-P')
     //         - 0.5(D-D' + S-S' + I-I')
     //         + 0.1(M-M') + ...
+    //         - H
     //
     // ' means the opponent score
     // KQRBNP = number of kings, queens, rooks, bishops, knights and pawns
     // D,S,I = doubled, blocked and isolated pawns
     // M = Mobility (the number of legal moves)
+    // H = material lost to a double attack, every hanging piece but the most valuable one
 
     // let mut k: f32 = 0.0;
     let mut q: f32 = 0.0;
@@ -273,6 +291,9 @@ pub fn get_board_value(board: &mut Board, max: bool, pieces: &[Piece]) -> f32 {
     let mut i: f32 = 0.0;
     let mut m: f32 = 0.0;
 
+    // Material of the side to move the opponent can win
+    let mut threats: Vec<i32> = Vec::new();
+
     let board_state = board.get_state_reference();
 
     let mut pst_score: f32 = 0.0;
@@ -325,13 +346,27 @@ pub fn get_board_value(board: &mut Board, max: bool, pieces: &[Piece]) -> f32 {
             _ => (),
         }
 
+        if factor > 0.0 && piece_type != PieceType::King {
+            let threat = get_threat_value(board_state, piece.get_position());
+
+            if threat > 0 {
+                threats.push(threat);
+            }
+        }
+
         m += piece.get_moves_reference().len() as f32 * factor;
     }
 
+    // Only one of the hanging pieces can be saved with the next move, the others are lost
+    threats.sort_by_key(|threat| std::cmp::Reverse(*threat));
+
+    let h: f32 = threats.iter().skip(1).sum::<i32>() as f32;
+
     let score = (QUEEN_WORTH * q) + (ROOK_WORTH * r) + (BISHOP_WORTH * (b + n)) + (PAWN_WORTH * p)
         - ((d + s + i) / 2.0)
         + (m / 10.0)
-        + pst_score;
+        + pst_score
+        - h;
 
     score * if max { 1.0 } else { -1.0 }
 }
@@ -431,3 +466,284 @@ pub fn is_doubled_pawn(board_state: &BoardState, position: i8, white_piece: bool
 
     false
 }
+
+// Pieces of both sides attacking the position, through the occupancy given
+fn get_attackers(board_state: &BoardState, position: i8, occupancy: Bitboard) -> Bitboard {
+    let pieces = |piece_type: PieceType| {
+        board_state.get_piece_bitboard(get_piece_value(piece_type, true))
+            | board_state.get_piece_bitboard(get_piece_value(piece_type, false))
+    };
+
+    let diagonal = pieces(PieceType::Bishop) | pieces(PieceType::Queen);
+    let straight = pieces(PieceType::Rook) | pieces(PieceType::Queen);
+
+    let pawns = (get_pawn_attacks(position, false) & board_state.get_piece_bitboard(get_piece_value(PieceType::Pawn, true)))
+        | (get_pawn_attacks(position, true) & board_state.get_piece_bitboard(get_piece_value(PieceType::Pawn, false)));
+
+    (pawns
+        | (get_knight_attacks(position) & pieces(PieceType::Knight))
+        | (get_king_attacks(position) & pieces(PieceType::King))
+        | (get_bishop_attacks(position, occupancy) & diagonal)
+        | (get_rook_attacks(position, occupancy) & straight))
+        & occupancy
+}
+
+/// Material won by the side making the capture once every piece attacking the target
+/// position, cheapest first, took part in the exchange. Each side can stop capturing
+/// whenever going on would lose material, so a capture of a defended piece only scores
+/// well when the attacker is worth less than what it takes. Attackers behind the ones
+/// that already captured join in as the position opens up.
+pub fn get_static_exchange_value(board_state: &BoardState, piece_move: &PieceMove) -> i32 {
+    let to_position = piece_move.get_to_position();
+    let moving_piece = piece_move.get_piece_value();
+
+    let mut occupancy = board_state.get_occupancy() & !get_square_bit(piece_move.get_from_position());
+
+    let mut first_gain = get_piece_worth(board_state.get_piece(to_position));
+
+    if piece_move.is_en_passant() {
+        occupancy &= !get_square_bit(get_en_passant_capture_position(moving_piece, to_position));
+
+        first_gain = PAWN_WORTH as i32;
+    }
+
+    // The piece standing on the target position, the next one to be taken
+    let mut target_worth = get_piece_worth(moving_piece);
+
+    if piece_move.is_promotion() {
+        first_gain += get_piece_worth(piece_move.get_promotion_value()) - PAWN_WORTH as i32;
+
+        target_worth = get_piece_worth(piece_move.get_promotion_value());
+    }
+
+    get_exchange_value(board_state, to_position, occupancy, first_gain, target_worth, !is_white_piece(moving_piece))
+}
+
+/// Material the opponent wins by taking the piece on the position with its cheapest
+/// attacker first, zero when the piece is safe
+pub fn get_threat_value(board_state: &BoardState, position: i8) -> i32 {
+    let piece = board_state.get_piece(position);
+
+    let white = !is_white_piece(piece);
+
+    let occupancy = board_state.get_occupancy();
+
+    let Some(attacker_position) = get_cheapest_attacker(board_state, position, occupancy, white) else {
+        return 0;
+    };
+
+    let attacker = board_state.get_piece(attacker_position);
+
+    let exchange_value = get_exchange_value(
+        board_state,
+        position,
+        occupancy & !get_square_bit(attacker_position),
+        get_piece_worth(piece),
+        get_piece_worth(attacker),
+        !white,
+    );
+
+    exchange_value.max(0)
+}
+
+fn get_cheapest_attacker(board_state: &BoardState, position: i8, occupancy: Bitboard, white: bool) -> Option<i8> {
+    let attackers = get_attackers(board_state, position, occupancy) & board_state.get_color_occupancy(white);
+
+    EXCHANGE_PIECE_TYPES
+        .iter()
+        .map(|piece_type| attackers & board_state.get_piece_bitboard(get_piece_value(*piece_type, white)))
+        .find(|attackers| *attackers != EMPTY_BITBOARD)
+        .map(|mut attackers| pop_first_position(&mut attackers))
+}
+
+// Swap algorithm over the captures on the position after the first one, `white` is the
+// side to capture next
+fn get_exchange_value(
+    board_state: &BoardState,
+    position: i8,
+    occupancy: Bitboard,
+    first_gain: i32,
+    target_worth: i32,
+    white: bool,
+) -> i32 {
+    let mut occupancy = occupancy;
+    let mut target_worth = target_worth;
+    let mut white = white;
+
+    let mut gains = Vec::with_capacity(32);
+
+    gains.push(first_gain);
+
+    while let Some(attacker_position) = get_cheapest_attacker(board_state, position, occupancy, white) {
+        gains.push(target_worth - gains[gains.len() - 1]);
+
+        target_worth = get_piece_worth(board_state.get_piece(attacker_position));
+
+        occupancy &= !get_square_bit(attacker_position);
+
+        white = !white;
+    }
+
+    // Walks the exchange back, each side only captures when it pays off
+    while gains.len() > 1 {
+        let last_gain = gains.pop().unwrap_or(0);
+
+        let previous_gain = gains.len() - 1;
+
+        gains[previous_gain] = -(-gains[previous_gain]).max(last_gain);
+    }
+
+    gains[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ai::{constants::MAX_HISTORY, search_context::SearchContext},
+        common::{board_utils::get_position_from_notation, piece_move::PieceMove},
+        game::board::Board,
+    };
+
+    use super::{get_sorted_moves, get_static_exchange_value, get_threat_value};
+
+    fn get_move_index(moves: &[PieceMove], from: &str, to: &str) -> usize {
+        let from = get_position_from_notation(from).unwrap();
+        let to = get_position_from_notation(to).unwrap();
+
+        moves
+            .iter()
+            .position(|_move| _move.get_from_position() == from && _move.get_to_position() == to)
+            .unwrap()
+    }
+
+    fn get_exchange_value(fen: &str, from: &str, to: &str) -> i32 {
+        let mut board = Board::new();
+
+        board.load_position(fen).unwrap();
+
+        let from_position = get_position_from_notation(from).unwrap();
+        let to_position = get_position_from_notation(to).unwrap();
+
+        let piece_move = board
+            .get_legal_moves()
+            .into_iter()
+            .find(|_move| _move.get_from_position() == from_position && _move.get_to_position() == to_position)
+            .unwrap();
+
+        get_static_exchange_value(board.get_state_reference(), &piece_move)
+    }
+
+    #[test]
+    fn test_static_exchange_value() {
+        // Undefended and defended pawns
+        assert_eq!(get_exchange_value("4k3/8/8/3p4/8/8/3Q4/4K3 w - - 0 1", "d2", "d5"), 100);
+        assert_eq!(get_exchange_value("4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1", "d2", "d5"), -800);
+
+        // The rook behind the first one joins the exchange once it has captured
+        assert_eq!(get_exchange_value("4k3/3r4/8/3n4/8/3R4/3R4/4K3 w - - 0 1", "d3", "d5"), 300);
+        assert_eq!(get_exchange_value("3rk3/3r4/8/3n4/8/8/3R4/4K3 w - - 0 1", "d2", "d5"), -200);
+
+        // Pawns capture first, also en passant
+        assert_eq!(get_exchange_value("4k3/8/2b5/3n4/4P3/8/8/4K3 w - - 0 1", "e4", "d5"), 200);
+        assert_eq!(get_exchange_value("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5", "d6"), 100);
+    }
+
+    #[test]
+    fn test_threat_value() {
+        let mut board = Board::new();
+
+        // The knight is defended by a pawn, the rook can't take it
+        board.load_position("4k3/8/8/3r4/8/3N4/2P5/4K3 w - - 0 1").unwrap();
+
+        assert_eq!(get_threat_value(board.get_state_reference(), get_position_from_notation("d3").unwrap()), 0);
+
+        // A pawn takes the knight, the recapture only wins the pawn back
+        board.load_position("4k3/8/8/4p3/3N4/2P5/8/4K3 w - - 0 1").unwrap();
+
+        assert_eq!(get_threat_value(board.get_state_reference(), get_position_from_notation("d4").unwrap()), 200);
+    }
+
+    #[test]
+    fn test_sorted_moves_prefer_winning_captures() {
+        let mut board = Board::new();
+
+        board.load_position("4k3/8/2p4n/3p4/8/8/3Q4/4K3 w - - 0 1").unwrap();
+
+        let pieces = board.get_pieces();
+
+        let moves = get_sorted_moves(&None, &board, true, &pieces, None, 0);
+
+        assert_eq!(moves[0].get_to_position(), get_position_from_notation("h6").unwrap());
+
+        // Taking the defended pawn loses the queen
+        let pawn_capture = moves
+            .iter()
+            .position(|_move| _move.get_to_position() == get_position_from_notation("d5").unwrap())
+            .unwrap();
+
+        assert!(pawn_capture > moves.len() / 2);
+    }
+
+    #[test]
+    fn test_sorted_moves_use_the_search_context() {
+        let mut board = Board::new();
+
+        board
+            .load_position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            .unwrap();
+
+        let pieces = board.get_pieces();
+
+        let moves = get_sorted_moves(&None, &board, true, &pieces, None, 0);
+
+        let killer = moves[get_move_index(&moves, "a2", "a3")].clone();
+        let good_history = moves[get_move_index(&moves, "h2", "h3")].clone();
+
+        let mut context = SearchContext::new();
+
+        context.add_killer(2, &killer);
+        context.update_history(&good_history, MAX_HISTORY);
+
+        let ordered_moves = get_sorted_moves(&None, &board, true, &pieces, Some(&context), 2);
+
+        assert_eq!(get_move_index(&ordered_moves, "a2", "a3"), 0);
+        assert!(get_move_index(&ordered_moves, "h2", "h3") < get_move_index(&moves, "h2", "h3"));
+
+        // Killers are kept by ply
+        let other_ply_moves = get_sorted_moves(&None, &board, true, &pieces, Some(&context), 3);
+
+        assert_ne!(get_move_index(&other_ply_moves, "a2", "a3"), 0);
+    }
+
+    #[test]
+    fn test_sorted_moves_put_the_hash_move_first() {
+        let mut board = Board::new();
+
+        board
+            .load_position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            .unwrap();
+
+        let pieces = board.get_pieces();
+
+        let moves = get_sorted_moves(&None, &board, true, &pieces, None, 0);
+
+        let mut context = SearchContext::new();
+
+        context.add_killer(2, &moves[get_move_index(&moves, "a2", "a3")]);
+
+        // Stored in the table with the killer bonus in its worth
+        let ordered_moves = get_sorted_moves(&None, &board, true, &pieces, Some(&context), 2);
+
+        let hash_move = ordered_moves[get_move_index(&ordered_moves, "a2", "a3")].clone();
+
+        // Another killer and history by the next visit
+        let mut context = SearchContext::new();
+
+        context.add_killer(2, &moves[get_move_index(&moves, "h2", "h3")]);
+        context.update_history(&moves[get_move_index(&moves, "g2", "g3")], MAX_HISTORY);
+
+        let revisited_moves = get_sorted_moves(&Some(hash_move), &board, true, &pieces, Some(&context), 2);
+
+        assert_eq!(get_move_index(&revisited_moves, "a2", "a3"), 0);
+    }
+}
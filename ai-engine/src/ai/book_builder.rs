@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use crate::{
+    common::fen_error::FenError,
+    game::{board::Board, pgn::PgnGame, san::get_san_notation},
+};
+
+use super::opening_book::{encode_book_move, BookEntry, OpeningBook};
+
+pub const DEFAULT_BOOK_MAX_PLY: usize = 16;
+
+/// Games taken into the book
+#[derive(Debug, Clone)]
+pub struct BookBuilderOptions {
+    pub max_ply: usize,
+    // Both players must be rated at least this much, unrated games are left out
+    pub min_rating: Option<u32>,
+    // Accepted `Result` tags, any result when empty
+    pub results: Vec<String>,
+}
+
+impl Default for BookBuilderOptions {
+    fn default() -> Self {
+        BookBuilderOptions {
+            max_ply: DEFAULT_BOOK_MAX_PLY,
+            min_rating: None,
+            results: Vec::new(),
+        }
+    }
+}
+
+/// Outcomes of the games a move was played in, from the point of view of the side playing it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MoveStatistics {
+    pub draws: u32,
+    pub games: u32,
+    pub losses: u32,
+    pub wins: u32,
+}
+
+impl MoveStatistics {
+    fn add_result(&mut self, result: &str, white_move: bool) {
+        self.games += 1;
+
+        match (result, white_move) {
+            ("1-0", true) | ("0-1", false) => self.wins += 1,
+            ("1-0", false) | ("0-1", true) => self.losses += 1,
+            ("1/2-1/2", _) => self.draws += 1,
+            _ => {}
+        }
+    }
+
+    /// Half points scored with the move, the usual weight of Polyglot books
+    pub fn get_score(&self) -> u32 {
+        self.wins * 2 + self.draws
+    }
+}
+
+#[derive(Debug, Default)]
+struct BookTreeNode {
+    children: Vec<(String, BookTreeNode)>,
+    statistics: MoveStatistics,
+}
+
+impl BookTreeNode {
+    fn get_child(&mut self, notation: &str) -> &mut BookTreeNode {
+        let index = match self.children.iter().position(|(child_notation, _)| child_notation == notation) {
+            Some(index) => index,
+            None => {
+                self.children.push((notation.to_string(), BookTreeNode::default()));
+
+                self.children.len() - 1
+            }
+        };
+
+        &mut self.children[index].1
+    }
+
+    fn to_json(&self) -> Vec<Value> {
+        let mut children: Vec<&(String, BookTreeNode)> = self.children.iter().collect();
+
+        children.sort_by_key(|(_, child)| std::cmp::Reverse(child.statistics.games));
+
+        children
+            .iter()
+            .map(|(notation, child)| {
+                json!({
+                    "move": notation,
+                    "games": child.statistics.games,
+                    "wins": child.statistics.wins,
+                    "draws": child.statistics.draws,
+                    "losses": child.statistics.losses,
+                    "children": child.to_json(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Aggregates the opening moves of PGN games into a Polyglot book
+pub struct BookBuilder {
+    games_added: usize,
+    games_skipped: usize,
+    options: BookBuilderOptions,
+    // Statistics of each move by the Polyglot hash of the position it was played in
+    positions: HashMap<u64, HashMap<u16, MoveStatistics>>,
+    tree: BookTreeNode,
+}
+
+impl BookBuilder {
+    pub fn new(options: BookBuilderOptions) -> Self {
+        BookBuilder {
+            games_added: 0,
+            games_skipped: 0,
+            options,
+            positions: HashMap::new(),
+            tree: BookTreeNode::default(),
+        }
+    }
+
+    pub fn get_games_added(&self) -> usize {
+        self.games_added
+    }
+
+    pub fn get_games_skipped(&self) -> usize {
+        self.games_skipped
+    }
+
+    fn is_accepted(&self, game: &PgnGame) -> bool {
+        if !self.options.results.is_empty()
+            && !self.options.results.iter().any(|result| result == game.get_result())
+        {
+            return false;
+        }
+
+        let Some(min_rating) = self.options.min_rating else {
+            return true;
+        };
+
+        ["WhiteElo", "BlackElo"].iter().all(|tag| {
+            game.get_header(tag)
+                .and_then(|rating| rating.parse::<u32>().ok())
+                .is_some_and(|rating| rating >= min_rating)
+        })
+    }
+
+    /// Replays the first moves of the game, returns false when the filters leave it out
+    pub fn add_game(&mut self, game: &PgnGame) -> Result<bool, FenError> {
+        if !self.is_accepted(game) {
+            self.games_skipped += 1;
+
+            return Ok(false);
+        }
+
+        let mut board = Board::new();
+
+        board.load_position(game.get_initial_fen())?;
+
+        let result = game.get_result();
+
+        let mut node = &mut self.tree;
+
+        for piece_move in game.get_main_line().take(self.options.max_ply) {
+            let white_move = board.is_white_move();
+            let hash = board.get_zobrist_hash();
+            let notation = get_san_notation(&board, piece_move);
+
+            // Only moves that can be played count
+            if board.move_piece(piece_move).is_err() {
+                break;
+            }
+
+            self.positions
+                .entry(hash)
+                .or_default()
+                .entry(encode_book_move(piece_move))
+                .or_default()
+                .add_result(result, white_move);
+
+            node = node.get_child(&notation);
+
+            node.statistics.add_result(result, white_move);
+        }
+
+        self.games_added += 1;
+
+        Ok(true)
+    }
+
+    /// Moves that never scored a point are left out, the weights of a position
+    /// are scaled down together when they don't fit
+    pub fn build(&self) -> OpeningBook {
+        let mut entries = Vec::new();
+
+        for (&key, moves) in &self.positions {
+            let max_score = moves.values().map(MoveStatistics::get_score).max().unwrap_or(0);
+
+            let scale = (max_score as f64 / u16::MAX as f64).max(1.0);
+
+            for (&raw_move, statistics) in moves {
+                let weight = (statistics.get_score() as f64 / scale).round() as u16;
+
+                if weight > 0 {
+                    entries.push(BookEntry {
+                        key,
+                        learn: 0,
+                        raw_move,
+                        weight,
+                    });
+                }
+            }
+        }
+
+        OpeningBook::from_entries(entries)
+    }
+
+    /// Moves from the initial position of the games with their statistics,
+    /// transpositions are kept apart to follow the lines as they were played
+    pub fn to_json_tree(&self) -> Value {
+        json!({
+            "games": self.games_added,
+            "moves": self.tree.to_json(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{
+        ai::opening_book::{BookMode, OpeningBook},
+        common::board_utils::get_uci_move_notation,
+        game::{board::Board, move_parser::parse_move, pgn::parse_pgn},
+    };
+
+    use super::{BookBuilder, BookBuilderOptions};
+
+    const GAMES: &str = r#"[White "A"]
+[Black "B"]
+[WhiteElo "2400"]
+[BlackElo "2300"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 1-0
+
+[White "C"]
+[Black "D"]
+[WhiteElo "2200"]
+[BlackElo "2500"]
+[Result "1/2-1/2"]
+
+1. e4 c5 2. Nf3 d6 1/2-1/2
+
+[White "E"]
+[Black "F"]
+[Result "0-1"]
+
+1. d4 d5 2. c4 e6 0-1
+"#;
+
+    fn build(options: BookBuilderOptions) -> (BookBuilder, OpeningBook) {
+        let mut builder = BookBuilder::new(options);
+
+        for game in parse_pgn(GAMES).unwrap() {
+            builder.add_game(&game).unwrap();
+        }
+
+        let book = OpeningBook::from_bytes(&builder.build().to_bytes()).unwrap();
+
+        (builder, book)
+    }
+
+    fn book_move(book: &OpeningBook, moves: &[&str]) -> Option<String> {
+        let mut board = Board::new();
+
+        for notation in moves {
+            let piece_move = parse_move(&mut board, notation).unwrap();
+
+            board.move_piece(&piece_move).unwrap();
+        }
+
+        book.get_move(&mut board, BookMode::Best, &mut StdRng::seed_from_u64(16))
+            .map(|piece_move| get_uci_move_notation(&piece_move))
+    }
+
+    #[test]
+    fn test_build_book() {
+        let (builder, book) = build(BookBuilderOptions::default());
+
+        assert_eq!(builder.get_games_added(), 3);
+
+        let initial_hash = Board::new().get_zobrist_hash();
+
+        // e4 scored a win and a draw, d4 lost its only game
+        let entries = book.get_entries(initial_hash);
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].weight, 3);
+        assert_eq!(book_move(&book, &[]), Some(String::from("e2e4")));
+
+        // Black's best answer only drew, the one that lost is left out
+        assert_eq!(book_move(&book, &["e4"]), Some(String::from("c7c5")));
+        assert_eq!(book_move(&book, &["d4"]), Some(String::from("d7d5")));
+        assert_eq!(book_move(&book, &["e4", "e5", "Nf3", "Nc6"]), Some(String::from("f1b5")));
+
+        let tree = builder.to_json_tree();
+
+        assert_eq!(tree["moves"][0]["move"], "e4");
+        assert_eq!(tree["moves"][0]["games"], 2);
+        assert_eq!(tree["moves"][0]["children"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_filter_games() {
+        let (builder, book) = build(BookBuilderOptions {
+            max_ply: 2,
+            min_rating: Some(2250),
+            results: Vec::new(),
+        });
+
+        assert_eq!(builder.get_games_added(), 1);
+        assert_eq!(builder.get_games_skipped(), 2);
+        assert_eq!(book.len(), 1);
+
+        let (builder, book) = build(BookBuilderOptions {
+            results: vec![String::from("0-1")],
+            ..Default::default()
+        });
+
+        assert_eq!(builder.get_games_added(), 1);
+        assert_eq!(book_move(&book, &[]), None);
+        assert_eq!(book_move(&book, &["d4", "d5", "c4"]), Some(String::from("e7e6")));
+    }
+
+    #[test]
+    fn test_unplayable_moves_are_not_counted() {
+        let mut game = parse_pgn("[Result \"1-0\"]\n\n1. e4 e5 1-0").unwrap().remove(0);
+
+        // The moves were read from the initial position, there is no pawn to push here
+        game.set_header("FEN", "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+
+        let mut builder = BookBuilder::new(BookBuilderOptions::default());
+
+        builder.add_game(&game).unwrap();
+
+        assert!(builder.build().is_empty());
+        assert!(builder.to_json_tree()["moves"].as_array().unwrap().is_empty());
+    }
+}
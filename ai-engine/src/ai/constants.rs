@@ -141,4 +141,48 @@ pub const QUEEN_SQUARE_TABLE: [f32; 64] = [
     -20.0,-10.0,-10.0, -5.0, -5.0,-10.0,-10.0,-20.0,
 ];
 
-pub const END_GAME_PIECES_THRESHOLD: f32 = 4.0;
\ No newline at end of file
+pub const END_GAME_PIECES_THRESHOLD: f32 = 4.0;
+
+// Captures that can't bring the score back above alpha, even winning this much more,
+// are skipped by the quiescence search
+pub const QUIESCENCE_DELTA_MARGIN: f32 = 200.0;
+
+// Longest capture sequence searched past the horizon
+pub const MAX_QUIESCENCE_PLIES: u8 = 16;
+
+// Width of the null window proving a move is not better than the best one so far
+pub const NULL_WINDOW: f32 = 0.01;
+
+// Scores searched around the previous iteration's one, the window grows this many
+// times on each re-search and becomes unbounded past the largest one
+pub const ASPIRATION_WINDOW: f32 = 50.0;
+pub const ASPIRATION_WIDENING: f32 = 4.0;
+pub const MAX_ASPIRATION_WINDOW: f32 = 1000.0;
+
+// Quiet moves from this one on, in the sorted order, are searched with less depth.
+// The reduction grows with log(depth) * log(move index), these tune how fast
+pub const LATE_MOVE_INDEX: usize = 4;
+pub const LATE_MOVE_MIN_DEPTH: u8 = 3;
+pub const LATE_MOVE_REDUCTION_BASE: f32 = 0.75;
+pub const LATE_MOVE_REDUCTION_DIVISOR: f32 = 2.25;
+
+// Passing the turn is tried from this depth on, searched this many plies shallower plus
+// one more for every divisor plies of depth
+pub const NULL_MOVE_MIN_DEPTH: u8 = 3;
+pub const NULL_MOVE_REDUCTION: u8 = 2;
+pub const NULL_MOVE_DEPTH_DIVISOR: u8 = 6;
+
+// With this few pieces besides pawns and the king, passing could be the only good move
+// (zugzwang), so a null move cutoff is confirmed by a normal search first
+pub const NULL_MOVE_VERIFICATION_PIECES: usize = 2;
+
+// Plies a single line can be extended in total, checks, single replies, recaptures and
+// pawns reaching the seventh rank add one each until it runs out
+pub const MAX_SEARCH_EXTENSIONS: u8 = 4;
+
+// Quiet moves are ordered by these on top of their static worth: killers, the usual reply
+// to the opponent's last move, and the butterfly history scaled from its bounds to the bonus
+pub const KILLER_MOVE_BONUS: i32 = 80;
+pub const COUNTERMOVE_BONUS: i32 = 60;
+pub const HISTORY_MOVE_BONUS: i32 = 50;
+pub const MAX_HISTORY: i32 = 16384;
@@ -0,0 +1,689 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    common::{
+        contants::INVALID_BOARD_POSITION,
+        enums::PieceType,
+        piece_utils::{get_piece_type, get_piece_value, is_white_piece},
+        tablebase_error::TablebaseError,
+    },
+    game::bitboard::{
+        get_bishop_attacks, get_king_attacks, get_knight_attacks, get_pawn_attacks, get_queen_attacks,
+        get_rook_attacks, get_square_bit, Bitboard, EMPTY_BITBOARD,
+    },
+};
+
+use super::{
+    get_table_index, get_table_name, get_table_path, get_table_pieces, get_table_positions, get_table_size,
+    parse_table_name, Dtm, EndgameTable, MAX_MATE_PLIES, MAX_TABLE_PIECES,
+};
+
+// Marks the positions that can't be reached while the table is built, stored as draws
+const ILLEGAL_VALUE: u8 = u8::MAX;
+
+// Marks the positions with a move that doesn't lose, see `compute_values`
+const ESCAPE_PLIES: u8 = u8::MAX;
+
+const PROMOTION_TYPES: [PieceType; 4] = [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight];
+
+// Pieces besides the kings, in the order of the table names
+const TABLE_PIECE_TYPES: [PieceType; 5] =
+    [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight, PieceType::Pawn];
+
+/// Position reached by a legal move
+enum Successor {
+    // Same material, solved along with the position
+    Table,
+    // A capture or a promotion, result from another table
+    Converted(Dtm),
+}
+
+/// Names of every table with up to four pieces, like `KQK` or `KRKN`
+pub fn get_default_tables() -> Vec<String> {
+    let mut names = Vec::new();
+
+    let extra_pieces: Vec<Vec<PieceType>> = TABLE_PIECE_TYPES
+        .iter()
+        .enumerate()
+        .flat_map(|(index, first)| {
+            let pairs = TABLE_PIECE_TYPES[index..].iter().map(move |second| vec![*first, *second]);
+
+            std::iter::once(vec![*first]).chain(pairs)
+        })
+        .collect();
+
+    for pieces in &extra_pieces {
+        let white: Vec<u8> = pieces.iter().map(|piece_type| get_piece_value(*piece_type, true)).collect();
+
+        let mut sides = vec![Vec::new()];
+
+        // One piece for each side as well when a single piece was added
+        if pieces.len() == 1 {
+            sides.extend(TABLE_PIECE_TYPES.iter().map(|piece_type| vec![get_piece_value(*piece_type, false)]));
+        }
+
+        for black in sides {
+            let mut material = vec![get_piece_value(PieceType::King, true), get_piece_value(PieceType::King, false)];
+
+            material.extend(&white);
+            material.extend(black);
+
+            let name = get_table_name(&material);
+
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    names.sort_by_key(|name| name.len());
+
+    names
+}
+
+fn get_occupancy(positions: &[i8]) -> Bitboard {
+    positions
+        .iter()
+        .filter(|position| **position != INVALID_BOARD_POSITION)
+        .fold(EMPTY_BITBOARD, |occupancy, position| occupancy | get_square_bit(*position))
+}
+
+fn get_color_occupancy(pieces: &[u8], positions: &[i8], white: bool) -> Bitboard {
+    pieces
+        .iter()
+        .zip(positions)
+        .filter(|(piece, position)| is_white_piece(**piece) == white && **position != INVALID_BOARD_POSITION)
+        .fold(EMPTY_BITBOARD, |occupancy, (_, position)| occupancy | get_square_bit(*position))
+}
+
+fn get_attacks(piece: u8, position: i8, occupancy: Bitboard) -> Bitboard {
+    match get_piece_type(piece) {
+        PieceType::Bishop => get_bishop_attacks(position, occupancy),
+        PieceType::King => get_king_attacks(position),
+        PieceType::Knight => get_knight_attacks(position),
+        PieceType::Pawn => get_pawn_attacks(position, is_white_piece(piece)),
+        PieceType::Queen => get_queen_attacks(position, occupancy),
+        PieceType::Rook => get_rook_attacks(position, occupancy),
+        PieceType::Empty => EMPTY_BITBOARD,
+    }
+}
+
+// The pieces left out have been captured
+fn is_king_attacked(pieces: &[u8], positions: &[i8], white_king: bool) -> bool {
+    let occupancy = get_occupancy(positions);
+
+    let king = get_piece_value(PieceType::King, white_king);
+
+    let Some(king_position) = pieces.iter().position(|piece| *piece == king).map(|slot| positions[slot]) else {
+        return false;
+    };
+
+    pieces.iter().zip(positions).any(|(&piece, &position)| {
+        position != INVALID_BOARD_POSITION
+            && is_white_piece(piece) != white_king
+            && get_attacks(piece, position, occupancy) & get_square_bit(king_position) != 0
+    })
+}
+
+// First or eighth rank, pawns never stand there
+fn is_back_rank(position: i8) -> bool {
+    !(8..56).contains(&position)
+}
+
+fn is_legal_position(pieces: &[u8], positions: &[i8], white_move: bool) -> bool {
+    if get_occupancy(positions).count_ones() as usize != positions.len() {
+        return false;
+    }
+
+    let pawn_on_last_rank = pieces.iter().zip(positions).any(|(piece, position)| {
+        get_piece_type(*piece) == PieceType::Pawn && is_back_rank(*position)
+    });
+
+    !pawn_on_last_rank && !is_king_attacked(pieces, positions, !white_move)
+}
+
+/// Positions a pawn of the side can push to
+fn get_pawn_pushes(position: i8, white: bool, occupancy: Bitboard) -> Bitboard {
+    let step = if white { -8 } else { 8 };
+    let start_row = if white { 6 } else { 1 };
+
+    let single = position + step;
+
+    if occupancy & get_square_bit(single) != 0 {
+        return EMPTY_BITBOARD;
+    }
+
+    let double = single + step;
+
+    if position / 8 == start_row && occupancy & get_square_bit(double) == 0 {
+        return get_square_bit(single) | get_square_bit(double);
+    }
+
+    get_square_bit(single)
+}
+
+/// Positions the pawn of the side can have been pushed from, pawns never stand on
+/// the first rank
+fn get_pawn_unpushes(position: i8, white: bool, occupancy: Bitboard) -> Bitboard {
+    let step = if white { 8 } else { -8 };
+    let first_row = if white { 7 } else { 0 };
+    let double_row = if white { 4 } else { 3 };
+
+    let single = position + step;
+
+    if single / 8 == first_row || occupancy & get_square_bit(single) != 0 {
+        return EMPTY_BITBOARD;
+    }
+
+    let double = single + step;
+
+    if position / 8 == double_row && occupancy & get_square_bit(double) == 0 {
+        return get_square_bit(single) | get_square_bit(double);
+    }
+
+    get_square_bit(single)
+}
+
+/// Builds distance to mate tables by retrograde analysis, starting from the checkmates
+/// and walking the moves backwards. Captures and promotions lead to smaller or other
+/// tables, which are generated first and saved in the directory as well.
+pub struct TableGenerator {
+    directory: PathBuf,
+    tables: HashMap<String, EndgameTable>,
+}
+
+impl TableGenerator {
+    pub fn new(directory: &str) -> Self {
+        TableGenerator {
+            directory: PathBuf::from(directory),
+            tables: HashMap::new(),
+        }
+    }
+
+    /// Generates the table with the ones it depends on, the tables already in
+    /// the directory are read instead
+    pub fn generate(&mut self, name: &str) -> Result<&EndgameTable, TablebaseError> {
+        let pieces = parse_table_name(name)
+            .filter(|pieces| pieces.len() <= MAX_TABLE_PIECES)
+            .ok_or_else(|| TablebaseError::InvalidTable(name.to_string()))?;
+
+        let name = get_table_name(&pieces);
+
+        self.add_table(&pieces)?;
+
+        Ok(&self.tables[&name])
+    }
+
+    /// Frees the tables kept for the next ones, they are read back from the directory
+    pub fn release_tables(&mut self) {
+        self.tables.clear();
+    }
+
+    fn add_table(&mut self, pieces: &[u8]) -> Result<(), TablebaseError> {
+        let name = get_table_name(pieces);
+
+        if self.tables.contains_key(&name) {
+            return Ok(());
+        }
+
+        let path = get_table_path(&self.directory, &name);
+
+        if Path::new(&path).exists() {
+            self.tables.insert(name, EndgameTable::load(&path)?);
+
+            return Ok(());
+        }
+
+        for dependency in get_dependencies(pieces) {
+            self.add_table(&dependency)?;
+        }
+
+        let (_, table_pieces, _) = get_table_pieces(&pieces.iter().map(|piece| (*piece, 0)).collect::<Vec<_>>(), true);
+
+        let table_pieces: Vec<u8> = table_pieces.into_iter().map(|(piece, _)| piece).collect();
+
+        let table = EndgameTable::new(table_pieces.clone(), self.compute_values(&table_pieces));
+
+        table.save(&path)?;
+
+        self.tables.insert(name, table);
+
+        Ok(())
+    }
+
+    /// Result after a capture or a promotion, the table is always generated first
+    fn probe_converted(&self, pieces: &[(u8, i8)], white_move: bool) -> Dtm {
+        if pieces.len() == 2 {
+            return Dtm::Draw;
+        }
+
+        let (name, table_pieces, white_move) = get_table_pieces(pieces, white_move);
+
+        self.tables
+            .get(&name)
+            .map_or(Dtm::Draw, |table| table.get_dtm(&table_pieces, white_move))
+    }
+
+    fn get_successors(&self, pieces: &[u8], positions: &[i8], white_move: bool, successors: &mut Vec<Successor>) {
+        successors.clear();
+
+        let occupancy = get_occupancy(positions);
+        let own_occupancy = get_color_occupancy(pieces, positions, white_move);
+        let opponent_occupancy = occupancy & !own_occupancy;
+
+        for (slot, &piece) in pieces.iter().enumerate() {
+            if is_white_piece(piece) != white_move {
+                continue;
+            }
+
+            let from_position = positions[slot];
+
+            let pawn = get_piece_type(piece) == PieceType::Pawn;
+
+            let mut targets = if pawn {
+                get_pawn_pushes(from_position, white_move, occupancy)
+                    | (get_pawn_attacks(from_position, white_move) & opponent_occupancy)
+            } else {
+                get_attacks(piece, from_position, occupancy) & !own_occupancy
+            };
+
+            while targets != 0 {
+                let to_position = targets.trailing_zeros() as i8;
+
+                targets &= targets - 1;
+
+                let mut new_positions = [INVALID_BOARD_POSITION; MAX_TABLE_PIECES];
+
+                let new_positions = &mut new_positions[..positions.len()];
+
+                new_positions.copy_from_slice(positions);
+
+                let captured = positions.iter().position(|position| *position == to_position);
+
+                if let Some(captured) = captured {
+                    new_positions[captured] = INVALID_BOARD_POSITION;
+                }
+
+                new_positions[slot] = to_position;
+
+                if is_king_attacked(pieces, new_positions, white_move) {
+                    continue;
+                }
+
+                let promotion = pawn && is_back_rank(to_position);
+
+                if captured.is_none() && !promotion {
+                    successors.push(Successor::Table);
+
+                    continue;
+                }
+
+                let promotions: Vec<u8> = if promotion {
+                    PROMOTION_TYPES.iter().map(|piece_type| get_piece_value(*piece_type, white_move)).collect()
+                } else {
+                    vec![piece]
+                };
+
+                for promoted in promotions {
+                    let converted: Vec<(u8, i8)> = pieces
+                        .iter()
+                        .zip(new_positions.iter())
+                        .enumerate()
+                        .filter(|(_, (_, position))| **position != INVALID_BOARD_POSITION)
+                        .map(|(other, (other_piece, position))| {
+                            (if other == slot { promoted } else { *other_piece }, *position)
+                        })
+                        .collect();
+
+                    successors.push(Successor::Converted(self.probe_converted(&converted, !white_move)));
+                }
+            }
+        }
+    }
+
+    /// Distance to mate of every position. The checkmates are found first, then each
+    /// pass walks the moves back from the positions solved by the previous one: a move
+    /// into a lost position wins, and a position loses once all its moves are known to
+    /// lose. The results of captures and promotions are known from the start and are
+    /// taken in at the pass of their distance.
+    fn compute_values(&self, pieces: &[u8]) -> Vec<u8> {
+        let piece_count = pieces.len();
+        let size = get_table_size(piece_count);
+
+        let mut values = vec![Dtm::Draw.to_value(); size];
+
+        // Moves staying in the table not yet known to lose
+        let mut open_moves = vec![0u8; size];
+
+        // Longest loss through a capture or a promotion, unless one of them doesn't lose
+        let mut conversion_losses = vec![0u8; size];
+
+        // Positions solved through a conversion, by distance
+        let mut pending: Vec<Vec<u32>> = vec![Vec::new(); MAX_MATE_PLIES as usize + 2];
+
+        let mut solved = Vec::new();
+
+        let mut successors = Vec::new();
+
+        for index in 0..size {
+            let (positions, white_move) = get_table_positions(index, piece_count);
+            let positions = &positions[..piece_count];
+
+            if !is_legal_position(pieces, positions, white_move) {
+                values[index] = ILLEGAL_VALUE;
+
+                continue;
+            }
+
+            self.get_successors(pieces, positions, white_move, &mut successors);
+
+            if successors.is_empty() {
+                if is_king_attacked(pieces, positions, white_move) {
+                    values[index] = Dtm::Loss(0).to_value();
+
+                    solved.push(index as u32);
+                }
+
+                continue;
+            }
+
+            let mut moves = 0;
+            let mut conversion_loss = 0;
+            let mut conversion_win = None;
+
+            for successor in &successors {
+                match successor {
+                    Successor::Table => moves += 1,
+                    Successor::Converted(dtm) => match dtm.get_parent() {
+                        Dtm::Win(plies) => {
+                            conversion_loss = ESCAPE_PLIES;
+                            conversion_win = Some(conversion_win.map_or(plies, |win: u8| win.min(plies)));
+                        }
+                        Dtm::Draw => conversion_loss = ESCAPE_PLIES,
+                        Dtm::Loss(plies) if conversion_loss != ESCAPE_PLIES => {
+                            conversion_loss = conversion_loss.max(plies);
+                        }
+                        Dtm::Loss(_) => {}
+                    },
+                }
+            }
+
+            open_moves[index] = moves;
+            conversion_losses[index] = conversion_loss;
+
+            if let Some(plies) = conversion_win {
+                pending[plies as usize].push(index as u32);
+            } else if moves == 0 && conversion_loss != ESCAPE_PLIES {
+                pending[conversion_loss as usize].push(index as u32);
+            }
+        }
+
+        let mut predecessors = Vec::new();
+
+        for plies in 0..=MAX_MATE_PLIES {
+            for index in pending[plies as usize].drain(..) {
+                if values[index as usize] == Dtm::Draw.to_value() {
+                    values[index as usize] = plies + 1;
+
+                    solved.push(index);
+                }
+            }
+
+            if solved.is_empty() && pending[plies as usize..].iter().all(|indices| indices.is_empty()) {
+                break;
+            }
+
+            let mut next_solved = Vec::new();
+
+            let next_plies = plies + 1;
+
+            for index in solved.drain(..) {
+                let (positions, white_move) = get_table_positions(index as usize, piece_count);
+
+                get_predecessors(pieces, &positions[..piece_count], white_move, &mut predecessors);
+
+                for &predecessor in &predecessors {
+                    if values[predecessor] != Dtm::Draw.to_value() {
+                        continue;
+                    }
+
+                    // Moving into a lost position wins
+                    if plies % 2 == 0 {
+                        values[predecessor] = Dtm::Win(next_plies).to_value();
+
+                        next_solved.push(predecessor as u32);
+
+                        continue;
+                    }
+
+                    open_moves[predecessor] -= 1;
+
+                    if open_moves[predecessor] != 0 || conversion_losses[predecessor] == ESCAPE_PLIES {
+                        continue;
+                    }
+
+                    let loss_plies = next_plies.max(conversion_losses[predecessor]);
+
+                    if loss_plies == next_plies {
+                        values[predecessor] = Dtm::Loss(next_plies).to_value();
+
+                        next_solved.push(predecessor as u32);
+                    } else {
+                        pending[loss_plies as usize].push(predecessor as u32);
+                    }
+                }
+            }
+
+            if next_plies > MAX_MATE_PLIES {
+                break;
+            }
+
+            solved = next_solved;
+        }
+
+        for value in values.iter_mut() {
+            if *value == ILLEGAL_VALUE {
+                *value = Dtm::Draw.to_value();
+            }
+        }
+
+        values
+    }
+}
+
+/// Tables reached by a capture or a promotion
+fn get_dependencies(pieces: &[u8]) -> Vec<Vec<u8>> {
+    let mut dependencies = Vec::new();
+
+    for (slot, &piece) in pieces.iter().enumerate() {
+        let piece_type = get_piece_type(piece);
+
+        if piece_type == PieceType::King {
+            continue;
+        }
+
+        let mut captured = pieces.to_vec();
+
+        captured.remove(slot);
+
+        if captured.len() > 2 {
+            dependencies.push(captured);
+        }
+
+        if piece_type == PieceType::Pawn {
+            for promotion in PROMOTION_TYPES {
+                let mut promoted = pieces.to_vec();
+
+                promoted[slot] = get_piece_value(promotion, is_white_piece(piece));
+
+                dependencies.push(promoted);
+            }
+        }
+    }
+
+    dependencies
+}
+
+/// Indexes of the positions the side that just moved came from without capturing
+/// or promoting, the ones left illegal are filtered out by the caller
+fn get_predecessors(pieces: &[u8], positions: &[i8], white_move: bool, predecessors: &mut Vec<usize>) {
+    predecessors.clear();
+
+    let occupancy = get_occupancy(positions);
+
+    for (slot, &piece) in pieces.iter().enumerate() {
+        if is_white_piece(piece) == white_move {
+            continue;
+        }
+
+        let to_position = positions[slot];
+
+        let mut origins = if get_piece_type(piece) == PieceType::Pawn {
+            get_pawn_unpushes(to_position, !white_move, occupancy)
+        } else {
+            get_attacks(piece, to_position, occupancy) & !occupancy
+        };
+
+        let mut previous_positions = positions.to_vec();
+
+        while origins != 0 {
+            previous_positions[slot] = origins.trailing_zeros() as i8;
+
+            origins &= origins - 1;
+
+            predecessors.push(get_table_index(&previous_positions, !white_move));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::{
+        ai::endgame_tables::{get_table_positions, parse_table_name, Dtm, EndgameTables},
+        common::fen_utils::get_piece_fen,
+        game::board::Board,
+    };
+
+    use super::{get_default_tables, is_legal_position, TableGenerator};
+
+    // FEN of a table position, the pieces are given in the order of the table
+    fn get_fen(pieces: &[u8], positions: &[i8], white_move: bool) -> String {
+        let mut squares = [' '; 64];
+
+        for (piece, position) in pieces.iter().zip(positions) {
+            squares[*position as usize] = get_piece_fen(*piece);
+        }
+
+        let rows: Vec<String> = squares
+            .chunks(8)
+            .map(|row| {
+                let mut notation = String::new();
+                let mut empty = 0;
+
+                for square in row {
+                    if *square == ' ' {
+                        empty += 1;
+
+                        continue;
+                    }
+
+                    if empty > 0 {
+                        notation.push_str(&empty.to_string());
+
+                        empty = 0;
+                    }
+
+                    notation.push(*square);
+                }
+
+                if empty > 0 {
+                    notation.push_str(&empty.to_string());
+                }
+
+                notation
+            })
+            .collect();
+
+        format!("{} {} - - 0 1", rows.join("/"), if white_move { "w" } else { "b" })
+    }
+
+    #[test]
+    fn test_default_tables() {
+        let tables = get_default_tables();
+
+        assert_eq!(tables.len(), 35);
+        assert_eq!(&tables[..5], ["KQK", "KRK", "KBK", "KNK", "KPK"]);
+        assert!(tables.contains(&String::from("KBNK")));
+        assert!(tables.contains(&String::from("KQKR")));
+        assert!(!tables.contains(&String::from("KRKQ")));
+    }
+
+    #[test]
+    fn test_tables_follow_the_move_generator() {
+        let directory = env::temp_dir().join(format!("rustmate-generated-tables-{}", std::process::id()));
+
+        fs::create_dir_all(&directory).unwrap();
+
+        let mut generator = TableGenerator::new(directory.to_str().unwrap());
+
+        generator.generate("KPK").unwrap();
+
+        let pieces = parse_table_name("KPK").unwrap();
+
+        let tables = EndgameTables::load(directory.to_str().unwrap()).unwrap();
+
+        let mut checked = 0;
+
+        // Every result must be the best one of the legal moves of the board
+        for index in (0..2 << 18).step_by(331) {
+            let (positions, white_move) = get_table_positions(index, 3);
+
+            if !is_legal_position(&pieces, &positions[..3], white_move) {
+                continue;
+            }
+
+            let mut board = Board::new();
+
+            board.load_position(&get_fen(&pieces, &positions[..3], white_move)).unwrap();
+
+            let dtm = tables.probe(board.get_state_reference()).unwrap();
+
+            let root_moves = tables.probe_root(&mut board).unwrap();
+
+            let best = root_moves.iter().map(|(_, dtm)| *dtm).max_by_key(|dtm| dtm.get_rank());
+
+            let expected = match best {
+                Some(best) => best,
+                None if board.get_state_reference().get_winner() != 0 => Dtm::Loss(0),
+                None => Dtm::Draw,
+            };
+
+            assert_eq!(dtm, expected, "{}", board.get_state_reference().get_fen());
+
+            checked += 1;
+        }
+
+        assert!(checked > 1000);
+
+        // The king in front of its pawn wins, a rook pawn can't drive the king out of the corner
+        let probe = |fen: &str| {
+            let mut board = Board::new();
+
+            board.load_position(fen).unwrap();
+
+            tables.probe(board.get_state_reference())
+        };
+
+        assert!(matches!(probe("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Some(Dtm::Win(_))));
+        assert!(matches!(probe("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Some(Dtm::Loss(_))));
+        assert_eq!(probe("7k/8/8/8/8/8/7P/6K1 w - - 0 1"), Some(Dtm::Draw));
+        assert_eq!(probe("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), Some(Dtm::Draw));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
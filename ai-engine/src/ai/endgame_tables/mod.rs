@@ -0,0 +1,659 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{
+    common::{
+        contants::INVALID_BOARD_POSITION,
+        enums::PieceType,
+        piece_move::PieceMove,
+        piece_utils::{get_piece_type, get_piece_value, get_piece_worth, is_white_piece},
+        tablebase_error::TablebaseError,
+        tablebase_utils::{self, get_name_piece_type, get_table_files, NAME_PIECES},
+    },
+    game::{board::Board, board_state::BoardState, zobrist::get_en_passant_hash},
+};
+
+use super::syzygy::{get_tablebase_moves, TABLEBASE_WIN_SCORE};
+
+pub mod generator;
+
+/// Largest tables the generator builds, a four piece table already holds 2 * 64^4 positions
+pub const MAX_TABLE_PIECES: usize = 4;
+
+const TABLE_EXTENSION: &str = "rmtb";
+
+const TABLE_MAGIC: &[u8; 4] = b"RMTB";
+const TABLE_VERSION: u8 = 1;
+
+// Values store the plies to mate plus one, zero is a draw
+const DRAW_VALUE: u8 = 0;
+
+/// Longest mate a table can store, far above the ones of four piece endings
+pub const MAX_MATE_PLIES: u8 = 253;
+
+/// Distance to mate in plies with best play for the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dtm {
+    Draw,
+    Loss(u8),
+    Win(u8),
+}
+
+impl Dtm {
+    pub fn from_value(value: u8) -> Self {
+        match value {
+            DRAW_VALUE => Dtm::Draw,
+            value if (value - 1) % 2 == 0 => Dtm::Loss(value - 1),
+            value => Dtm::Win(value - 1),
+        }
+    }
+
+    pub fn to_value(self) -> u8 {
+        match self {
+            Dtm::Draw => DRAW_VALUE,
+            Dtm::Loss(plies) | Dtm::Win(plies) => plies + 1,
+        }
+    }
+
+    /// Result of the position before the move leading here, for the side that played it
+    pub fn get_parent(self) -> Self {
+        match self {
+            Dtm::Draw => Dtm::Draw,
+            Dtm::Loss(plies) => Dtm::Win(plies + 1),
+            Dtm::Win(plies) => Dtm::Loss(plies + 1),
+        }
+    }
+
+    /// Higher is better for the side to move, quicker wins and slower losses rank higher
+    pub fn get_rank(&self) -> i32 {
+        match self {
+            Dtm::Draw => 0,
+            Dtm::Loss(plies) => *plies as i32 - 1000,
+            Dtm::Win(plies) => 1000 - *plies as i32,
+        }
+    }
+
+    /// Score for the search from the side to move point of view, the distance to mate
+    /// is known so these wins rank above the ones of the Syzygy tables
+    pub fn get_score(&self) -> f32 {
+        let mate_plies = |plies: u8| (MAX_MATE_PLIES - plies) as f32 + 1.0;
+
+        match self {
+            Dtm::Draw => 0.0,
+            Dtm::Loss(plies) => -TABLEBASE_WIN_SCORE - mate_plies(*plies),
+            Dtm::Win(plies) => TABLEBASE_WIN_SCORE + mate_plies(*plies),
+        }
+    }
+}
+
+// Pieces of a side are stored after the ones of white, each side in the order of the names
+fn get_piece_order(piece: u8) -> (bool, usize) {
+    let piece_type = get_piece_type(piece);
+
+    let index = NAME_PIECES.iter().position(|(_, name_type)| *name_type == piece_type).unwrap_or(0);
+
+    (!is_white_piece(piece), index)
+}
+
+fn get_side_name(pieces: &[u8], white: bool) -> String {
+    let piece_types: Vec<PieceType> = pieces
+        .iter()
+        .filter(|piece| is_white_piece(**piece) == white)
+        .map(|piece| get_piece_type(*piece))
+        .collect();
+
+    tablebase_utils::get_side_name(&piece_types)
+}
+
+/// Pieces of the table the position belongs to, ordered like the table stores them.
+/// Tables have the stronger side as white, the colors are swapped and the board
+/// mirrored when black is the stronger side.
+pub fn get_table_pieces(pieces: &[(u8, i8)], white_move: bool) -> (String, Vec<(u8, i8)>, bool) {
+    let values: Vec<u8> = pieces.iter().map(|(piece, _)| *piece).collect();
+
+    let side_key = |white: bool| {
+        let worth: i32 = values
+            .iter()
+            .filter(|piece| is_white_piece(**piece) == white)
+            .map(|piece| get_piece_worth(*piece))
+            .sum();
+
+        (worth, get_side_name(&values, white))
+    };
+
+    let flip = side_key(false) > side_key(true);
+
+    let mut table_pieces: Vec<(u8, i8)> = pieces
+        .iter()
+        .map(|&(piece, position)| {
+            if flip {
+                (get_piece_value(get_piece_type(piece), !is_white_piece(piece)), position ^ 56)
+            } else {
+                (piece, position)
+            }
+        })
+        .collect();
+
+    table_pieces.sort_by_key(|(piece, _)| get_piece_order(*piece));
+
+    let table_values: Vec<u8> = table_pieces.iter().map(|(piece, _)| *piece).collect();
+
+    let name = format!("{}{}", get_side_name(&table_values, true), get_side_name(&table_values, false));
+
+    (name, table_pieces, white_move != flip)
+}
+
+/// Name of the table holding the material, like `KQKR`
+pub fn get_table_name(pieces: &[u8]) -> String {
+    let pieces: Vec<(u8, i8)> = pieces.iter().map(|piece| (*piece, 0)).collect();
+
+    get_table_pieces(&pieces, true).0
+}
+
+/// Pieces of the table in the order it stores them, `KQvKR` is accepted as well as `KQKR`
+pub fn parse_table_name(name: &str) -> Option<Vec<u8>> {
+    let name = name.replace('v', "");
+
+    let black_index = name.char_indices().skip(1).find(|(_, letter)| *letter == 'K')?.0;
+
+    let mut pieces = Vec::new();
+
+    for (white, side) in [(true, &name[..black_index]), (false, &name[black_index..])] {
+        for (index, letter) in side.chars().enumerate() {
+            let piece_type = get_name_piece_type(letter)?;
+
+            // One king for each side, leading its pieces
+            if (index == 0) != (piece_type == PieceType::King) {
+                return None;
+            }
+
+            pieces.push(get_piece_value(piece_type, white));
+        }
+    }
+
+    let pieces: Vec<(u8, i8)> = pieces.into_iter().map(|piece| (piece, 0)).collect();
+
+    Some(get_table_pieces(&pieces, true).1.into_iter().map(|(piece, _)| piece).collect())
+}
+
+/// Index of the position in a table, six bits for the square of each piece in the
+/// order of the table and the side to move above them
+pub fn get_table_index(positions: &[i8], white_move: bool) -> usize {
+    let side = if white_move { 0 } else { 1 };
+
+    positions
+        .iter()
+        .rev()
+        .fold(side, |index, position| (index << 6) | *position as usize)
+}
+
+/// Squares of the pieces and the side to move of a position by its index
+pub fn get_table_positions(index: usize, piece_count: usize) -> ([i8; MAX_TABLE_PIECES], bool) {
+    let mut positions = [INVALID_BOARD_POSITION; MAX_TABLE_PIECES];
+
+    for (slot, position) in positions.iter_mut().enumerate().take(piece_count) {
+        *position = ((index >> (6 * slot)) & 63) as i8;
+    }
+
+    (positions, index >> (6 * piece_count) == 0)
+}
+
+// Position seen through one of the symmetries of the board: bit 0 mirrors the files,
+// bit 1 the ranks and bit 2 swaps them first
+fn get_symmetric_position(position: i8, symmetry: u8) -> i8 {
+    let mut position = position;
+
+    if symmetry & 4 != 0 {
+        position = (7 - position % 8) * 8 + 7 - position / 8;
+    }
+
+    if symmetry & 1 != 0 {
+        position ^= 7;
+    }
+
+    if symmetry & 2 != 0 {
+        position ^= 56;
+    }
+
+    position
+}
+
+// Squares the white king is stored for, the a1-d1-d4 triangle without pawns
+// and the files a to d with them
+fn is_stored_king_position(position: i8, pawns: bool) -> bool {
+    let file = position % 8;
+    let rank = 7 - position / 8;
+
+    if pawns {
+        return file < 4;
+    }
+
+    file < 4 && rank <= file
+}
+
+// Index of the stored position the position is symmetric to, the white king leads
+// the pieces of every table
+fn get_stored_index(index: usize, piece_count: usize, pawns: bool) -> usize {
+    let (positions, white_move) = get_table_positions(index, piece_count);
+
+    let symmetries = if pawns { 2 } else { 8 };
+
+    let symmetry = (0..symmetries)
+        .find(|symmetry| is_stored_king_position(get_symmetric_position(positions[0], *symmetry), pawns))
+        .unwrap_or(0);
+
+    let positions: Vec<i8> = positions[..piece_count]
+        .iter()
+        .map(|position| get_symmetric_position(*position, symmetry))
+        .collect();
+
+    get_table_index(&positions, white_move)
+}
+
+/// Number of positions of a table with the given number of pieces
+pub fn get_table_size(piece_count: usize) -> usize {
+    2 << (6 * piece_count)
+}
+
+/// Distance to mate of every position with the material of the table, illegal positions
+/// are stored as draws
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndgameTable {
+    name: String,
+    pieces: Vec<u8>,
+    values: Vec<u8>,
+}
+
+impl EndgameTable {
+    pub fn new(pieces: Vec<u8>, values: Vec<u8>) -> Self {
+        EndgameTable {
+            name: get_table_name(&pieces),
+            pieces,
+            values,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Result of the position, the pieces must be ordered like the table stores them
+    pub fn get_dtm(&self, pieces: &[(u8, i8)], white_move: bool) -> Dtm {
+        let positions: Vec<i8> = pieces.iter().map(|(_, position)| *position).collect();
+
+        Dtm::from_value(self.values[get_table_index(&positions, white_move)])
+    }
+
+    /// Longest win of the table in plies
+    pub fn get_max_mate(&self) -> u8 {
+        self.values
+            .iter()
+            .filter_map(|value| match Dtm::from_value(*value) {
+                Dtm::Win(plies) => Some(plies),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn has_pawns(&self) -> bool {
+        self.pieces.iter().any(|piece| get_piece_type(*piece) == PieceType::Pawn)
+    }
+
+    // Whether the value of the index is written to the file, the others follow
+    // from the symmetries of the board
+    fn is_stored_index(&self, index: usize) -> bool {
+        let (positions, _) = get_table_positions(index, self.pieces.len());
+
+        is_stored_king_position(positions[0], self.has_pawns())
+    }
+
+    /// The magic and version, the name and the values of the positions with the white
+    /// king on the stored squares run length encoded, each run is a value followed by
+    /// its length as a variable length integer
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = TABLE_MAGIC.to_vec();
+
+        bytes.push(TABLE_VERSION);
+        bytes.push(self.name.len() as u8);
+        bytes.extend_from_slice(self.name.as_bytes());
+
+        let mut stored_values = (0..self.values.len())
+            .filter(|index| self.is_stored_index(*index))
+            .map(|index| self.values[index])
+            .peekable();
+
+        while let Some(value) = stored_values.next() {
+            let mut run = 1;
+
+            while stored_values.next_if_eq(&value).is_some() {
+                run += 1;
+            }
+
+            bytes.push(value);
+
+            while run >= 0x80 {
+                bytes.push((run & 0x7F) as u8 | 0x80);
+
+                run >>= 7;
+            }
+
+            bytes.push(run as u8);
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TablebaseError> {
+        let invalid = |reason: &str| TablebaseError::InvalidTable(String::from(reason));
+
+        if bytes.len() < 6 || &bytes[0..4] != TABLE_MAGIC || bytes[4] != TABLE_VERSION {
+            return Err(invalid("header"));
+        }
+
+        let name_end = 6 + bytes[5] as usize;
+
+        let name = bytes
+            .get(6..name_end)
+            .and_then(|name| std::str::from_utf8(name).ok())
+            .ok_or_else(|| invalid("name"))?;
+
+        let pieces = parse_table_name(name)
+            .filter(|pieces| pieces.len() <= MAX_TABLE_PIECES && get_table_name(pieces) == name)
+            .ok_or_else(|| invalid(name))?;
+
+        let mut table = EndgameTable::new(pieces, Vec::new());
+
+        let piece_count = table.pieces.len();
+        let pawns = table.has_pawns();
+
+        let stored_indexes: Vec<usize> = (0..get_table_size(piece_count))
+            .filter(|index| table.is_stored_index(*index))
+            .collect();
+
+        let mut stored_values = Vec::with_capacity(stored_indexes.len());
+
+        let mut cursor = bytes[name_end..].iter();
+
+        while let Some(&value) = cursor.next() {
+            let mut run = 0usize;
+            let mut shift = 0;
+
+            loop {
+                let byte = *cursor.next().ok_or_else(|| invalid(name))?;
+
+                run |= ((byte & 0x7F) as usize) << shift;
+                shift += 7;
+
+                if byte & 0x80 == 0 || shift > 28 {
+                    break;
+                }
+            }
+
+            if stored_values.len() + run > stored_indexes.len() {
+                return Err(invalid(name));
+            }
+
+            stored_values.resize(stored_values.len() + run, value);
+        }
+
+        if stored_values.len() != stored_indexes.len() {
+            return Err(invalid(name));
+        }
+
+        table.values = vec![Dtm::Draw.to_value(); get_table_size(piece_count)];
+
+        for (index, value) in stored_indexes.into_iter().zip(stored_values) {
+            table.values[index] = value;
+        }
+
+        for index in 0..table.values.len() {
+            let stored_index = get_stored_index(index, piece_count, pawns);
+
+            table.values[index] = table.values[stored_index];
+        }
+
+        Ok(table)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TablebaseError> {
+        let bytes = fs::read(path).map_err(|err| TablebaseError::Io(format!("{}: {}", path.display(), err)))?;
+
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TablebaseError> {
+        fs::write(path, self.to_bytes()).map_err(|err| TablebaseError::Io(format!("{}: {}", path.display(), err)))
+    }
+}
+
+/// File of the table in the directory
+pub fn get_table_path(directory: &Path, name: &str) -> PathBuf {
+    directory.join(format!("{}.{}", name, TABLE_EXTENSION))
+}
+
+#[derive(Debug)]
+struct TableFile {
+    path: PathBuf,
+    // Read the first time a position needs it
+    table: OnceLock<Option<EndgameTable>>,
+}
+
+/// Distance to mate tables built by `TableGenerator`, found in one or more directories
+#[derive(Debug)]
+pub struct EndgameTables {
+    max_pieces: usize,
+    // By name, like `KQKR`
+    tables: HashMap<String, TableFile>,
+}
+
+impl EndgameTables {
+    /// Tables of the directories in the path, found by `get_table_files`
+    pub fn load(path: &str) -> Result<Self, TablebaseError> {
+        let mut tables = HashMap::new();
+
+        for (name, _, file_path) in get_table_files(path, &[TABLE_EXTENSION])? {
+            let Some(pieces) = parse_table_name(&name) else {
+                continue;
+            };
+
+            // The first directory has precedence
+            tables.entry(get_table_name(&pieces)).or_insert(TableFile {
+                path: file_path,
+                table: OnceLock::new(),
+            });
+        }
+
+        let max_pieces = tables
+            .keys()
+            .filter_map(|name| parse_table_name(name))
+            .map(|pieces| pieces.len())
+            .max()
+            .ok_or_else(|| TablebaseError::NoTables(path.to_string()))?;
+
+        Ok(EndgameTables { max_pieces, tables })
+    }
+
+    /// Most pieces of the available tables
+    pub fn get_max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    fn get_table(&self, name: &str) -> Option<&EndgameTable> {
+        let table_file = self.tables.get(name)?;
+
+        table_file
+            .table
+            .get_or_init(|| EndgameTable::load(&table_file.path).ok())
+            .as_ref()
+    }
+
+    /// Result of the position, None when it is not in the tables. The tables know nothing
+    /// of castling, en passant and the fifty move rule.
+    pub fn probe(&self, board_state: &BoardState) -> Option<Dtm> {
+        let castling = board_state.is_white_able_to_king_side_castle()
+            || board_state.is_white_able_to_queen_side_castle()
+            || board_state.is_black_able_to_king_side_castle()
+            || board_state.is_black_able_to_queen_side_castle();
+
+        if castling
+            || board_state.get_occupancy().count_ones() as usize > self.max_pieces
+            || get_en_passant_hash(board_state) != 0
+        {
+            return None;
+        }
+
+        let pieces: Vec<(u8, i8)> = (0..64)
+            .map(|position| (board_state.get_piece(position), position))
+            .filter(|(piece, _)| get_piece_type(*piece) != PieceType::Empty)
+            .collect();
+
+        // Two kings can't mate
+        if pieces.len() == 2 {
+            return Some(Dtm::Draw);
+        }
+
+        let (name, table_pieces, white_move) = get_table_pieces(&pieces, board_state.is_white_move());
+
+        Some(self.get_table(&name)?.get_dtm(&table_pieces, white_move))
+    }
+
+    /// Every legal move with the result it leads to
+    pub fn probe_root(&self, board: &mut Board) -> Option<Vec<(PieceMove, Dtm)>> {
+        self.probe(board.get_state_reference())?;
+
+        let mut root_moves = Vec::new();
+
+        for piece_move in get_tablebase_moves(board) {
+            board.move_piece(&piece_move).ok()?;
+
+            let dtm = self.probe(board.get_state_reference());
+
+            board.undo_last_move();
+
+            root_moves.push((piece_move, dtm?.get_parent()));
+        }
+
+        Some(root_moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::{
+        common::{
+            contants::{BLACK_KING, BLACK_ROOK, WHITE_KING, WHITE_QUEEN},
+            tablebase_error::TablebaseError,
+        },
+        game::board::Board,
+    };
+
+    use super::{generator::TableGenerator, get_table_name, parse_table_name, Dtm, EndgameTable, EndgameTables};
+
+    fn board_of(fen: &str) -> Board {
+        let mut board = Board::new();
+
+        board.load_position(fen).unwrap();
+
+        board
+    }
+
+    #[test]
+    fn test_table_names() {
+        assert_eq!(parse_table_name("KQKR"), Some(vec![WHITE_KING, WHITE_QUEEN, BLACK_KING, BLACK_ROOK]));
+        assert_eq!(parse_table_name("KQvKR"), parse_table_name("KQKR"));
+
+        // The stronger side is always white
+        assert_eq!(parse_table_name("KRKQ"), parse_table_name("KQKR"));
+        assert_eq!(get_table_name(&[BLACK_KING, BLACK_ROOK, WHITE_KING]), "KRK");
+
+        assert_eq!(parse_table_name("QKK"), None);
+        assert_eq!(parse_table_name("KQ"), None);
+        assert_eq!(parse_table_name("KXK"), None);
+    }
+
+    #[test]
+    fn test_dtm_values() {
+        for dtm in [Dtm::Draw, Dtm::Loss(0), Dtm::Win(1), Dtm::Loss(30), Dtm::Win(19)] {
+            assert_eq!(Dtm::from_value(dtm.to_value()), dtm);
+        }
+
+        assert_eq!(Dtm::Loss(0).get_parent(), Dtm::Win(1));
+        assert!(Dtm::Win(1).get_rank() > Dtm::Win(3).get_rank());
+        assert!(Dtm::Loss(4).get_rank() > Dtm::Loss(2).get_rank());
+        assert!(Dtm::Win(3).get_score() > Dtm::Win(5).get_score());
+    }
+
+    #[test]
+    fn test_probe_generated_tables() {
+        let directory = env::temp_dir().join(format!("rustmate-endgame-tables-{}", std::process::id()));
+
+        fs::create_dir_all(&directory).unwrap();
+
+        let mut generator = TableGenerator::new(directory.to_str().unwrap());
+
+        // Longest mates of the two endings, 10 and 16 moves
+        assert_eq!(generator.generate("KQK").unwrap().get_max_mate(), 19);
+        assert_eq!(generator.generate("KRK").unwrap().get_max_mate(), 31);
+
+        let saved = EndgameTable::load(&directory.join("KQK.rmtb")).unwrap();
+
+        assert_eq!(&saved, generator.generate("KQK").unwrap());
+
+        let tables = EndgameTables::load(directory.to_str().unwrap()).unwrap();
+
+        assert_eq!(tables.get_max_pieces(), 3);
+
+        let probe = |fen: &str| tables.probe(board_of(fen).get_state_reference());
+
+        assert_eq!(probe("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"), Some(Dtm::Loss(0)));
+        assert_eq!(probe("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1"), Some(Dtm::Win(1)));
+        assert_eq!(probe("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"), Some(Dtm::Draw));
+
+        // The black rook is found in the white rook table
+        assert_eq!(probe("K7/1r6/1k6/8/8/8/8/8 w - - 0 1"), Some(Dtm::Draw));
+        assert_eq!(probe("K7/8/1k6/8/8/8/8/7r b - - 0 1"), Some(Dtm::Win(1)));
+
+        // Taking the queen leaves the kings alone, missing tables aren't probed
+        assert_eq!(probe("8/8/8/8/8/8/4kQ2/7K b - - 0 1"), Some(Dtm::Draw));
+        assert_eq!(probe("8/8/8/8/8/8/4kB2/7K b - - 0 1"), None);
+        assert_eq!(probe("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1"), None);
+
+        let mut board = board_of("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1");
+
+        let best_move = tables
+            .probe_root(&mut board)
+            .unwrap()
+            .into_iter()
+            .max_by_key(|(_, dtm)| dtm.get_rank())
+            .unwrap();
+
+        assert_eq!(best_move.1, Dtm::Win(1));
+        assert_eq!(best_move.0.get_to_position(), 6);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_tables() {
+        let table = EndgameTable::new(vec![WHITE_KING, WHITE_QUEEN, BLACK_KING], vec![0; 2 << 18]);
+
+        let mut bytes = table.to_bytes();
+
+        assert_eq!(EndgameTable::from_bytes(&bytes).unwrap(), table);
+
+        bytes.truncate(bytes.len() - 2);
+
+        assert_eq!(
+            EndgameTable::from_bytes(&bytes).unwrap_err(),
+            TablebaseError::InvalidTable(String::from("KQK"))
+        );
+        assert!(EndgameTable::from_bytes(b"RMTB").is_err());
+    }
+}
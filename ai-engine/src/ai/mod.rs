@@ -1,4 +1,9 @@
 pub mod ai_player;
 pub mod ai_utils;
+pub mod book_builder;
 pub mod constants;
+pub mod endgame_tables;
+pub mod opening_book;
+pub mod search_context;
+pub mod syzygy;
 pub mod transposition_table;
@@ -0,0 +1,332 @@
+use std::{cmp::Reverse, fs};
+
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    common::{
+        board_utils::get_position_notation,
+        book_error::BookError,
+        contants::EMPTY_PIECE,
+        enums::PieceType,
+        piece_move::PieceMove,
+        piece_utils::{get_piece_type, is_piece_of_type},
+    },
+    game::{board::Board, move_parser::parse_uci_move},
+};
+
+// Key, move, weight and learn values, all big endian
+const ENTRY_SIZE: usize = 16;
+
+const PROMOTION_SUFFIXES: [&str; 5] = ["", "n", "b", "r", "q"];
+
+/// How the book move is chosen among the ones stored for the position
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BookMode {
+    Best,
+    Off,
+    #[default]
+    WeightedRandom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub learn: u32,
+    pub raw_move: u16,
+    pub weight: u16,
+}
+
+impl BookEntry {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        BookEntry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0u8; ENTRY_SIZE];
+
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+
+        bytes
+    }
+
+    /// Coordinate notation of the move, Polyglot stores castling as the king taking its own rook
+    pub fn get_uci_notation(&self, board: &Board) -> String {
+        let to_file = (self.raw_move & 7) as i8;
+        let to_row = ((self.raw_move >> 3) & 7) as i8;
+        let from_file = ((self.raw_move >> 6) & 7) as i8;
+        let from_row = ((self.raw_move >> 9) & 7) as i8;
+        let promotion = ((self.raw_move >> 12) & 7) as usize;
+
+        // Polyglot squares start from a1, the board positions from a8
+        let from_position = (from_row * 8 + from_file) ^ 56;
+        let mut to_position = (to_row * 8 + to_file) ^ 56;
+
+        let moving_piece = board.get_state_reference().get_piece(from_position);
+
+        if is_piece_of_type(moving_piece, PieceType::King) && (from_file - to_file).abs() > 2 {
+            to_position = if to_file > from_file { from_position + 2 } else { from_position - 2 };
+        }
+
+        let mut notation = get_position_notation(from_position);
+
+        notation.push_str(&get_position_notation(to_position));
+        notation.push_str(PROMOTION_SUFFIXES.get(promotion).unwrap_or(&""));
+
+        notation
+    }
+}
+
+/// Polyglot encoding of a move, the reverse of `BookEntry::get_uci_notation`
+pub fn encode_book_move(piece_move: &PieceMove) -> u16 {
+    let from_position = piece_move.get_from_position();
+    let mut to_position = piece_move.get_to_position();
+
+    if is_piece_of_type(piece_move.get_piece_value(), PieceType::King)
+        && (from_position - to_position).abs() == 2
+    {
+        // The king lands on the square of the rook it castles with
+        to_position = if to_position > from_position { to_position + 1 } else { to_position - 2 };
+    }
+
+    let promotion = if piece_move.is_promotion() && piece_move.get_promotion_value() != EMPTY_PIECE {
+        match get_piece_type(piece_move.get_promotion_value()) {
+            PieceType::Knight => 1,
+            PieceType::Bishop => 2,
+            PieceType::Rook => 3,
+            _ => 4,
+        }
+    } else {
+        0
+    };
+
+    // Polyglot squares start from a1, the board positions from a8
+    (promotion << 12) | (((from_position ^ 56) as u16) << 6) | (to_position ^ 56) as u16
+}
+
+/// Polyglot `.bin` opening book, the Zobrist hash of the board uses the Polyglot keys
+/// so it can be looked up directly
+#[derive(Debug, Clone, Default)]
+pub struct OpeningBook {
+    entries: Vec<BookEntry>,
+}
+
+impl OpeningBook {
+    /// Book with the given entries, sorted by key then by weight like Polyglot books
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> Self {
+        entries.sort_by_key(|entry| (entry.key, Reverse(entry.weight), entry.raw_move));
+
+        OpeningBook { entries }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(BookError::InvalidSize(bytes.len()));
+        }
+
+        let mut entries: Vec<BookEntry> = bytes.chunks_exact(ENTRY_SIZE).map(BookEntry::from_bytes).collect();
+
+        // Books are written sorted, sorting again keeps the lookup right for the ones that aren't
+        entries.sort_by_key(|entry| entry.key);
+
+        Ok(OpeningBook { entries })
+    }
+
+    pub fn load(path: &str) -> Result<Self, BookError> {
+        let bytes = fs::read(path).map_err(|err| BookError::Io(format!("{}: {}", path, err)))?;
+
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries.iter().flat_map(|entry| entry.to_bytes()).collect()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), BookError> {
+        fs::write(path, self.to_bytes()).map_err(|err| BookError::Io(format!("{}: {}", path, err)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get_entries(&self, hash: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < hash);
+        let end = self.entries.partition_point(|entry| entry.key <= hash);
+
+        &self.entries[start..end]
+    }
+
+    /// Book move for the current position, moves without weight are never played
+    pub fn get_move<R: Rng>(&self, board: &mut Board, mode: BookMode, rng: &mut R) -> Option<PieceMove> {
+        if mode == BookMode::Off {
+            return None;
+        }
+
+        let entries = self.get_entries(board.get_zobrist_hash());
+
+        // Entries of another position sharing the hash are not legal here and are left out
+        let candidates: Vec<(PieceMove, u16)> = entries
+            .iter()
+            .filter(|entry| entry.weight > 0)
+            .filter_map(|entry| {
+                let notation = entry.get_uci_notation(board);
+
+                parse_uci_move(board, &notation).ok().map(|piece_move| (piece_move, entry.weight))
+            })
+            .collect();
+
+        if mode == BookMode::Best {
+            return candidates
+                .into_iter()
+                .min_by_key(|(_, weight)| Reverse(*weight))
+                .map(|(piece_move, _)| piece_move);
+        }
+
+        let total_weight: u32 = candidates.iter().map(|(_, weight)| *weight as u32).sum();
+
+        if total_weight == 0 {
+            return None;
+        }
+
+        let mut pick = rng.gen_range(0..total_weight);
+
+        for (piece_move, weight) in candidates {
+            if pick < weight as u32 {
+                return Some(piece_move);
+            }
+
+            pick -= weight as u32;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{
+        common::{book_error::BookError, board_utils::get_uci_move_notation, contants::{INITIAL_FEN, WHITE_KNIGHT}},
+        game::board::Board,
+    };
+
+    use super::{BookMode, OpeningBook};
+
+    // Polyglot moves pack the from and to squares counted from a1 and the promotion piece
+    fn encode_move(from: u16, to: u16, promotion: u16) -> u16 {
+        promotion << 12 | from << 6 | to
+    }
+
+    fn encode_book(entries: &[(u64, u16, u16)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for &(key, raw_move, weight) in entries {
+            bytes.extend_from_slice(&key.to_be_bytes());
+            bytes.extend_from_slice(&raw_move.to_be_bytes());
+            bytes.extend_from_slice(&weight.to_be_bytes());
+            bytes.extend_from_slice(&0u32.to_be_bytes());
+        }
+
+        bytes
+    }
+
+    fn hash_of(fen: &str) -> u64 {
+        let mut board = Board::new();
+
+        board.load_position(fen).unwrap();
+
+        board.get_zobrist_hash()
+    }
+
+    fn book_move(book: &OpeningBook, fen: &str, mode: BookMode) -> Option<String> {
+        let mut board = Board::new();
+
+        board.load_position(fen).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(15);
+
+        book.get_move(&mut board, mode, &mut rng)
+            .map(|piece_move| get_uci_move_notation(&piece_move))
+    }
+
+    const INITIAL_HASH: u64 = 0x463b_9618_1691_fc9c;
+
+    #[test]
+    fn test_pick_book_moves() {
+        let book = OpeningBook::from_bytes(&encode_book(&[
+            (INITIAL_HASH, encode_move(11, 27, 0), 10),
+            (INITIAL_HASH, encode_move(12, 28, 0), 30),
+            (INITIAL_HASH, encode_move(6, 21, 0), 0),
+            (INITIAL_HASH, encode_move(12, 36, 0), 50),
+        ]))
+        .unwrap();
+
+        assert_eq!(book.get_entries(INITIAL_HASH).len(), 4);
+        assert_eq!(book_move(&book, INITIAL_FEN, BookMode::Best), Some(String::from("e2e4")));
+        assert_eq!(book_move(&book, INITIAL_FEN, BookMode::Off), None);
+
+        // The illegal entry is skipped and the one without weight is never picked
+        let mut board = Board::new();
+        let mut rng = StdRng::seed_from_u64(15);
+        let mut d4_count = 0;
+
+        for _ in 0..400 {
+            let piece_move = book.get_move(&mut board, BookMode::WeightedRandom, &mut rng).unwrap();
+
+            match get_uci_move_notation(&piece_move).as_str() {
+                "d2d4" => d4_count += 1,
+                "e2e4" => {}
+                notation => panic!("unexpected book move {}", notation),
+            }
+        }
+
+        assert!((60..140).contains(&d4_count), "d2d4 played {} times", d4_count);
+
+        assert_eq!(book_move(&book, "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1", BookMode::Best), None);
+    }
+
+    #[test]
+    fn test_castling_and_promotion_moves() {
+        let castling_fen = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1";
+        let promotion_fen = "8/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+
+        let book = OpeningBook::from_bytes(&encode_book(&[
+            (hash_of(castling_fen), encode_move(60, 56, 0), 1),
+            (hash_of(promotion_fen), encode_move(52, 60, 1), 1),
+        ]))
+        .unwrap();
+
+        assert_eq!(book_move(&book, castling_fen, BookMode::Best), Some(String::from("e8c8")));
+
+        let mut board = Board::new();
+
+        board.load_position(promotion_fen).unwrap();
+
+        let piece_move = book
+            .get_move(&mut board, BookMode::Best, &mut StdRng::seed_from_u64(15))
+            .unwrap();
+
+        assert_eq!(piece_move.get_promotion_value(), WHITE_KNIGHT);
+    }
+
+    #[test]
+    fn test_invalid_book() {
+        assert_eq!(OpeningBook::from_bytes(&[0; 20]).unwrap_err(), BookError::InvalidSize(20));
+        assert!(matches!(OpeningBook::load("missing-book.bin"), Err(BookError::Io(_))));
+    }
+}
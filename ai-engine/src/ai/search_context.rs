@@ -0,0 +1,222 @@
+use crate::common::{piece_move::PieceMove, piece_utils::is_white_piece};
+
+use super::constants::{MAX_HISTORY, MAX_SEARCH_EXTENSIONS};
+
+// Quiet moves kept for each ply
+const KILLER_SLOTS: usize = 2;
+
+const BOARD_SQUARES: usize = 64;
+
+/// State of the search owned by a single thread, filled as its branches are searched
+pub struct SearchContext {
+    // Quiet moves that caused a beta cutoff, by ply from the root, the latest first
+    killers: Vec<[Option<PieceMove>; KILLER_SLOTS]>,
+    // Butterfly history of the quiet moves by color, from and to positions, raised when
+    // they cause a beta cutoff and lowered when they were searched before the one that did
+    history: Vec<i32>,
+    // Quiet moves that refuted the opponent's move, by its from and to positions
+    countermoves: Vec<Option<PieceMove>>,
+    // Plies added by extensions to the line being searched
+    extensions: u8,
+    // Set while a null move cutoff is verified, no null moves are tried meanwhile
+    verifying: bool,
+}
+
+impl SearchContext {
+    pub fn new() -> Self {
+        SearchContext {
+            killers: Vec::new(),
+            history: vec![0; 2 * BOARD_SQUARES * BOARD_SQUARES],
+            countermoves: vec![None; BOARD_SQUARES * BOARD_SQUARES],
+            extensions: 0,
+            verifying: false,
+        }
+    }
+
+    pub fn add_killer(&mut self, ply: u8, piece_move: &PieceMove) {
+        let ply = ply as usize;
+
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None, None]);
+        }
+
+        let killers = &mut self.killers[ply];
+
+        if killers[0].as_ref().is_some_and(|killer| killer.is_same_move(piece_move)) {
+            return;
+        }
+
+        killers[1] = killers[0].take();
+        killers[0] = Some(piece_move.clone());
+    }
+
+    pub fn is_killer(&self, ply: u8, piece_move: &PieceMove) -> bool {
+        self.killers.get(ply as usize).is_some_and(|killers| {
+            killers
+                .iter()
+                .flatten()
+                .any(|killer| killer.is_same_move(piece_move))
+        })
+    }
+
+    pub fn get_history(&self, piece_move: &PieceMove) -> i32 {
+        self.history[get_history_index(piece_move)]
+    }
+
+    /// Adds the bonus, or the malus when negative, to the move's history. The closer the
+    /// entry is to the bounds the less it changes, so it never goes past them.
+    pub fn update_history(&mut self, piece_move: &PieceMove, bonus: i32) {
+        let bonus = bonus.clamp(-MAX_HISTORY, MAX_HISTORY);
+
+        let entry = &mut self.history[get_history_index(piece_move)];
+
+        *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+    }
+
+    /// Halves the history so a new iteration isn't driven by the shallower ones
+    pub fn age_history(&mut self) {
+        self.history.iter_mut().for_each(|entry| *entry /= 2);
+    }
+
+    pub fn set_countermove(&mut self, previous_move: &PieceMove, piece_move: &PieceMove) {
+        if let Some(index) = get_countermove_index(previous_move) {
+            self.countermoves[index] = Some(piece_move.clone());
+        }
+    }
+
+    pub fn is_countermove(&self, previous_move: &PieceMove, piece_move: &PieceMove) -> bool {
+        get_countermove_index(previous_move)
+            .and_then(|index| self.countermoves[index].as_ref())
+            .is_some_and(|countermove| countermove.is_same_move(piece_move))
+    }
+
+    pub fn can_extend(&self) -> bool {
+        self.extensions < MAX_SEARCH_EXTENSIONS
+    }
+
+    /// Counts the extension of the move about to be searched, until it is retracted
+    pub fn extend(&mut self, plies: u8) {
+        self.extensions += plies;
+    }
+
+    pub fn retract(&mut self, plies: u8) {
+        self.extensions -= plies;
+    }
+
+    pub fn is_verifying(&self) -> bool {
+        self.verifying
+    }
+
+    pub fn set_verifying(&mut self, verifying: bool) {
+        self.verifying = verifying;
+    }
+}
+
+fn get_history_index(piece_move: &PieceMove) -> usize {
+    let color = is_white_piece(piece_move.get_piece_value()) as usize;
+
+    (color * BOARD_SQUARES + piece_move.get_from_position() as usize) * BOARD_SQUARES
+        + piece_move.get_to_position() as usize
+}
+
+// Null moves have no positions, nothing refutes them
+fn get_countermove_index(previous_move: &PieceMove) -> Option<usize> {
+    let from = usize::try_from(previous_move.get_from_position()).ok()?;
+    let to = usize::try_from(previous_move.get_to_position()).ok()?;
+
+    Some(from * BOARD_SQUARES + to)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{
+        contants::{BLACK_PAWN, INVALID_BOARD_POSITION, WHITE_KNIGHT},
+        piece_move::PieceMove,
+    };
+
+    use super::{SearchContext, MAX_HISTORY, MAX_SEARCH_EXTENSIONS};
+
+    #[test]
+    fn test_killers() {
+        let mut context = SearchContext::new();
+
+        let first = PieceMove::new(62, WHITE_KNIGHT, 45);
+        let second = PieceMove::new(62, WHITE_KNIGHT, 47);
+        let third = PieceMove::new(57, WHITE_KNIGHT, 42);
+
+        context.add_killer(3, &first);
+        context.add_killer(3, &second);
+
+        assert!(context.is_killer(3, &first));
+        assert!(context.is_killer(3, &second));
+        assert!(!context.is_killer(2, &first));
+
+        // The oldest one makes room for the new killer, a repeated one is kept once
+        context.add_killer(3, &third);
+        context.add_killer(3, &third);
+
+        assert!(!context.is_killer(3, &first));
+        assert!(context.is_killer(3, &second));
+        assert!(context.is_killer(3, &third));
+    }
+
+    #[test]
+    fn test_extension_budget() {
+        let mut context = SearchContext::new();
+
+        for _ in 0..MAX_SEARCH_EXTENSIONS {
+            assert!(context.can_extend());
+
+            context.extend(1);
+        }
+
+        assert!(!context.can_extend());
+
+        context.retract(1);
+
+        assert!(context.can_extend());
+    }
+
+    #[test]
+    fn test_history() {
+        let mut context = SearchContext::new();
+
+        let piece_move = PieceMove::new(62, WHITE_KNIGHT, 45);
+        let other_move = PieceMove::new(62, WHITE_KNIGHT, 47);
+
+        context.update_history(&piece_move, 100);
+        context.update_history(&other_move, -100);
+
+        assert!(context.get_history(&piece_move) > 0);
+        assert!(context.get_history(&other_move) < 0);
+
+        // Gravity keeps repeated bonuses within the bounds
+        for _ in 0..1000 {
+            context.update_history(&piece_move, MAX_HISTORY / 2);
+        }
+
+        assert!(context.get_history(&piece_move) <= MAX_HISTORY);
+
+        let history = context.get_history(&piece_move);
+
+        context.age_history();
+
+        assert_eq!(context.get_history(&piece_move), history / 2);
+    }
+
+    #[test]
+    fn test_countermoves() {
+        let mut context = SearchContext::new();
+
+        let previous_move = PieceMove::new(12, BLACK_PAWN, 28);
+        let piece_move = PieceMove::new(62, WHITE_KNIGHT, 45);
+        let null_move = PieceMove::new(INVALID_BOARD_POSITION, 0, INVALID_BOARD_POSITION);
+
+        context.set_countermove(&previous_move, &piece_move);
+        context.set_countermove(&null_move, &piece_move);
+
+        assert!(context.is_countermove(&previous_move, &piece_move));
+        assert!(!context.is_countermove(&previous_move, &PieceMove::new(62, WHITE_KNIGHT, 47)));
+        assert!(!context.is_countermove(&null_move, &piece_move));
+    }
+}
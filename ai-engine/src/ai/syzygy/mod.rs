@@ -0,0 +1,689 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::OnceLock,
+};
+
+use crate::{
+    common::{
+        contants::{EMPTY_PIECE, KING_WORTH, PAWN_WORTH},
+        enums::PieceType,
+        piece_move::PieceMove,
+        piece_utils::{get_piece_type, get_promotion_options, is_piece_of_type, is_white_piece},
+        tablebase_error::TablebaseError,
+        tablebase_utils::{self, get_table_files},
+    },
+    game::{board::Board, board_state::BoardState},
+};
+
+use table::{
+    Table, TableMaterial, TableProbe, SYZYGY_BISHOP, SYZYGY_BLACK, SYZYGY_KING, SYZYGY_KNIGHT, SYZYGY_PAWN,
+    SYZYGY_QUEEN, SYZYGY_ROOK,
+};
+
+pub mod table;
+
+pub const DEFAULT_PROBE_DEPTH: u8 = 1;
+
+/// Score of a won tablebase position, below the checkmates found by the search
+pub const TABLEBASE_WIN_SCORE: f32 = KING_WORTH * 5.0;
+
+// Wins and losses the fifty move rule turns into draws are scored close to a draw
+const CURSED_WIN_SCORE: f32 = PAWN_WORTH / 50.0;
+
+// Ranks of the root moves are built around it, no DTZ gets close
+const MAX_DTZ: i32 = 1 << 18;
+
+const WDL_EXTENSION: &str = "rtbw";
+const DTZ_EXTENSION: &str = "rtbz";
+
+/// Result of the position with best play for the side to move. Cursed wins and
+/// blessed losses are drawn by the fifty move rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Self {
+        match value {
+            ..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    /// Score for the search from the side to move point of view
+    pub fn get_score(&self) -> f32 {
+        match self {
+            Wdl::Loss => -TABLEBASE_WIN_SCORE,
+            Wdl::BlessedLoss => -CURSED_WIN_SCORE,
+            Wdl::Draw => 0.0,
+            Wdl::CursedWin => CURSED_WIN_SCORE,
+            Wdl::Win => TABLEBASE_WIN_SCORE,
+        }
+    }
+}
+
+/// Legal move of the root position ranked by the tables
+#[derive(Debug, Clone)]
+pub struct RootMove {
+    pub piece_move: PieceMove,
+    // Higher is better, quicker wins rank higher and wins that can't be converted
+    // before the fifty move rule rank below the other wins
+    pub rank: i32,
+}
+
+impl RootMove {
+    pub fn get_score(&self) -> f32 {
+        let bound = MAX_DTZ / 2 - 100;
+
+        let wdl = match self.rank {
+            rank if rank >= bound => Wdl::Win,
+            rank if rank > 0 => Wdl::CursedWin,
+            0 => Wdl::Draw,
+            rank if rank > -bound => Wdl::BlessedLoss,
+            _ => Wdl::Loss,
+        };
+
+        wdl.get_score()
+    }
+}
+
+#[derive(Debug)]
+struct TableFile {
+    path: PathBuf,
+    // Read the first time a position needs it
+    table: OnceLock<Option<Table>>,
+}
+
+impl TableFile {
+    fn new(path: PathBuf) -> Self {
+        TableFile {
+            path,
+            table: OnceLock::new(),
+        }
+    }
+
+    fn get_table(&self, material: &TableMaterial, dtz: bool) -> Option<&Table> {
+        self.table
+            .get_or_init(|| Table::new(fs::read(&self.path).ok()?, material.clone(), dtz))
+            .as_ref()
+    }
+}
+
+#[derive(Debug)]
+struct TableEntry {
+    dtz: Option<TableFile>,
+    material: TableMaterial,
+    wdl: Option<TableFile>,
+}
+
+/// Syzygy endgame tables found in one or more directories, the WDL tables give the
+/// result of a position and the DTZ tables how to make progress towards it
+#[derive(Debug)]
+pub struct Tablebase {
+    max_pieces: usize,
+    // By name, like `KRPvKR`
+    tables: HashMap<String, TableEntry>,
+}
+
+impl Tablebase {
+    /// Tables of the directories in the path, found by `get_table_files`
+    pub fn load(path: &str) -> Result<Self, TablebaseError> {
+        let mut tables: HashMap<String, TableEntry> = HashMap::new();
+
+        for (name, extension, file_path) in get_table_files(path, &[WDL_EXTENSION, DTZ_EXTENSION])? {
+            let Some(material) = TableMaterial::from_name(&name) else {
+                continue;
+            };
+
+            let entry = tables.entry(name).or_insert(TableEntry {
+                dtz: None,
+                material,
+                wdl: None,
+            });
+
+            // The first directory has precedence
+            let table_file = if extension == DTZ_EXTENSION { &mut entry.dtz } else { &mut entry.wdl };
+
+            table_file.get_or_insert_with(|| TableFile::new(file_path));
+        }
+
+        let max_pieces = tables
+            .values()
+            .filter(|entry| entry.wdl.is_some())
+            .map(|entry| entry.material.get_piece_count())
+            .max()
+            .ok_or_else(|| TablebaseError::NoTables(path.to_string()))?;
+
+        Ok(Tablebase { max_pieces, tables })
+    }
+
+    /// Most pieces of the available WDL tables
+    pub fn get_max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Positions with castling rights or more pieces than the tables are not stored
+    pub fn is_position_stored(&self, board_state: &BoardState) -> bool {
+        let castling = board_state.is_white_able_to_king_side_castle()
+            || board_state.is_white_able_to_queen_side_castle()
+            || board_state.is_black_able_to_king_side_castle()
+            || board_state.is_black_able_to_queen_side_castle();
+
+        !castling && board_state.get_occupancy().count_ones() as usize <= self.max_pieces
+    }
+
+    /// Result of the position, None when it is not in the tables
+    pub fn probe_wdl(&self, board: &mut Board) -> Option<Wdl> {
+        if !self.is_position_stored(board.get_state_reference()) {
+            return None;
+        }
+
+        self.search(board, false).map(|(wdl, _)| Wdl::from_value(wdl))
+    }
+
+    /// Every legal move ranked by its result and DTZ, taking the fifty move
+    /// counter of the position into account
+    pub fn probe_root(&self, board: &mut Board) -> Option<Vec<RootMove>> {
+        if !self.is_position_stored(board.get_state_reference()) {
+            return None;
+        }
+
+        let half_moves = board.get_state_reference().get_half_moves() as i32;
+        let repeated = board.is_repeated_position();
+
+        let mut root_moves = Vec::new();
+
+        for piece_move in get_tablebase_moves(board) {
+            board.move_piece(&piece_move).ok()?;
+
+            let dtz = if board.get_state_reference().get_half_moves() == 0 {
+                self.search(board, false).map(|(wdl, _)| get_dtz_before_zeroing(-wdl))
+            } else if board.is_repeated_position() {
+                Some(0)
+            } else {
+                // Counted from the root position
+                self.get_dtz(board).map(|dtz| -dtz - dtz.signum())
+            };
+
+            let mate = dtz == Some(2) && is_checkmate(board);
+
+            board.undo_last_move();
+
+            let dtz = if mate { 1 } else { dtz? };
+
+            let rank = if dtz > 0 {
+                if dtz + half_moves <= 99 && !repeated {
+                    MAX_DTZ - dtz
+                } else {
+                    MAX_DTZ / 2 - (dtz + half_moves)
+                }
+            } else if dtz < 0 {
+                if -dtz * 2 + half_moves < 100 {
+                    -MAX_DTZ - dtz
+                } else {
+                    -MAX_DTZ / 2 + (-dtz + half_moves)
+                }
+            } else {
+                0
+            };
+
+            root_moves.push(RootMove {
+                piece_move,
+                rank,
+            });
+        }
+
+        Some(root_moves)
+    }
+
+    // Value of the position in the table, the DTZ ones need its WDL value
+    fn probe_table(&self, board: &Board, dtz: bool, wdl: i32) -> Option<TableProbe> {
+        let pieces = get_table_pieces(board.get_state_reference());
+
+        // Only the kings are left
+        if pieces.len() == 2 {
+            return Some(TableProbe::Value(0));
+        }
+
+        let white_name = get_side_name(&pieces, 0);
+        let black_name = get_side_name(&pieces, SYZYGY_BLACK);
+
+        // Tables are named with the stronger side first
+        let (entry, black_stronger) = match self.tables.get(&format!("{}v{}", white_name, black_name)) {
+            Some(entry) => (entry, false),
+            None => (self.tables.get(&format!("{}v{}", black_name, white_name))?, true),
+        };
+
+        let table_file = if dtz { entry.dtz.as_ref() } else { entry.wdl.as_ref() }?;
+
+        table_file
+            .get_table(&entry.material, dtz)?
+            .probe(&pieces, board.is_white_move(), black_stronger, wdl)
+    }
+
+    // The tables don't know about en passant and may store anything when capturing is
+    // the best move, so the captures are searched first. With `check_zeroing` the pawn
+    // moves are too. Also tells if a move resetting the fifty move counter is the best.
+    fn search(&self, board: &mut Board, check_zeroing: bool) -> Option<(i32, bool)> {
+        let moves = get_tablebase_moves(board);
+
+        let mut best_value = -2;
+        let mut searched_moves = 0;
+
+        for piece_move in &moves {
+            let pawn_move = is_piece_of_type(piece_move.get_piece_value(), PieceType::Pawn);
+
+            if !(piece_move.is_capture() || (check_zeroing && pawn_move)) {
+                continue;
+            }
+
+            searched_moves += 1;
+
+            board.move_piece(piece_move).ok()?;
+
+            let result = self.search(board, false);
+
+            board.undo_last_move();
+
+            let value = -result?.0;
+
+            if value > best_value {
+                best_value = value;
+
+                if value >= 2 {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        let every_move_searched = searched_moves > 0 && searched_moves == moves.len();
+
+        let value = if every_move_searched {
+            best_value
+        } else {
+            match self.probe_table(board, false, 0)? {
+                TableProbe::Value(value) => value,
+                TableProbe::ChangeSideToMove => return None,
+            }
+        };
+
+        if best_value >= value {
+            return Some((best_value, best_value > 0 || every_move_searched));
+        }
+
+        Some((value, false))
+    }
+
+    // Plies until the fifty move counter is reset by a capture or a pawn move with best
+    // play, negative when losing, 0 for draws and 100 more for the cursed wins
+    fn get_dtz(&self, board: &mut Board) -> Option<i32> {
+        let (wdl, zeroing_best) = self.search(board, true)?;
+
+        // Draws are not stored
+        if wdl == 0 {
+            return Some(0);
+        }
+
+        if zeroing_best {
+            return Some(get_dtz_before_zeroing(wdl));
+        }
+
+        if let TableProbe::Value(dtz) = self.probe_table(board, true, wdl)? {
+            let cursed = if wdl.abs() == 1 { 100 } else { 0 };
+
+            return Some((dtz + cursed) * wdl.signum());
+        }
+
+        // The table only stores the other side to move, the DTZ comes from the best reply
+        let mut min_dtz = i32::MAX;
+
+        for piece_move in get_tablebase_moves(board) {
+            let zeroing = piece_move.is_capture() || is_piece_of_type(piece_move.get_piece_value(), PieceType::Pawn);
+
+            board.move_piece(&piece_move).ok()?;
+
+            // Zeroing moves take the DTZ of the position before them
+            let result = if zeroing {
+                self.search(board, false).map(|(wdl, _)| -get_dtz_before_zeroing(wdl))
+            } else {
+                self.get_dtz(board).map(|dtz| -dtz)
+            };
+
+            let mate = result == Some(1) && is_checkmate(board);
+
+            board.undo_last_move();
+
+            let mut dtz = result?;
+
+            if mate {
+                min_dtz = 1;
+            }
+
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+
+            if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                min_dtz = dtz;
+            }
+        }
+
+        // Without legal moves the position is a checkmate
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+}
+
+fn get_dtz_before_zeroing(wdl: i32) -> i32 {
+    match wdl {
+        2 => 1,
+        1 => 101,
+        -1 => -101,
+        -2 => -1,
+        _ => 0,
+    }
+}
+
+fn is_checkmate(board: &mut Board) -> bool {
+    let no_moves = board.get_legal_moves().is_empty();
+
+    no_moves
+        && if board.is_white_move() {
+            board.is_white_king_in_check()
+        } else {
+            board.is_black_king_in_check()
+        }
+}
+
+/// Legal moves with one move for each promotion piece
+pub fn get_tablebase_moves(board: &mut Board) -> Vec<PieceMove> {
+    let white_move = board.is_white_move();
+
+    let mut moves = Vec::new();
+
+    for piece_move in board.get_legal_moves() {
+        if !piece_move.is_promotion() {
+            moves.push(piece_move);
+
+            continue;
+        }
+
+        for promotion in get_promotion_options(white_move) {
+            let mut promotion_move = piece_move.clone();
+
+            promotion_move.set_promotion_value(promotion);
+
+            moves.push(promotion_move);
+        }
+    }
+
+    moves
+}
+
+// Pieces with the squares counted from a1 and the piece codes of the tables
+fn get_table_pieces(board_state: &BoardState) -> Vec<(usize, u8)> {
+    let mut pieces = Vec::new();
+
+    for square in 0..64 {
+        let piece = board_state.get_piece((square ^ 56) as i8);
+
+        if piece == EMPTY_PIECE {
+            continue;
+        }
+
+        let code = match get_piece_type(piece) {
+            PieceType::Bishop => SYZYGY_BISHOP,
+            PieceType::King => SYZYGY_KING,
+            PieceType::Knight => SYZYGY_KNIGHT,
+            PieceType::Pawn => SYZYGY_PAWN,
+            PieceType::Queen => SYZYGY_QUEEN,
+            _ => SYZYGY_ROOK,
+        };
+
+        pieces.push((square, if is_white_piece(piece) { code } else { code | SYZYGY_BLACK }));
+    }
+
+    pieces
+}
+
+fn get_side_name(pieces: &[(usize, u8)], color: u8) -> String {
+    let piece_types: Vec<PieceType> = pieces
+        .iter()
+        .filter(|(_, piece)| piece & SYZYGY_BLACK == color)
+        .map(|(_, piece)| match piece & !SYZYGY_BLACK {
+            SYZYGY_BISHOP => PieceType::Bishop,
+            SYZYGY_KING => PieceType::King,
+            SYZYGY_KNIGHT => PieceType::Knight,
+            SYZYGY_PAWN => PieceType::Pawn,
+            SYZYGY_QUEEN => PieceType::Queen,
+            _ => PieceType::Rook,
+        })
+        .collect();
+
+    tablebase_utils::get_side_name(&piece_types)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::{
+        ai::endgame_tables::{generator::TableGenerator, Dtm, EndgameTables},
+        common::{board_utils::get_uci_move_notation, tablebase_error::TablebaseError},
+        game::board::Board,
+    };
+
+    use super::{table::tests::get_single_value_wdl_table, table::DTZ_MAGIC, Tablebase, Wdl, MAX_DTZ};
+
+    // KQvK tables where white always wins and needs 19 plies to checkmate,
+    // the DTZ table only stores white to move
+    fn write_tables(test_name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("rustmate-{}-{}", test_name, std::process::id()));
+
+        fs::create_dir_all(&directory).unwrap();
+
+        fs::write(directory.join("KQvK.rtbw"), get_single_value_wdl_table(4, 0)).unwrap();
+
+        let mut dtz_table = DTZ_MAGIC.to_vec();
+
+        // Moves to zeroing are stored in moves, not plies
+        dtz_table.extend_from_slice(&[0, 0, 0x66, 0x55, 0xEE, 0, 128, 9]);
+
+        fs::write(directory.join("KQvK.rtbz"), dtz_table).unwrap();
+
+        directory
+    }
+
+    fn board_of(fen: &str) -> Board {
+        let mut board = Board::new();
+
+        board.load_position(fen).unwrap();
+
+        board
+    }
+
+    #[test]
+    fn test_probe_tables() {
+        let directory = write_tables("probe-tables");
+
+        let tablebase = Tablebase::load(directory.to_str().unwrap()).unwrap();
+
+        assert_eq!(tablebase.get_max_pieces(), 3);
+
+        let mut white_to_move = board_of("8/8/3k4/8/8/8/8/4KQ2 w - - 0 1");
+        let mut black_to_move = board_of("8/8/3k4/8/8/8/8/4KQ2 b - - 0 1");
+        let mut black_stronger = board_of("8/8/3K4/8/8/8/8/4kq2 b - - 0 1");
+
+        assert_eq!(tablebase.probe_wdl(&mut white_to_move), Some(Wdl::Win));
+        assert_eq!(tablebase.probe_wdl(&mut black_to_move), Some(Wdl::Loss));
+        assert_eq!(tablebase.probe_wdl(&mut black_stronger), Some(Wdl::Win));
+
+        assert_eq!(tablebase.get_dtz(&mut white_to_move), Some(19));
+
+        // Found from the replies of black, the table only has white to move
+        assert_eq!(tablebase.get_dtz(&mut black_to_move), Some(-20));
+
+        // Taking the queen leaves the kings alone
+        let mut hanging_queen = board_of("8/8/8/8/8/8/4kQ2/7K b - - 0 1");
+
+        assert_eq!(tablebase.probe_wdl(&mut hanging_queen), Some(Wdl::Draw));
+
+        // Missing tables and castling rights can't be probed
+        let mut missing_table = board_of("8/8/3k4/8/8/8/8/4KR2 w - - 0 1");
+        let mut castling = board_of("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1");
+
+        assert_eq!(tablebase.probe_wdl(&mut missing_table), None);
+        assert_eq!(tablebase.probe_wdl(&mut castling), None);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_probe_root() {
+        let directory = write_tables("probe-root");
+
+        let tablebase = Tablebase::load(directory.to_str().unwrap()).unwrap();
+
+        let mut board = board_of("8/8/8/8/8/8/4k3/Q6K w - - 0 1");
+
+        let root_moves = tablebase.probe_root(&mut board).unwrap();
+
+        assert_eq!(root_moves.len(), board.get_legal_moves().len());
+
+        // Moves giving the queen away draw, the other ones win
+        for root_move in &root_moves {
+            let notation = get_uci_move_notation(&root_move.piece_move);
+
+            if ["a1d1", "a1e1", "a1f1"].contains(&notation.as_str()) {
+                assert_eq!(root_move.rank, 0, "{}", notation);
+            } else {
+                assert_eq!(root_move.rank, MAX_DTZ - 21, "{}", notation);
+            }
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(matches!(Tablebase::load(directory.to_str().unwrap()), Err(TablebaseError::Io(_))));
+    }
+
+    // Board with the pieces on random squares, None when the position is illegal
+    fn get_random_board(rng: &mut StdRng, letters: &[char]) -> Option<Board> {
+        let white_move = rng.gen_bool(0.5);
+
+        let mut squares = [None; 64];
+
+        for letter in letters {
+            let square = loop {
+                let square = rng.gen_range(0..64);
+
+                // Pawns can't stand on the first and last ranks
+                if squares[square].is_none() && (*letter != 'P' || (8..56).contains(&square)) {
+                    break square;
+                }
+            };
+
+            squares[square] = Some(*letter);
+        }
+
+        let mut placement = String::new();
+
+        for (square, letter) in squares.iter().enumerate() {
+            if square > 0 && square % 8 == 0 {
+                placement.push('/');
+            }
+
+            placement.push(letter.unwrap_or('1'));
+        }
+
+        // Runs of empty squares are counted in the notation
+        for empty_squares in (2..=8).rev() {
+            placement = placement.replace(&"1".repeat(empty_squares), &empty_squares.to_string());
+        }
+
+        let mut board = Board::new();
+
+        board
+            .load_position(&format!("{} {} - - 0 1", placement, if white_move { 'w' } else { 'b' }))
+            .ok()?;
+
+        Some(board)
+    }
+
+    // Checks the Syzygy tables against the distance to mate tables the engine generates,
+    // without pawns the winning side never zeroes before the mate
+    #[test]
+    #[ignore = "needs the KQvK, KRvK and KPvK Syzygy tables in resources/syzygy"]
+    fn test_probe_real_tables() {
+        let tablebase = Tablebase::load(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/syzygy")).unwrap();
+
+        assert_eq!(tablebase.get_max_pieces(), 3);
+
+        let mut mate_in_one = board_of("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1");
+        let mut hanging_rook = board_of("8/8/8/8/8/8/3kR3/7K b - - 0 1");
+        let mut king_in_front = board_of("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1");
+        let mut opposition = board_of("8/8/8/8/8/4k3/4P3/4K3 w - - 0 1");
+
+        assert_eq!(tablebase.probe_wdl(&mut mate_in_one), Some(Wdl::Win));
+        assert_eq!(tablebase.get_dtz(&mut mate_in_one), Some(1));
+        assert_eq!(tablebase.probe_wdl(&mut hanging_rook), Some(Wdl::Draw));
+        assert_eq!(tablebase.probe_wdl(&mut king_in_front), Some(Wdl::Loss));
+        assert_eq!(tablebase.probe_wdl(&mut opposition), Some(Wdl::Draw));
+
+        let directory = env::temp_dir().join(format!("rustmate-real-tables-{}", std::process::id()));
+
+        fs::create_dir_all(&directory).unwrap();
+
+        let mut generator = TableGenerator::new(directory.to_str().unwrap());
+
+        let mut rng = StdRng::seed_from_u64(17);
+
+        for (name, letters) in [("KQK", ['K', 'Q', 'k']), ("KRK", ['K', 'R', 'k']), ("KPK", ['K', 'P', 'k'])] {
+            generator.generate(name).unwrap();
+
+            let tables = EndgameTables::load(directory.to_str().unwrap()).unwrap();
+
+            let mut probed = 0;
+
+            while probed < 200 {
+                let Some(mut board) = get_random_board(&mut rng, &letters) else {
+                    continue;
+                };
+
+                let dtm = tables.probe(board.get_state_reference()).unwrap();
+                let fen = board.get_state_reference().get_fen();
+
+                let wdl = tablebase.probe_wdl(&mut board).unwrap();
+                let dtz = tablebase.get_dtz(&mut board).unwrap();
+
+                let (expected_wdl, sign, plies) = match dtm {
+                    Dtm::Draw => (Wdl::Draw, 0, 0),
+                    Dtm::Loss(plies) => (Wdl::Loss, -1, plies as i32),
+                    Dtm::Win(plies) => (Wdl::Win, 1, plies as i32),
+                };
+
+                assert_eq!(wdl, expected_wdl, "{}", fen);
+                assert_eq!(dtz.signum(), sign, "{}", fen);
+
+                // DTZ tables may round the plies up to whole moves, mated positions have a DTZ of -1
+                if name != "KPK" && dtm != Dtm::Draw {
+                    assert!([plies, plies + 1].contains(&dtz.abs()), "{} {}", fen, dtz);
+                }
+
+                probed += 1;
+            }
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
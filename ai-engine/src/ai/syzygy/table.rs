@@ -0,0 +1,1193 @@
+use std::sync::LazyLock;
+
+// Squares in this file follow the Syzygy convention, 0 is a1 and 63 is h8
+
+pub const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+pub const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+pub const MAX_TABLE_PIECES: usize = 7;
+
+// Flags of the table header
+const SPLIT_FLAG: u8 = 1;
+const HAS_PAWNS_FLAG: u8 = 2;
+
+// Flags of each compressed sub table
+const STM_FLAG: u8 = 1;
+const MAPPED_FLAG: u8 = 2;
+const WIN_PLIES_FLAG: u8 = 4;
+const LOSS_PLIES_FLAG: u8 = 8;
+const WIDE_FLAG: u8 = 16;
+const SINGLE_VALUE_FLAG: u8 = 128;
+
+// Index of the DTZ value map used for each WDL result, from loss to win
+const WDL_TO_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+
+// Piece codes used by the tables, black pieces have the 8 bit set
+pub const SYZYGY_PAWN: u8 = 1;
+pub const SYZYGY_KNIGHT: u8 = 2;
+pub const SYZYGY_BISHOP: u8 = 3;
+pub const SYZYGY_ROOK: u8 = 4;
+pub const SYZYGY_QUEEN: u8 = 5;
+pub const SYZYGY_KING: u8 = 6;
+pub const SYZYGY_BLACK: u8 = 8;
+
+/// Lookup tables of the position indexing, shared by every table
+struct IndexTables {
+    binomial: [[u64; 64]; 6],
+    kk_index: [[u64; 64]; 10],
+    lead_pawn_index: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+    map_a1d1d4: [u64; 64],
+    map_b1h1h7: [u64; 64],
+    map_pawns: [u64; 64],
+}
+
+static INDEX_TABLES: LazyLock<IndexTables> = LazyLock::new(IndexTables::new);
+
+fn get_file(square: usize) -> usize {
+    square % 8
+}
+
+fn get_rank(square: usize) -> usize {
+    square / 8
+}
+
+// Negative below the a1-h8 diagonal, positive above it
+fn get_diagonal_offset(square: usize) -> i32 {
+    get_rank(square) as i32 - get_file(square) as i32
+}
+
+fn is_king_distance_below_two(first_square: usize, second_square: usize) -> bool {
+    get_file(first_square).abs_diff(get_file(second_square)) <= 1
+        && get_rank(first_square).abs_diff(get_rank(second_square)) <= 1
+}
+
+impl IndexTables {
+    fn new() -> Self {
+        let mut tables = IndexTables {
+            binomial: [[0; 64]; 6],
+            kk_index: [[0; 64]; 10],
+            lead_pawn_index: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+            map_a1d1d4: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_pawns: [0; 64],
+        };
+
+        // Squares below the a1-h8 diagonal
+        let mut code = 0;
+
+        for square in 0..64 {
+            if get_diagonal_offset(square) < 0 {
+                tables.map_b1h1h7[square] = code;
+
+                code += 1;
+            }
+        }
+
+        // Squares of the a1-d1-d4 triangle, the ones on the diagonal come last
+        let mut diagonal = Vec::new();
+
+        code = 0;
+
+        for square in 0..=27 {
+            if get_diagonal_offset(square) < 0 && get_file(square) <= 3 {
+                tables.map_a1d1d4[square] = code;
+
+                code += 1;
+            } else if get_diagonal_offset(square) == 0 && get_file(square) <= 3 {
+                diagonal.push(square);
+            }
+        }
+
+        for square in diagonal {
+            tables.map_a1d1d4[square] = code;
+
+            code += 1;
+        }
+
+        // The 462 legal placements of two kings with the first one in the triangle,
+        // when the first king is on the diagonal the second one can't be above it
+        let mut both_on_diagonal = Vec::new();
+
+        code = 0;
+
+        for index in 0..10 {
+            for first_square in 0..=27 {
+                if tables.map_a1d1d4[first_square] != index as u64 || (index == 0 && first_square != 1) {
+                    continue;
+                }
+
+                for second_square in 0..64 {
+                    if is_king_distance_below_two(first_square, second_square) {
+                        continue;
+                    }
+
+                    let first_offset = get_diagonal_offset(first_square);
+                    let second_offset = get_diagonal_offset(second_square);
+
+                    if first_offset == 0 && second_offset > 0 {
+                        continue;
+                    }
+
+                    if first_offset == 0 && second_offset == 0 {
+                        both_on_diagonal.push((index, second_square));
+                    } else {
+                        tables.kk_index[index][second_square] = code;
+
+                        code += 1;
+                    }
+                }
+            }
+        }
+
+        for (index, square) in both_on_diagonal {
+            tables.kk_index[index][square] = code;
+
+            code += 1;
+        }
+
+        // Ways to choose k pieces among n squares
+        tables.binomial[0][0] = 1;
+
+        for n in 1..64 {
+            for k in 0..6.min(n + 1) {
+                tables.binomial[k][n] = if k > 0 { tables.binomial[k - 1][n - 1] } else { 0 }
+                    + if k < n { tables.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // Pawn squares a2-h7 numbered from the edges, the leading pawn is the one with the
+        // highest number, the other pawns of its group are only allowed on lower numbers
+        let mut available_squares = 47;
+
+        for lead_pawns_count in 1..=5 {
+            for file in 0..4 {
+                let mut index = 0;
+
+                for rank in 1..=6 {
+                    let square = rank * 8 + file;
+
+                    if lead_pawns_count == 1 {
+                        tables.map_pawns[square] = available_squares;
+                        tables.map_pawns[square ^ 7] = available_squares - 1;
+
+                        available_squares = available_squares.saturating_sub(2);
+                    }
+
+                    tables.lead_pawn_index[lead_pawns_count][square] = index;
+
+                    index += tables.binomial[lead_pawns_count - 1][tables.map_pawns[square] as usize];
+                }
+
+                tables.lead_pawns_size[lead_pawns_count][file] = index;
+            }
+        }
+
+        tables
+    }
+}
+
+fn read_u16_le(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+// Compressed data past the end of a block is never used, it reads as zeroes
+fn read_u32_be(data: &[u8], offset: usize) -> u32 {
+    match data.get(offset..offset + 4) {
+        Some(bytes) => u32::from_be_bytes(bytes.try_into().unwrap()),
+        None => 0,
+    }
+}
+
+/// Result of looking up a position in a single table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableProbe {
+    // DTZ tables only store one side to move, the other one needs a search
+    ChangeSideToMove,
+    Value(i32),
+}
+
+/// Material of a table, read from its name like `KRPvKR`
+#[derive(Debug, Clone)]
+pub struct TableMaterial {
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    // Pawns of the leading color first, the one with fewer pawns when both sides have some
+    pawn_counts: [usize; 2],
+    piece_count: usize,
+    symmetric: bool,
+}
+
+impl TableMaterial {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (white, black) = name.split_once('v')?;
+
+        let mut counts = [[0usize; 7]; 2];
+
+        for (color, pieces) in [white, black].iter().enumerate() {
+            for piece in pieces.chars() {
+                counts[color][get_piece_code(piece)? as usize] += 1;
+            }
+
+            if counts[color][SYZYGY_KING as usize] != 1 {
+                return None;
+            }
+        }
+
+        let piece_count = white.len() + black.len();
+
+        if piece_count > MAX_TABLE_PIECES {
+            return None;
+        }
+
+        let white_pawns = counts[0][SYZYGY_PAWN as usize];
+        let black_pawns = counts[1][SYZYGY_PAWN as usize];
+
+        // The index tables only go up to five pawns of a color
+        if white_pawns > 5 || black_pawns > 5 {
+            return None;
+        }
+
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+
+        Some(TableMaterial {
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: counts
+                .iter()
+                .any(|color_counts| color_counts[SYZYGY_PAWN as usize..SYZYGY_KING as usize].contains(&1)),
+            pawn_counts: if white_leads {
+                [white_pawns, black_pawns]
+            } else {
+                [black_pawns, white_pawns]
+            },
+            piece_count,
+            symmetric: white == black,
+        })
+    }
+
+    pub fn get_piece_count(&self) -> usize {
+        self.piece_count
+    }
+}
+
+fn get_piece_code(piece: char) -> Option<u8> {
+    match piece {
+        'P' => Some(SYZYGY_PAWN),
+        'N' => Some(SYZYGY_KNIGHT),
+        'B' => Some(SYZYGY_BISHOP),
+        'R' => Some(SYZYGY_ROOK),
+        'Q' => Some(SYZYGY_QUEEN),
+        'K' => Some(SYZYGY_KING),
+        _ => None,
+    }
+}
+
+/// Compressed values of one side to move and leading pawn file
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    base64: Vec<u64>,
+    block_length: usize,
+    block_length_size: usize,
+    block_size: u64,
+    blocks: usize,
+    blocks_count: usize,
+    btree: usize,
+    flags: u8,
+    group_index: [u64; MAX_TABLE_PIECES + 1],
+    group_len: [usize; MAX_TABLE_PIECES + 1],
+    lowest_symbol: usize,
+    map_index: [usize; 4],
+    max_symbol_len: u8,
+    // Holds the value of the whole table when it has a single one
+    min_symbol_len: u8,
+    pieces: [u8; MAX_TABLE_PIECES],
+    sparse_index: usize,
+    sparse_index_size: usize,
+    span: u64,
+    symbol_len: Vec<u8>,
+}
+
+impl PairsData {
+    fn get_left_symbol(&self, data: &[u8], symbol: usize) -> usize {
+        let entry = self.btree + symbol * 3;
+
+        ((data[entry + 1] as usize & 0xF) << 8) | data[entry] as usize
+    }
+
+    fn get_right_symbol(&self, data: &[u8], symbol: usize) -> usize {
+        let entry = self.btree + symbol * 3;
+
+        ((data[entry + 2] as usize) << 4) | (data[entry + 1] as usize >> 4)
+    }
+
+    fn get_lowest_symbol(&self, data: &[u8], len: usize) -> Option<u64> {
+        read_u16_le(data, self.lowest_symbol + len * 2).map(u64::from)
+    }
+
+    fn get_table_size(&self) -> u64 {
+        let groups = self.group_len.iter().position(|&len| len == 0).unwrap_or(MAX_TABLE_PIECES);
+
+        self.group_index[groups]
+    }
+
+    // Number of values a symbol expands to, minus one
+    fn set_symbol_len(&mut self, data: &[u8], symbol: usize, visited: &mut [bool]) -> u8 {
+        visited[symbol] = true;
+
+        let right = self.get_right_symbol(data, symbol);
+
+        if right == 0xFFF {
+            return 0;
+        }
+
+        let left = self.get_left_symbol(data, symbol);
+
+        for child in [left, right] {
+            if child < visited.len() && !visited[child] {
+                self.symbol_len[child] = self.set_symbol_len(data, child, visited);
+            }
+        }
+
+        let left_len = self.symbol_len.get(left).copied().unwrap_or(0);
+        let right_len = self.symbol_len.get(right).copied().unwrap_or(0);
+
+        left_len.wrapping_add(right_len).wrapping_add(1)
+    }
+
+    /// Value at the given index, the symbols are canonical Huffman codes expanding by
+    /// recursive pairing to one or more values
+    fn decompress(&self, data: &[u8], index: u64) -> Option<i32> {
+        if self.flags & SINGLE_VALUE_FLAG != 0 {
+            return Some(self.min_symbol_len as i32);
+        }
+
+        // The sparse index points to the block and offset of the value in the middle of each span
+        let span_index = (index / self.span) as usize;
+
+        if span_index >= self.sparse_index_size {
+            return None;
+        }
+
+        let sparse_entry = self.sparse_index + span_index * 6;
+
+        let mut block = read_u32_le(data, sparse_entry)? as usize;
+        let mut offset = read_u16_le(data, sparse_entry + 4)? as i64;
+
+        offset += (index % self.span) as i64 - (self.span / 2) as i64;
+
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+
+            offset += read_u16_le(data, self.block_length + block * 2)? as i64 + 1;
+        }
+
+        loop {
+            let block_length = read_u16_le(data, self.block_length + block * 2)? as i64;
+
+            if offset <= block_length {
+                break;
+            }
+
+            offset -= block_length + 1;
+            block += 1;
+        }
+
+        let mut position = self.blocks + block * self.block_size as usize;
+
+        let mut buffer = ((read_u32_be(data, position) as u64) << 32) | read_u32_be(data, position + 4) as u64;
+        let mut buffer_size = 64;
+
+        position += 8;
+
+        let mut symbol;
+
+        loop {
+            let mut len = 0;
+
+            while buffer < *self.base64.get(len)? {
+                len += 1;
+            }
+
+            symbol = ((buffer - self.base64[len]) >> (64 - len - self.min_symbol_len as usize)) as usize;
+            symbol += self.get_lowest_symbol(data, len)? as usize;
+
+            let symbol_len = *self.symbol_len.get(symbol)? as i64;
+
+            if offset < symbol_len + 1 {
+                break;
+            }
+
+            offset -= symbol_len + 1;
+
+            let len = len + self.min_symbol_len as usize;
+
+            buffer <<= len;
+            buffer_size -= len;
+
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (read_u32_be(data, position) as u64) << (64 - buffer_size);
+
+                position += 4;
+            }
+        }
+
+        // Children of a pair are adjacent, so the value is on the side its offset falls into
+        while self.symbol_len[symbol] != 0 {
+            let left = self.get_left_symbol(data, symbol);
+            let left_len = *self.symbol_len.get(left)? as i64;
+
+            if offset < left_len + 1 {
+                symbol = left;
+            } else {
+                offset -= left_len + 1;
+                symbol = self.get_right_symbol(data, symbol);
+            }
+        }
+
+        Some(self.get_left_symbol(data, symbol) as i32)
+    }
+}
+
+/// A WDL or DTZ table file read in memory
+#[derive(Debug)]
+pub struct Table {
+    data: Vec<u8>,
+    dtz: bool,
+    dtz_map: usize,
+    material: TableMaterial,
+    // Indexed by side to move then by file of the leading pawn
+    pairs: Vec<Vec<PairsData>>,
+}
+
+impl Table {
+    /// Parses the header of the table, None when the data is not a valid table
+    pub fn new(data: Vec<u8>, material: TableMaterial, dtz: bool) -> Option<Self> {
+        let magic = if dtz { DTZ_MAGIC } else { WDL_MAGIC };
+
+        if data.get(0..4)? != magic {
+            return None;
+        }
+
+        let mut table = Table {
+            data,
+            dtz,
+            dtz_map: 0,
+            material,
+            pairs: Vec::new(),
+        };
+
+        table.read_header()?;
+
+        Some(table)
+    }
+
+    fn read_header(&mut self) -> Option<()> {
+        let data = &self.data;
+        let material = &self.material;
+
+        let flags = *data.get(4)?;
+
+        if (flags & HAS_PAWNS_FLAG != 0) != material.has_pawns
+            || (!self.dtz && (flags & SPLIT_FLAG != 0) == material.symmetric)
+        {
+            return None;
+        }
+
+        let sides = if !self.dtz && !material.symmetric { 2 } else { 1 };
+        let files = if material.has_pawns { 4 } else { 1 };
+
+        // Pawns on both sides
+        let both_pawns = material.has_pawns && material.pawn_counts[1] > 0;
+
+        let mut pairs = vec![vec![PairsData::default(); files]; sides];
+        let mut offset = 5;
+
+        for file in 0..files {
+            let first = *data.get(offset)?;
+            let second = if both_pawns { *data.get(offset + 1)? } else { 0xFF };
+
+            let orders = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+
+            offset += 1 + both_pawns as usize;
+
+            for piece_index in 0..material.piece_count {
+                let pieces = *data.get(offset)?;
+
+                for (side, side_pairs) in pairs.iter_mut().enumerate() {
+                    side_pairs[file].pieces[piece_index] = if side == 1 { pieces >> 4 } else { pieces & 0xF };
+                }
+
+                offset += 1;
+            }
+
+            for (side, side_pairs) in pairs.iter_mut().enumerate() {
+                set_groups(&mut side_pairs[file], material, orders[side], file);
+            }
+        }
+
+        offset += offset & 1;
+
+        for file in 0..files {
+            for side_pairs in pairs.iter_mut() {
+                offset = set_sizes(&mut side_pairs[file], data, offset)?;
+            }
+        }
+
+        if self.dtz {
+            self.dtz_map = offset;
+
+            for file_pairs in pairs[0].iter_mut() {
+                if file_pairs.flags & MAPPED_FLAG == 0 {
+                    continue;
+                }
+
+                for map_index in file_pairs.map_index.iter_mut() {
+                    if file_pairs.flags & WIDE_FLAG != 0 {
+                        offset += offset & 1;
+
+                        *map_index = (offset - self.dtz_map) / 2 + 1;
+                        offset += 2 * read_u16_le(data, offset)? as usize + 2;
+                    } else {
+                        *map_index = offset - self.dtz_map + 1;
+                        offset += *data.get(offset)? as usize + 1;
+                    }
+                }
+            }
+
+            offset += offset & 1;
+        }
+
+        for file in 0..files {
+            for side_pairs in pairs.iter_mut() {
+                side_pairs[file].sparse_index = offset;
+
+                offset += side_pairs[file].sparse_index_size * 6;
+            }
+        }
+
+        for file in 0..files {
+            for side_pairs in pairs.iter_mut() {
+                side_pairs[file].block_length = offset;
+
+                offset += side_pairs[file].block_length_size * 2;
+            }
+        }
+
+        for file in 0..files {
+            for side_pairs in pairs.iter_mut() {
+                // Blocks are aligned on 64 bytes
+                offset = (offset + 0x3F) & !0x3F;
+
+                side_pairs[file].blocks = offset;
+
+                offset += side_pairs[file].blocks_count * side_pairs[file].block_size as usize;
+
+                if side_pairs[file].blocks_count > 0 && offset > data.len() {
+                    return None;
+                }
+            }
+        }
+
+        self.pairs = pairs;
+
+        Some(())
+    }
+
+    /// Index of the position in the table with the sub table storing it, `pieces` holds
+    /// the square and Syzygy code of every piece. None for the side the table doesn't store.
+    fn get_index(&self, pieces: &[(usize, u8)], white_move: bool, black_stronger: bool) -> Option<(&PairsData, usize, u64)> {
+        let index_tables = &*INDEX_TABLES;
+        let material = &self.material;
+
+        // Tables are stored with white as the stronger side and only for white to move
+        // when both sides have the same pieces, the other positions are mirrored
+        let flip = black_stronger || (material.symmetric && !white_move);
+
+        let flip_color = if flip { SYZYGY_BLACK } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let side_to_move = flip as usize ^ !white_move as usize;
+
+        let mut squares = Vec::with_capacity(pieces.len());
+        let mut codes = Vec::with_capacity(pieces.len());
+        let mut lead_pawns_count = 0;
+        let mut file = 0;
+
+        if material.has_pawns {
+            let lead_pawn = self.pairs[0][0].pieces[0] ^ flip_color;
+
+            for &(square, code) in pieces.iter().filter(|(_, code)| *code == lead_pawn) {
+                squares.push(square ^ flip_squares);
+                codes.push(code ^ flip_color);
+            }
+
+            lead_pawns_count = squares.len();
+
+            let lead_position = (0..lead_pawns_count)
+                .rev()
+                .max_by_key(|&index| index_tables.map_pawns[squares[index]])?;
+
+            squares.swap(0, lead_position);
+
+            file = get_file(squares[0]).min(7 - get_file(squares[0]));
+        }
+
+        let pairs_data = &self.pairs[if self.pairs.len() == 1 { 0 } else { side_to_move }][file];
+
+        // Symmetric tables without pawns store both sides to move in the same way
+        let one_sided = !material.symmetric || material.has_pawns;
+
+        if self.dtz && one_sided && (pairs_data.flags & STM_FLAG) as usize != side_to_move {
+            return None;
+        }
+
+        let lead_pawn = if material.has_pawns { Some(self.pairs[0][0].pieces[0] ^ flip_color) } else { None };
+
+        for &(square, code) in pieces.iter().filter(|(_, code)| Some(*code) != lead_pawn) {
+            squares.push(square ^ flip_squares);
+            codes.push(code ^ flip_color);
+        }
+
+        let size = squares.len();
+
+        // Same piece order as the table
+        for i in lead_pawns_count..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|&j| pairs_data.pieces[i] == codes[j]) {
+                codes.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // The leading piece goes to the a1-d1-d4 triangle, or to the a-d files with pawns
+        if get_file(squares[0]) > 3 {
+            for square in squares.iter_mut() {
+                *square ^= 7;
+            }
+        }
+
+        let mut index;
+
+        if material.has_pawns {
+            index = index_tables.lead_pawn_index[lead_pawns_count][squares[0]];
+
+            squares[1..lead_pawns_count].sort_by_key(|&square| index_tables.map_pawns[square]);
+
+            for (i, &square) in squares.iter().enumerate().take(lead_pawns_count).skip(1) {
+                index += index_tables.binomial[i][index_tables.map_pawns[square] as usize];
+            }
+        } else {
+            if get_rank(squares[0]) > 3 {
+                for square in squares.iter_mut() {
+                    *square ^= 56;
+                }
+            }
+
+            // The first piece of the leading group off the diagonal goes below it
+            for i in 0..pairs_data.group_len[0] {
+                let offset = get_diagonal_offset(squares[i]);
+
+                if offset == 0 {
+                    continue;
+                }
+
+                if offset > 0 {
+                    for square in squares[i..].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+
+                break;
+            }
+
+            index = if material.has_unique_pieces {
+                get_unique_pieces_index(&squares)
+            } else {
+                index_tables.kk_index[index_tables.map_a1d1d4[squares[0]] as usize][squares[1]]
+            };
+        }
+
+        index *= pairs_data.group_index[0];
+
+        let mut group_start = pairs_data.group_len[0];
+        let mut remaining_pawns = material.has_pawns && material.pawn_counts[1] > 0;
+        let mut next = 1;
+
+        // The other groups are encoded by their squares in ascending order, skipping the
+        // squares taken by the previous groups
+        while pairs_data.group_len[next] != 0 {
+            let group_end = group_start + pairs_data.group_len[next];
+
+            squares[group_start..group_end].sort_unstable();
+
+            let mut group_index = 0;
+
+            for i in 0..pairs_data.group_len[next] {
+                let square = squares[group_start + i];
+                let adjust = squares[..group_start].iter().filter(|&&previous| square > previous).count();
+
+                group_index += index_tables.binomial[i + 1][square - adjust - 8 * remaining_pawns as usize];
+            }
+
+            remaining_pawns = false;
+
+            index += group_index * pairs_data.group_index[next];
+
+            group_start = group_end;
+            next += 1;
+        }
+
+        Some((pairs_data, file, index))
+    }
+
+    /// Value stored for the position, a WDL score from -2 to 2 or the DTZ in plies.
+    /// DTZ tables need the WDL score of the position to read their value.
+    pub fn probe(&self, pieces: &[(usize, u8)], white_move: bool, black_stronger: bool, wdl: i32) -> Option<TableProbe> {
+        let Some((pairs_data, file, index)) = self.get_index(pieces, white_move, black_stronger) else {
+            return Some(TableProbe::ChangeSideToMove);
+        };
+
+        let value = pairs_data.decompress(&self.data, index)?;
+
+        if !self.dtz {
+            return Some(TableProbe::Value(value - 2));
+        }
+
+        Some(TableProbe::Value(self.map_dtz_value(file, value, wdl)?))
+    }
+
+    fn map_dtz_value(&self, file: usize, mut value: i32, wdl: i32) -> Option<i32> {
+        let pairs_data = &self.pairs[0][file];
+        let flags = pairs_data.flags;
+
+        if flags & MAPPED_FLAG != 0 {
+            let map_index = pairs_data.map_index[WDL_TO_MAP[(wdl + 2) as usize]] + value as usize;
+
+            value = if flags & WIDE_FLAG != 0 {
+                read_u16_le(&self.data, self.dtz_map + map_index * 2)? as i32
+            } else {
+                *self.data.get(self.dtz_map + map_index)? as i32
+            };
+        }
+
+        // Values are stored in moves unless the table says they are plies
+        if (wdl == 2 && flags & WIN_PLIES_FLAG == 0) || (wdl == -2 && flags & LOSS_PLIES_FLAG == 0) || wdl.abs() == 1 {
+            value *= 2;
+        }
+
+        Some(value + 1)
+    }
+}
+
+// Three unique pieces including the kings are encoded together
+fn get_unique_pieces_index(squares: &[usize]) -> u64 {
+    let index_tables = &*INDEX_TABLES;
+
+    let adjust1 = (squares[1] > squares[0]) as u64;
+    let adjust2 = (squares[2] > squares[0]) as u64 + (squares[2] > squares[1]) as u64;
+
+    let square1 = squares[1] as u64;
+    let square2 = squares[2] as u64;
+
+    if get_diagonal_offset(squares[0]) != 0 {
+        (index_tables.map_a1d1d4[squares[0]] * 63 + square1 - adjust1) * 62 + square2 - adjust2
+    } else if get_diagonal_offset(squares[1]) != 0 {
+        (6 * 63 + get_rank(squares[0]) as u64 * 28 + index_tables.map_b1h1h7[squares[1]]) * 62 + square2 - adjust2
+    } else if get_diagonal_offset(squares[2]) != 0 {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + get_rank(squares[0]) as u64 * 7 * 28
+            + (get_rank(squares[1]) as u64 - adjust1) * 28
+            + index_tables.map_b1h1h7[squares[2]]
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + get_rank(squares[0]) as u64 * 7 * 6
+            + (get_rank(squares[1]) as u64 - adjust1) * 6
+            + get_rank(squares[2]) as u64
+            - adjust2
+    }
+}
+
+// Groups of identical pieces and the factor of each one in the index, the groups can be
+// encoded in a different order than they are listed
+fn set_groups(pairs_data: &mut PairsData, material: &TableMaterial, order: [u8; 2], file: usize) {
+    let index_tables = &*INDEX_TABLES;
+
+    let mut first_len: i32 = if material.has_pawns {
+        0
+    } else if material.has_unique_pieces {
+        3
+    } else {
+        2
+    };
+
+    let mut groups = 0;
+
+    pairs_data.group_len[0] = 1;
+
+    for i in 1..material.piece_count {
+        first_len -= 1;
+
+        if first_len > 0 || pairs_data.pieces[i] == pairs_data.pieces[i - 1] {
+            pairs_data.group_len[groups] += 1;
+        } else {
+            groups += 1;
+
+            pairs_data.group_len[groups] = 1;
+        }
+    }
+
+    groups += 1;
+
+    pairs_data.group_len[groups] = 0;
+
+    let both_pawns = material.has_pawns && material.pawn_counts[1] > 0;
+
+    let mut next = if both_pawns { 2 } else { 1 };
+    let mut free_squares = 64 - pairs_data.group_len[0] - if both_pawns { pairs_data.group_len[1] } else { 0 };
+    let mut index = 1u64;
+    let mut k = 0;
+
+    while next < groups || k == order[0] as usize || k == order[1] as usize {
+        if k == order[0] as usize {
+            pairs_data.group_index[0] = index;
+
+            index *= if material.has_pawns {
+                index_tables.lead_pawns_size[pairs_data.group_len[0]][file]
+            } else if material.has_unique_pieces {
+                31332
+            } else {
+                462
+            };
+        } else if k == order[1] as usize {
+            pairs_data.group_index[1] = index;
+
+            index *= index_tables.binomial[pairs_data.group_len[1]][48 - pairs_data.group_len[0]];
+        } else {
+            pairs_data.group_index[next] = index;
+
+            index *= index_tables.binomial[pairs_data.group_len[next]][free_squares];
+
+            free_squares -= pairs_data.group_len[next];
+            next += 1;
+        }
+
+        k += 1;
+    }
+
+    pairs_data.group_index[groups] = index;
+}
+
+// Reads the compression parameters, returns the offset after them
+fn set_sizes(pairs_data: &mut PairsData, data: &[u8], mut offset: usize) -> Option<usize> {
+    pairs_data.flags = *data.get(offset)?;
+
+    offset += 1;
+
+    if pairs_data.flags & SINGLE_VALUE_FLAG != 0 {
+        pairs_data.min_symbol_len = *data.get(offset)?;
+
+        return Some(offset + 1);
+    }
+
+    let table_size = pairs_data.get_table_size();
+
+    pairs_data.block_size = 1u64.checked_shl(*data.get(offset)? as u32)?;
+    pairs_data.span = 1u64.checked_shl(*data.get(offset + 1)? as u32)?;
+    pairs_data.sparse_index_size = table_size.div_ceil(pairs_data.span) as usize;
+
+    let padding = *data.get(offset + 2)? as usize;
+
+    pairs_data.blocks_count = read_u32_le(data, offset + 3)? as usize;
+    pairs_data.block_length_size = pairs_data.blocks_count + padding;
+    pairs_data.max_symbol_len = *data.get(offset + 7)?;
+    pairs_data.min_symbol_len = *data.get(offset + 8)?;
+    pairs_data.lowest_symbol = offset + 9;
+
+    if pairs_data.max_symbol_len < pairs_data.min_symbol_len || pairs_data.min_symbol_len == 0 {
+        return None;
+    }
+
+    let lengths = (pairs_data.max_symbol_len - pairs_data.min_symbol_len) as usize + 1;
+
+    // Canonical Huffman codes, longer codes have lower values. base64 holds the lowest
+    // code of each length left aligned on 64 bits, so the length of the code at the
+    // start of a buffer is the first one with a base not above it.
+    let mut base64 = vec![0u64; lengths];
+
+    for i in (0..lengths - 1).rev() {
+        let lowest = pairs_data.get_lowest_symbol(data, i)?;
+        let next_lowest = pairs_data.get_lowest_symbol(data, i + 1)?;
+
+        base64[i] = (base64[i + 1].wrapping_add(lowest).wrapping_sub(next_lowest)) / 2;
+    }
+
+    for (i, base) in base64.iter_mut().enumerate() {
+        *base <<= 64 - i - pairs_data.min_symbol_len as usize;
+    }
+
+    pairs_data.base64 = base64;
+
+    offset = pairs_data.lowest_symbol + lengths * 2;
+
+    let symbols = read_u16_le(data, offset)? as usize;
+
+    offset += 2;
+
+    pairs_data.btree = offset;
+
+    data.get(offset..offset + symbols * 3)?;
+
+    pairs_data.symbol_len = vec![0; symbols];
+
+    let mut visited = vec![false; symbols];
+
+    for symbol in 0..symbols {
+        if !visited[symbol] {
+            pairs_data.symbol_len[symbol] = pairs_data.set_symbol_len(data, symbol, &mut visited);
+        }
+    }
+
+    Some(offset + symbols * 3 + (symbols & 1))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        is_king_distance_below_two, Table, TableMaterial, TableProbe, INDEX_TABLES,
+        SYZYGY_BLACK, SYZYGY_KING, SYZYGY_PAWN, SYZYGY_QUEEN, WDL_MAGIC,
+    };
+
+    /// WDL table of KQvK storing a single value for each side to move
+    pub fn get_single_value_wdl_table(white_value: u8, black_value: u8) -> Vec<u8> {
+        let mut data = WDL_MAGIC.to_vec();
+
+        // Split table, piece order and padding
+        data.extend_from_slice(&[1, 0, 0x66, 0x55, 0xEE, 0]);
+
+        // Both sides store a single value
+        data.extend_from_slice(&[128, white_value, 128, black_value]);
+
+        data
+    }
+
+    // Compressed KQvK table where every value is one bit, the positions with an index
+    // multiple of 3 are drawn and white wins the other ones
+    fn get_compressed_wdl_table() -> Vec<u8> {
+        let table_size: usize = 31332;
+        let values_per_block = 256;
+        let blocks = table_size.div_ceil(values_per_block);
+        let span = 1024;
+
+        let mut data = WDL_MAGIC.to_vec();
+
+        data.extend_from_slice(&[1, 0, 0x66, 0x55, 0xEE, 0]);
+
+        // White to move: 32 bytes blocks, spans of 1024 values, no padding
+        data.extend_from_slice(&[0, 5, 10, 0]);
+        data.extend_from_slice(&(blocks as u32).to_le_bytes());
+
+        // Symbols of a single bit, the lowest one is 0
+        data.extend_from_slice(&[1, 1, 0, 0]);
+
+        // Two leaves holding a draw and a win
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&[2, 0xF0, 0xFF, 4, 0xF0, 0xFF]);
+
+        // Black to move
+        data.extend_from_slice(&[128, 2]);
+
+        // Sparse index with the block and offset in the middle of each span
+        for span_index in 0..table_size.div_ceil(span) {
+            let index = span_index * span + span / 2;
+
+            data.extend_from_slice(&((index / values_per_block) as u32).to_le_bytes());
+            data.extend_from_slice(&((index % values_per_block) as u16).to_le_bytes());
+        }
+
+        for block in 0..blocks {
+            let values = values_per_block.min(table_size - block * values_per_block);
+
+            data.extend_from_slice(&((values - 1) as u16).to_le_bytes());
+        }
+
+        data.resize(data.len().div_ceil(64) * 64, 0);
+
+        for block in 0..blocks {
+            let mut bytes = [0u8; 32];
+
+            for value in 0..values_per_block {
+                let index = block * values_per_block + value;
+
+                // Wins are the code 1
+                if index < table_size && index % 3 != 0 {
+                    bytes[value / 8] |= 0x80 >> (value % 8);
+                }
+            }
+
+            data.extend_from_slice(&bytes);
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_index_tables() {
+        let index_tables = &*INDEX_TABLES;
+
+        let king_placements = index_tables.kk_index.iter().flatten().max().unwrap() + 1;
+
+        assert_eq!(king_placements, 462);
+
+        // One leading pawn on each of the 6 ranks of its file
+        assert!(index_tables.lead_pawns_size[1].iter().all(|&size| size == 6));
+
+        assert_eq!(index_tables.map_pawns[8], 47);
+        assert_eq!(index_tables.map_pawns[15], 46);
+        assert_eq!(index_tables.binomial[2][62], 1891);
+    }
+
+    #[test]
+    fn test_unique_index_of_symmetric_positions() {
+        let table = Table::new(
+            get_single_value_wdl_table(4, 0),
+            TableMaterial::from_name("KQvK").unwrap(),
+            false,
+        )
+        .unwrap();
+
+        // Positions sharing an index must be mirrors of each other
+        let mut positions: HashMap<u64, Vec<[usize; 3]>> = HashMap::new();
+
+        for white_king in 0..64 {
+            for black_king in 0..64 {
+                if is_king_distance_below_two(white_king, black_king) {
+                    continue;
+                }
+
+                for queen in (0..64).filter(|&queen| queen != white_king && queen != black_king) {
+                    let pieces = [(white_king, SYZYGY_KING), (queen, SYZYGY_QUEEN), (black_king, SYZYGY_KING | SYZYGY_BLACK)];
+
+                    let (_, _, index) = table.get_index(&pieces, true, false).unwrap();
+
+                    assert!(index < 31332);
+
+                    positions.entry(index).or_default().push([white_king, queen, black_king]);
+                }
+            }
+        }
+
+        let mirror = |squares: [usize; 3], symmetry: usize| {
+            squares.map(|square| {
+                let square = if symmetry & 1 != 0 { square ^ 7 } else { square };
+                let square = if symmetry & 2 != 0 { square ^ 56 } else { square };
+
+                if symmetry & 4 != 0 {
+                    ((square >> 3) | (square << 3)) & 63
+                } else {
+                    square
+                }
+            })
+        };
+
+        for same_index in positions.values() {
+            let first = same_index[0];
+
+            for squares in same_index {
+                assert!((0..8).any(|symmetry| mirror(first, symmetry) == *squares), "{:?} {:?}", first, squares);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pawn_index_of_mirrored_positions() {
+        let mut data = WDL_MAGIC.to_vec();
+
+        // Split table with pawns, each file of the leading pawn orders the pieces the same way
+        data.push(3);
+
+        for _ in 0..4 {
+            data.extend_from_slice(&[0, 0x11, 0x66, 0xEE]);
+        }
+
+        data.push(0);
+
+        for _ in 0..8 {
+            data.extend_from_slice(&[128, 4]);
+        }
+
+        let table = Table::new(data, TableMaterial::from_name("KPvK").unwrap(), false).unwrap();
+
+        // Only the mirror along the files keeps the pawn moving the same way
+        let mut positions: HashMap<(usize, u64), [usize; 3]> = HashMap::new();
+
+        for pawn in 8..56 {
+            for white_king in (0..64).filter(|&white_king| white_king != pawn) {
+                for black_king in (0..64).filter(|&black_king| black_king != pawn) {
+                    if is_king_distance_below_two(white_king, black_king) {
+                        continue;
+                    }
+
+                    let pieces = [(white_king, SYZYGY_KING), (pawn, SYZYGY_PAWN), (black_king, SYZYGY_KING | SYZYGY_BLACK)];
+
+                    let (pairs_data, file, index) = table.get_index(&pieces, true, false).unwrap();
+
+                    assert!(index < pairs_data.get_table_size());
+
+                    let squares = [white_king, pawn, black_king];
+
+                    if let Some(previous) = positions.insert((file, index), squares) {
+                        assert_eq!(previous.map(|square| square ^ 7), squares);
+                    }
+                }
+            }
+        }
+
+        assert_eq!(table.pairs[0][2].get_table_size(), 6 * 63 * 62);
+    }
+
+    #[test]
+    fn test_decompress_values() {
+        let table = Table::new(
+            get_compressed_wdl_table(),
+            TableMaterial::from_name("KQvK").unwrap(),
+            false,
+        )
+        .unwrap();
+
+        let pairs_data = &table.pairs[0][0];
+
+        for index in [0, 1, 2, 3, 255, 256, 511, 512, 1000, 1023, 1024, 1536, 20000, 31330, 31331] {
+            let expected = if index % 3 == 0 { 2 } else { 4 };
+
+            assert_eq!(pairs_data.decompress(&table.data, index), Some(expected), "index {}", index);
+        }
+
+        assert_eq!(table.pairs[1][0].decompress(&table.data, 12345), Some(2));
+
+        let pieces = [(4, SYZYGY_KING), (3, SYZYGY_QUEEN), (60, SYZYGY_KING | SYZYGY_BLACK)];
+
+        assert!(matches!(table.probe(&pieces, false, false, 0), Some(TableProbe::Value(0))));
+    }
+
+    #[test]
+    fn test_reject_invalid_tables() {
+        let material = TableMaterial::from_name("KQvK").unwrap();
+
+        assert!(Table::new(vec![0; 32], material.clone(), false).is_none());
+        assert!(Table::new(get_single_value_wdl_table(4, 0)[..8].to_vec(), material.clone(), false).is_none());
+        assert!(Table::new(get_single_value_wdl_table(4, 0), material, true).is_none());
+
+        assert!(TableMaterial::from_name("KQQvKX").is_none());
+        assert!(TableMaterial::from_name("KQvQ").is_none());
+        assert!(TableMaterial::from_name("KRPvKR").unwrap().has_pawns);
+    }
+}
@@ -2,16 +2,51 @@ use std::collections::HashMap;
 
 use crate::common::piece_move::PieceMove;
 
+/// What the value of a search tells about the real score of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    // Failed high, the score is at least the value
+    Lower,
+    // Failed low, the score is at most the value
+    Upper,
+}
+
+impl Bound {
+    /// Bound of the value a search with the given window returned
+    pub fn from_window(value: f32, alpha: f32, beta: f32) -> Self {
+        if value >= beta {
+            Bound::Lower
+        } else if value <= alpha {
+            Bound::Upper
+        } else {
+            Bound::Exact
+        }
+    }
+}
+
 pub struct TranspositionTableEntry {
     pub depth: u8,
     pub value: f32,
+    pub bound: Bound,
     pub best_move: Option<PieceMove>,
 }
 
 impl TranspositionTableEntry {
+    /// Whether the value answers a search of the given depth and window without searching again
+    pub fn is_usable(&self, depth: u8, alpha: f32, beta: f32) -> bool {
+        self.depth >= depth
+            && match self.bound {
+                Bound::Exact => true,
+                Bound::Lower => self.value >= beta,
+                Bound::Upper => self.value <= alpha,
+            }
+    }
+
     pub fn estimated_size(&self) -> usize {
         std::mem::size_of::<u8>()
             + std::mem::size_of::<f32>()
+            + std::mem::size_of::<Bound>()
             + std::mem::size_of::<Option<PieceMove>>()
     }
 }
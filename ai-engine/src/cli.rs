@@ -0,0 +1,256 @@
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ai::{
+        book_builder::{BookBuilder, BookBuilderOptions, DEFAULT_BOOK_MAX_PLY},
+        endgame_tables::generator::{get_default_tables, TableGenerator},
+    },
+    common::contants::INITIAL_FEN,
+    game::{
+        board::Board,
+        moves_counter::{perft_divide, perft_statistics},
+        perft_suite::{parse_epd, run_perft_suite},
+        pgn::parse_pgn_games,
+    },
+};
+
+const DEFAULT_SUITE_MAX_DEPTH: usize = 4;
+
+fn get_argument_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let index = args.iter().position(|arg| arg == name)?;
+
+    args.get(index + 1).map(|value| value.as_str())
+}
+
+// Values following the option up to the next one
+fn get_argument_values<'a>(args: &'a [String], name: &str) -> Vec<&'a str> {
+    let Some(index) = args.iter().position(|arg| arg == name) else {
+        return Vec::new();
+    };
+
+    args[index + 1..]
+        .iter()
+        .take_while(|arg| !arg.starts_with("--"))
+        .map(|arg| arg.as_str())
+        .collect()
+}
+
+/// `--perft <depth> [--fen "<fen>"] [--stats]`, prints the divide in the same format
+/// as Stockfish's `go perft` so both outputs can be diffed
+pub fn run_perft(args: &[String]) -> Result<(), String> {
+    let depth = get_argument_value(args, "--perft")
+        .and_then(|depth| depth.parse::<usize>().ok())
+        .ok_or("usage: --perft <depth> [--fen \"<fen>\"] [--stats]")?;
+
+    let fen = get_argument_value(args, "--fen").unwrap_or(INITIAL_FEN);
+
+    let mut board = Board::new();
+
+    board.load_position(fen).map_err(|err| format!("invalid fen: {}", err))?;
+
+    let start = Instant::now();
+
+    let divide = perft_divide(&mut board, depth);
+
+    for (notation, nodes) in &divide {
+        println!("{}: {}", notation, nodes);
+    }
+
+    let nodes: u64 = divide.iter().map(|(_, nodes)| nodes).sum();
+
+    println!();
+    println!("Nodes searched: {}", nodes);
+    println!("Time: {}ms", start.elapsed().as_millis());
+
+    if args.iter().any(|arg| arg == "--stats") {
+        let statistics = perft_statistics(&mut board, depth);
+
+        println!();
+        println!("Captures: {}", statistics.captures);
+        println!("En passants: {}", statistics.en_passants);
+        println!("Castles: {}", statistics.castles);
+        println!("Promotions: {}", statistics.promotions);
+        println!("Checks: {}", statistics.checks);
+        println!("Discovered checks: {}", statistics.discovered_checks);
+        println!("Double checks: {}", statistics.double_checks);
+        println!("Checkmates: {}", statistics.checkmates);
+    }
+
+    Ok(())
+}
+
+/// `--perft-suite <file> [--max-depth <depth>] [--time-budget <seconds>]`
+pub fn run_perft_suite_file(args: &[String]) -> Result<(), String> {
+    let usage = "usage: --perft-suite <file> [--max-depth <depth>] [--time-budget <seconds>]";
+
+    let path = get_argument_value(args, "--perft-suite").ok_or(usage)?;
+
+    let max_depth = match get_argument_value(args, "--max-depth") {
+        Some(depth) => depth.parse::<usize>().map_err(|_| usage)?,
+        None => DEFAULT_SUITE_MAX_DEPTH,
+    };
+
+    let time_budget = match get_argument_value(args, "--time-budget") {
+        Some(seconds) => Some(Duration::from_secs(seconds.parse::<u64>().map_err(|_| usage)?)),
+        None => None,
+    };
+
+    let text = fs::read_to_string(path).map_err(|err| format!("can't read {}: {}", path, err))?;
+
+    let entries = parse_epd(&text)?;
+
+    let start = Instant::now();
+
+    let report = run_perft_suite(&entries, max_depth, time_budget);
+
+    for mismatch in &report.mismatches {
+        println!(
+            "Mismatch at depth {} for {}: expected {}, found {}",
+            mismatch.depth, mismatch.fen, mismatch.expected, mismatch.found
+        );
+
+        for (notation, nodes) in &mismatch.divide {
+            println!("  {}: {}", notation, nodes);
+        }
+
+        println!();
+    }
+
+    println!(
+        "{} passed, {} failed, {} skipped in {}ms",
+        report.passed,
+        report.mismatches.len(),
+        report.skipped,
+        start.elapsed().as_millis()
+    );
+
+    if !report.mismatches.is_empty() {
+        return Err(String::from("perft suite failed"));
+    }
+
+    Ok(())
+}
+
+/// `--build-book <pgn files> --output <file> [--json <file>] [--max-ply <plies>]
+/// [--min-rating <elo>] [--results <result,...>]`
+pub fn run_build_book(args: &[String]) -> Result<(), String> {
+    let usage = "usage: --build-book <pgn files> --output <file> [--json <file>] [--max-ply <plies>] \
+        [--min-rating <elo>] [--results <result,...>]";
+
+    let pgn_paths = get_argument_values(args, "--build-book");
+    let output_path = get_argument_value(args, "--output").ok_or(usage)?;
+
+    if pgn_paths.is_empty() {
+        return Err(String::from(usage));
+    }
+
+    let max_ply = match get_argument_value(args, "--max-ply") {
+        Some(max_ply) => max_ply.parse::<usize>().map_err(|_| usage)?,
+        None => DEFAULT_BOOK_MAX_PLY,
+    };
+
+    let min_rating = match get_argument_value(args, "--min-rating") {
+        Some(rating) => Some(rating.parse::<u32>().map_err(|_| usage)?),
+        None => None,
+    };
+
+    let results = get_argument_value(args, "--results")
+        .map(|results| results.split(',').map(String::from).collect())
+        .unwrap_or_default();
+
+    let mut builder = BookBuilder::new(BookBuilderOptions {
+        max_ply,
+        min_rating,
+        results,
+    });
+
+    let start = Instant::now();
+
+    // Archives often have a few broken games, they are reported and left out
+    let mut malformed_games = 0;
+
+    for path in pgn_paths {
+        let text = fs::read_to_string(path).map_err(|err| format!("can't read {}: {}", path, err))?;
+
+        for game in parse_pgn_games(&text) {
+            let added = match game {
+                Ok(game) => builder.add_game(&game).map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+
+            if let Err(err) = added {
+                println!("Skipping a game of {}: {}", path, err);
+
+                malformed_games += 1;
+            }
+        }
+    }
+
+    let book = builder.build();
+
+    book.save(output_path).map_err(|err| err.to_string())?;
+
+    if let Some(json_path) = get_argument_value(args, "--json") {
+        let tree = serde_json::to_string_pretty(&builder.to_json_tree()).map_err(|err| err.to_string())?;
+
+        fs::write(json_path, tree).map_err(|err| format!("can't write {}: {}", json_path, err))?;
+    }
+
+    if book.is_empty() {
+        println!("No move scored in the selected games, the book is empty");
+    }
+
+    println!(
+        "{} entries from {} games ({} filtered out, {} malformed) written to {} in {}ms",
+        book.len(),
+        builder.get_games_added(),
+        builder.get_games_skipped(),
+        malformed_games,
+        output_path,
+        start.elapsed().as_millis()
+    );
+
+    Ok(())
+}
+
+/// `--generate-tables [<table>...] --output <directory>`, builds the distance to mate
+/// tables, every table of up to four pieces when none is given
+pub fn run_generate_tables(args: &[String]) -> Result<(), String> {
+    let usage = "usage: --generate-tables [<table>...] --output <directory>";
+
+    let output_path = get_argument_value(args, "--output").ok_or(usage)?;
+
+    let mut names: Vec<String> = get_argument_values(args, "--generate-tables")
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+
+    if names.is_empty() {
+        names = get_default_tables();
+    }
+
+    fs::create_dir_all(output_path).map_err(|err| format!("can't create {}: {}", output_path, err))?;
+
+    let mut generator = TableGenerator::new(output_path);
+
+    for name in &names {
+        let start = Instant::now();
+
+        let table = generator.generate(name).map_err(|err| err.to_string())?;
+
+        println!(
+            "{}: longest mate in {} plies, {}ms",
+            table.get_name(),
+            table.get_max_mate(),
+            start.elapsed().as_millis()
+        );
+
+        // The tables of the next ones are read back from the directory
+        generator.release_tables();
+    }
+
+    Ok(())
+}
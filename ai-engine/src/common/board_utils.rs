@@ -1,4 +1,4 @@
-use super::{piece_move::PieceMove, piece_utils::{get_piece_symbol, is_piece_of_type}};
+use super::{contants::EMPTY_PIECE, fen_utils::get_piece_fen, piece_move::PieceMove};
 
 #[inline]
 pub fn get_position_rank(position: i8) -> usize {
@@ -19,40 +19,28 @@ pub fn get_position_notation(position: i8) -> String {
     format!("{}{}", get_position_file(position), get_position_rank(position))
 }
 
-pub fn get_move_notation(piece_move: &PieceMove) -> String {
-    let mut result = String::new();
+/// Inverse of `get_position_notation`: "a8" -> 0, "h1" -> 63
+pub fn get_position_from_notation(notation: &str) -> Option<i8> {
+    let mut chars = notation.chars();
 
-    result.push(get_piece_symbol(piece_move.get_piece_value()));
+    let file = chars.next()?;
+    let rank = chars.next()?.to_digit(10)?;
 
-    if !is_piece_of_type(piece_move.get_piece_value(), super::enums::PieceType::Pawn) {
-        let from_position_str = get_position_notation(piece_move.get_from_position());
-    
-        result.push_str(&from_position_str);
+    if chars.next().is_some() || !('a'..='h').contains(&file) || !(1..=8).contains(&rank) {
+        return None;
     }
 
-    if piece_move.is_capture() {
-        if is_piece_of_type(piece_move.get_piece_value(), super::enums::PieceType::Pawn) {
-            result.push(get_position_file(piece_move.get_from_position()));
-        }
-
-        result.push_str("x");
-    }
-
-    let to_position_str = get_position_notation(piece_move.get_to_position());
-
-    result.push_str(&to_position_str);
+    Some(((8 - rank as i8) * 8) + (file as u8 - b'a') as i8)
+}
 
-    if piece_move.is_en_passant() {
-        result.push_str(" e.p");
-    }
+/// Long algebraic coordinate notation used by UCI (e.g. `e2e4`, `e7e8q`)
+pub fn get_uci_move_notation(piece_move: &PieceMove) -> String {
+    let mut result = get_position_notation(piece_move.get_from_position());
 
-    if piece_move.is_promotion() {
-        result.push(get_piece_symbol(piece_move.get_promotion_value()));
-    }
+    result.push_str(&get_position_notation(piece_move.get_to_position()));
 
-    if piece_move.puts_king_in_check() {
-        // println!("Colocando a cruz {}->{}", piece_move.get_from_position(), piece_move.get_to_position());
-        result.push('+');
+    if piece_move.is_promotion() && piece_move.get_promotion_value() != EMPTY_PIECE {
+        result.push(get_piece_fen(piece_move.get_promotion_value()).to_ascii_lowercase());
     }
 
     result
@@ -0,0 +1,20 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookError {
+    InvalidSize(usize),
+    Io(String),
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookError::InvalidSize(size) => {
+                write!(f, "book size of {} bytes is not a multiple of the 16 bytes entries", size)
+            }
+            BookError::Io(error) => write!(f, "can't access the book file, {}", error),
+        }
+    }
+}
+
+impl std::error::Error for BookError {}
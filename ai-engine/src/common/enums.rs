@@ -26,3 +26,26 @@ impl PieceColor {
         *self as u8
     }
 }
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DrawReason {
+    FiftyMoveRule,
+    FivefoldRepetition,
+    InsufficientMaterial,
+    SeventyFiveMoveRule,
+    Stalemate,
+    ThreefoldRepetition,
+}
+
+impl DrawReason {
+    pub fn value(&self) -> &'static str {
+        match self {
+            DrawReason::FiftyMoveRule => "fiftyMoveRule",
+            DrawReason::FivefoldRepetition => "fivefoldRepetition",
+            DrawReason::InsufficientMaterial => "insufficientMaterial",
+            DrawReason::SeventyFiveMoveRule => "seventyFiveMoveRule",
+            DrawReason::Stalemate => "stalemate",
+            DrawReason::ThreefoldRepetition => "threefoldRepetition",
+        }
+    }
+}
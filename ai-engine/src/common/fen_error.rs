@@ -0,0 +1,78 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenError {
+    IncompleteRank(usize),
+    InvalidActiveColor(String),
+    InvalidCastlingRights(String),
+    InvalidEnPassantSquare(String),
+    InvalidFullMoveNumber(String),
+    InvalidHalfMoveClock(String),
+    InvalidPieceChar(char),
+    ImpossibleEnPassantSquare(String),
+    InconsistentCastlingRights(char),
+    MissingKing { white: bool },
+    PawnOnBackRank(String),
+    RankOverflow(usize),
+    SideNotToMoveInCheck,
+    TooManyKings { white: bool },
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+}
+
+fn get_color_name(white: bool) -> &'static str {
+    if white {
+        "white"
+    } else {
+        "black"
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::IncompleteRank(rank) => write!(f, "rank {} has less than 8 squares", rank),
+            FenError::InvalidActiveColor(color) => {
+                write!(f, "invalid active color '{}', expected 'w' or 'b'", color)
+            }
+            FenError::InvalidCastlingRights(castling) => {
+                write!(f, "invalid castling rights '{}'", castling)
+            }
+            FenError::InvalidEnPassantSquare(square) => {
+                write!(f, "invalid en passant square '{}'", square)
+            }
+            FenError::InvalidFullMoveNumber(moves) => {
+                write!(f, "invalid full move number '{}'", moves)
+            }
+            FenError::InvalidHalfMoveClock(moves) => {
+                write!(f, "invalid half move clock '{}'", moves)
+            }
+            FenError::InvalidPieceChar(piece) => write!(f, "invalid piece character '{}'", piece),
+            FenError::ImpossibleEnPassantSquare(square) => write!(
+                f,
+                "en passant square '{}' doesn't match a pawn that just moved two squares",
+                square
+            ),
+            FenError::InconsistentCastlingRights(right) => write!(
+                f,
+                "castling right '{}' without the king and rook on their initial squares",
+                right
+            ),
+            FenError::MissingKing { white } => write!(f, "missing {} king", get_color_name(*white)),
+            FenError::PawnOnBackRank(square) => write!(f, "pawn on back rank square {}", square),
+            FenError::RankOverflow(rank) => write!(f, "rank {} has more than 8 squares", rank),
+            FenError::SideNotToMoveInCheck => {
+                write!(f, "the side not to move is in check")
+            }
+            FenError::TooManyKings { white } => {
+                write!(f, "more than one {} king", get_color_name(*white))
+            }
+            FenError::WrongFieldCount(count) => {
+                write!(f, "expected 4 to 6 fields, found {}", count)
+            }
+            FenError::WrongRankCount(count) => write!(f, "expected 8 ranks, found {}", count),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
@@ -1,7 +1,13 @@
 pub mod piece;
 pub mod board_utils;
+pub mod book_error;
 pub mod contants;
 pub mod enums;
+pub mod fen_error;
+pub mod move_parse_error;
+pub mod pgn_error;
+pub mod tablebase_error;
+pub mod tablebase_utils;
 pub mod piece_move;
 pub mod piece_utils;
 pub mod fen_utils;
\ No newline at end of file
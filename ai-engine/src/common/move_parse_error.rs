@@ -0,0 +1,33 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MoveParseError {
+    AmbiguousMove { notation: String, candidates: Vec<String> },
+    IllegalMove(String),
+    InvalidNotation(String),
+    MissingPromotion(String),
+}
+
+impl fmt::Display for MoveParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveParseError::AmbiguousMove { notation, candidates } => write!(
+                f,
+                "ambiguous move '{}', it could be any of {}",
+                notation,
+                candidates.join(", ")
+            ),
+            MoveParseError::IllegalMove(notation) => {
+                write!(f, "illegal move '{}' in the current position", notation)
+            }
+            MoveParseError::InvalidNotation(notation) => {
+                write!(f, "'{}' is neither SAN nor coordinate notation", notation)
+            }
+            MoveParseError::MissingPromotion(notation) => {
+                write!(f, "move '{}' must say which piece the pawn promotes to", notation)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MoveParseError {}
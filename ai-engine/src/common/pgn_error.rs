@@ -0,0 +1,44 @@
+use std::fmt;
+
+use super::{fen_error::FenError, move_parse_error::MoveParseError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PgnError {
+    IllegalMove { game: usize, line: usize, error: MoveParseError },
+    InvalidFen { game: usize, error: FenError },
+    InvalidTag { game: usize, line: usize },
+    UnbalancedVariation { game: usize, line: usize },
+    UnexpectedToken { game: usize, line: usize, token: String },
+    UnplayableMove { game: usize, ply: usize, error: &'static str },
+    UnterminatedComment { game: usize, line: usize },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::IllegalMove { game, line, error } => {
+                write!(f, "game {}, line {}: {}", game, line, error)
+            }
+            PgnError::InvalidFen { game, error } => {
+                write!(f, "game {}: invalid FEN tag, {}", game, error)
+            }
+            PgnError::InvalidTag { game, line } => {
+                write!(f, "game {}, line {}: malformed tag pair", game, line)
+            }
+            PgnError::UnbalancedVariation { game, line } => {
+                write!(f, "game {}, line {}: unbalanced variation parenthesis", game, line)
+            }
+            PgnError::UnexpectedToken { game, line, token } => {
+                write!(f, "game {}, line {}: unexpected '{}'", game, line, token)
+            }
+            PgnError::UnplayableMove { game, ply, error } => {
+                write!(f, "game {}: move {} of the main line can't be played, {}", game, ply, error)
+            }
+            PgnError::UnterminatedComment { game, line } => {
+                write!(f, "game {}, line {}: comment is never closed", game, line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
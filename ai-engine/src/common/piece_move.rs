@@ -3,7 +3,7 @@ use serde::{
     Deserialize, Deserializer, Serialize,
 };
 
-use super::{board_utils::get_move_notation, contants::EMPTY_PIECE};
+use super::{board_utils::get_uci_move_notation, contants::EMPTY_PIECE};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct PieceMove {
@@ -156,17 +156,12 @@ impl PieceMove {
         }
     }
 
-    pub fn eq(&self, other: &Self) -> bool {
-        self.from_position == other.from_position &&
-        self.capture == other.capture &&
-        self.castle == other.castle &&
-        self.en_passant == other.en_passant &&
-        self.promotion == other.promotion &&
-        self.move_worth == other.move_worth &&
-        self.puts_king_in_check == other.puts_king_in_check &&
-        self.piece_value == other.piece_value &&
-        self.promotion_type == other.promotion_type &&
-        self.to_position == other.to_position
+    /// Whether both moves take the same piece between the same positions, whatever was
+    /// computed about them
+    pub fn is_same_move(&self, other: &Self) -> bool {
+        self.from_position == other.from_position
+            && self.to_position == other.to_position
+            && self.promotion_type == other.promotion_type
     }
 
     pub fn get_from_position(&self) -> i8 {
@@ -189,10 +184,6 @@ impl PieceMove {
         self.to_position
     }
 
-    pub fn puts_king_in_check(&self) -> bool {
-        self.puts_king_in_check
-    }
-
     pub fn is_capture(&self) -> bool {
         self.capture
     }
@@ -229,7 +220,16 @@ impl PieceMove {
         self.move_worth += value
     }
 
+    /// SAN notation when it was set from the board, coordinate notation otherwise
     pub fn get_notation(&self) -> String {
-        get_move_notation(self)
+        if self.notation.is_empty() {
+            return get_uci_move_notation(self);
+        }
+
+        self.notation.clone()
+    }
+
+    pub fn set_notation(&mut self, notation: String) {
+        self.notation = notation;
     }
 }
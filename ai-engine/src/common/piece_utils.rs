@@ -2,9 +2,15 @@ use super::{
     contants::{
         BISHOP_WORTH, BLACK_BISHOP, BLACK_KING, BLACK_KNIGHT, BLACK_PAWN, BLACK_QUEEN, BLACK_ROOK, EMPTY_PIECE, KING_WORTH, KNIGHT_WORTH, PAWN_WORTH, QUEEN_WORTH, ROOK_WORTH, WHITE_BISHOP, WHITE_KING, WHITE_KNIGHT, WHITE_LOWER_BOUND, WHITE_PAWN, WHITE_QUEEN, WHITE_ROOK, WHITE_UPPER_BOUND
     },
-    enums::PieceType,
+    enums::{PieceColor, PieceType},
 };
 
+pub fn get_piece_value(piece_type: PieceType, white: bool) -> u8 {
+    let color = if white { PieceColor::White } else { PieceColor::Black };
+
+    color.value() | piece_type.value()
+}
+
 pub fn is_piece_of_type(piece: u8, piece_type: PieceType) -> bool {
     get_piece_type(piece) == piece_type
 }
@@ -46,26 +52,3 @@ pub fn get_piece_worth(piece_value: u8) -> i32 {
 pub fn is_white_piece(piece_value: u8) -> bool {
     (WHITE_LOWER_BOUND..=WHITE_UPPER_BOUND).contains(&piece_value)
 }
-
-#[inline]
-pub fn is_same_color(piece1: u8, piece2: u8) -> bool {
-    is_white_piece(piece1) == is_white_piece(piece2)
-}
-
-pub fn get_piece_symbol(piece_code: u8) -> char {
-    match piece_code {
-        WHITE_KING => '♔',
-        WHITE_QUEEN => '♕',
-        WHITE_ROOK => '♖',
-        WHITE_BISHOP => '♗',
-        WHITE_KNIGHT => '♘',
-        WHITE_PAWN => '♙',
-        BLACK_KING => '♚',
-        BLACK_QUEEN => '♛',
-        BLACK_ROOK => '♜',
-        BLACK_BISHOP => '♝',
-        BLACK_KNIGHT => '♞',
-        BLACK_PAWN => '♟',
-        _ => 'e',
-    }
-}
\ No newline at end of file
@@ -0,0 +1,20 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TablebaseError {
+    InvalidTable(String),
+    Io(String),
+    NoTables(String),
+}
+
+impl fmt::Display for TablebaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TablebaseError::InvalidTable(name) => write!(f, "invalid endgame table {}", name),
+            TablebaseError::Io(error) => write!(f, "can't access the tablebase directory, {}", error),
+            TablebaseError::NoTables(path) => write!(f, "no endgame tables found in {}", path),
+        }
+    }
+}
+
+impl std::error::Error for TablebaseError {}
@@ -0,0 +1,57 @@
+use std::{env, fs, path::PathBuf};
+
+use super::{enums::PieceType, tablebase_error::TablebaseError};
+
+/// Order of the pieces in the names of the Syzygy and distance to mate tables
+pub const NAME_PIECES: [(char, PieceType); 6] = [
+    ('K', PieceType::King),
+    ('Q', PieceType::Queen),
+    ('R', PieceType::Rook),
+    ('B', PieceType::Bishop),
+    ('N', PieceType::Knight),
+    ('P', PieceType::Pawn),
+];
+
+pub fn get_name_piece_type(letter: char) -> Option<PieceType> {
+    NAME_PIECES.iter().find(|(name_letter, _)| *name_letter == letter).map(|(_, piece_type)| *piece_type)
+}
+
+/// Pieces of one side in the order of the table names, like `KRP`
+pub fn get_side_name(piece_types: &[PieceType]) -> String {
+    NAME_PIECES
+        .iter()
+        .flat_map(|&(letter, name_type)| {
+            let count = piece_types.iter().filter(|piece_type| **piece_type == name_type).count();
+
+            std::iter::repeat_n(letter, count)
+        })
+        .collect()
+}
+
+/// Table files of the directories in the path, separated like the `PATH` variable, with
+/// their name and extension. Files of the first directories come first.
+pub fn get_table_files<'a>(path: &str, extensions: &[&'a str]) -> Result<Vec<(String, &'a str, PathBuf)>, TablebaseError> {
+    let mut table_files = Vec::new();
+
+    for directory in env::split_paths(path) {
+        let files = fs::read_dir(&directory)
+            .map_err(|err| TablebaseError::Io(format!("{}: {}", directory.display(), err)))?;
+
+        for file_path in files.flatten().map(|file| file.path()) {
+            let (Some(name), Some(extension)) = (
+                file_path.file_stem().and_then(|name| name.to_str()),
+                file_path.extension().and_then(|extension| extension.to_str()),
+            ) else {
+                continue;
+            };
+
+            let Some(extension) = extensions.iter().find(|table_extension| **table_extension == extension) else {
+                continue;
+            };
+
+            table_files.push((name.to_string(), *extension, file_path.clone()));
+        }
+    }
+
+    Ok(table_files)
+}
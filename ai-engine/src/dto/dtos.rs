@@ -1,16 +1,59 @@
 use serde::Deserialize;
 
+use crate::{ai::opening_book::BookMode, common::piece_move::PieceMove};
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct FenDTO {
     pub fen: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct PgnDTO {
+    pub pgn: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct MovesCountDTO {
     pub depth: usize,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct PerftDTO {
+    pub depth: usize,
+    #[serde(default)]
+    pub statistics: bool,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct AIDepthDTO {
     pub time_to_think: u64,
 }
+
+/// Book used by the AI, the current one is kept when no path is given
+#[derive(Debug, Clone, Deserialize)]
+pub struct BookDTO {
+    pub mode: BookMode,
+    pub path: Option<String>,
+}
+
+/// Distance to mate tables used by the AI, an empty path removes them
+#[derive(Debug, Clone, Deserialize)]
+pub struct EndgameTablesDTO {
+    pub path: String,
+}
+
+/// Syzygy tables used by the AI, the current ones are kept when no path is given
+/// and an empty path removes them
+#[derive(Debug, Clone, Deserialize)]
+pub struct TablebaseDTO {
+    pub path: Option<String>,
+    pub probe_depth: Option<u8>,
+}
+
+/// A move given either as the full move object or as SAN/coordinate notation
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum MoveDTO {
+    Piece(PieceMove),
+    Notation { notation: String },
+}
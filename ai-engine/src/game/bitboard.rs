@@ -0,0 +1,370 @@
+use std::sync::LazyLock;
+
+/// One bit per square, bit 0 is a8 and bit 63 is h1 like the board positions
+pub type Bitboard = u64;
+
+pub const EMPTY_BITBOARD: Bitboard = 0;
+pub const FULL_BITBOARD: Bitboard = !0;
+
+pub const FIRST_RANK: Bitboard = 0xFF00_0000_0000_0000;
+pub const EIGHTH_RANK: Bitboard = 0x0000_0000_0000_00FF;
+
+const KING_OFFSETS: [(i8, i8); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)];
+
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+// Found by trying sparse random numbers until every occupancy of the relevant squares
+// was mapped to an index without colliding with a different set of attacks
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0040_0808_0840_812C, 0x400B_1009_2200_8410, 0x1004_2400_8200_400A, 0x2009_0405_0200_0401,
+    0x0124_0420_0800_0900, 0x010A_1210_0400_0050, 0x8080_4828_0809_8041, 0x0210_2129_1010_1000,
+    0x8000_9004_0820_840C, 0x2200_5002_0210_4200, 0x0010_5004_4080_2488, 0x0800_0404_2080_0030,
+    0x0000_4404_2025_0000, 0x1040_1088_0440_1090, 0x0020_0414_8C10_0900, 0x8200_0041_0801_9000,
+    0x0020_0488_8801_0850, 0x0C08_0050_0280_8404, 0x0050_0008_0221_4017, 0x4008_0004_0212_2008,
+    0x0034_0080_9420_0000, 0x0002_0000_2090_0800, 0x5004_0813_9411_6800, 0x0008_8802_0204_8288,
+    0x0104_9034_4010_0124, 0x4090_0300_4404_0400, 0x0014_1004_0100_4080, 0x0004_0800_8020_20C0,
+    0xA008_4880_0400_2010, 0x1042_4080_3310_1000, 0x1244_0400_0041_04A9, 0x2200_8108_408C_0880,
+    0x2801_1108_0210_2042, 0xB002_1084_0050_1900, 0x1454_1050_0008_0886, 0x0020_2008_0093_0050,
+    0x2010_0202_0002_2008, 0x0019_0808_215E_0200, 0x0002_0C00_4004_1244, 0x3004_0402_8112_3A80,
+    0x8008_4208_04A0_A084, 0x6800_4208_8400_6048, 0x0008_1402_0100_2800, 0x2100_0042_0800_C080,
+    0x0080_0410_1210_8400, 0x0020_2004_4041_0284, 0x8984_0404_2040_8402, 0x044C_0C04_0020_4550,
+    0x0000_4A08_1440_0240, 0x2A90_8200_8220_100A, 0x0014_0100_9804_5B00, 0x0410_0488_4110_8040,
+    0x0002_0010_8202_0000, 0x0420_0811_0408_2104, 0x0310_0210_6102_0080, 0x2020_0204_8A0E_8484,
+    0x8808_4040_4808_C000, 0x0004_8042_0811_2820, 0x0050_1000_2402_0811, 0x0100_2002_162A_0800,
+    0x0008_0000_A120_4100, 0x4000_0020_A002_1084, 0x8010_1021_1204_8204, 0x0442_0802_0096_0202,
+];
+
+const ROOK_MAGICS: [u64; 64] = [
+    0x1480_0061_8040_0412, 0x0040_4000_2000_1000, 0x6080_1000_2000_0880, 0x0100_0420_1000_0902,
+    0x0200_1002_0020_0804, 0x4500_1400_082B_0002, 0x0400_1411_2208_0090, 0x6100_0040_8210_2100,
+    0x8021_8010_C000_2080, 0x0011_0021_0080_4000, 0x4041_0019_0020_0040, 0x1206_0010_2041_0A00,
+    0x0010_8080_0800_0400, 0x3020_8080_0400_0200, 0x4044_0010_0104_0802, 0x0102_0000_4124_0486,
+    0x0400_9180_0060_4003, 0x0040_0020_0028_1001, 0x2020_0080_8020_1000, 0x0000_8080_0800_1002,
+    0x0004_0080_8008_0004, 0x0000_8080_0400_0200, 0x1020_A101_0002_0004, 0x1002_2200_2084_0041,
+    0x0424_4012_8000_8020, 0xC020_5000_4008_2000, 0x0400_1202_0040_2080, 0x0000_0800_8010_0080,
+    0x0D81_0005_0010_0800, 0x4004_0040_4002_0100, 0x0408_C804_0030_0122, 0x4000_4132_0000_8844,
+    0x0400_8340_0280_0020, 0x0030_0240_0240_2000, 0xA000_8010_0080_2000, 0x0000_0800_8080_1000,
+    0x0128_0080_0880_0400, 0x01A4_0002_0080_0480, 0x0100_5002_4400_0738, 0x0000_1244_0200_00A7,
+    0x4280_0120_0045_4008, 0x0A20_0050_0020_4000, 0x0896_0410_4082_0020, 0x1010_4022_0012_0008,
+    0x0110_1008_0101_0004, 0x0002_0010_0402_0008, 0x0800_2102_9004_0008, 0x0040_4284_0852_0003,
+    0x0200_2090_4201_0200, 0x0043_8030_4005_0100, 0x80A0_0410_0024_8080, 0x8208_0010_0180_0880,
+    0x0408_0004_0008_8080, 0x1804_0200_8004_0080, 0x0104_3211_1008_0400, 0x0040_408C_0100_4A00,
+    0x0083_0022_4010_800D, 0x1000_4001_0020_1081, 0x1A02_D140_2900_2001, 0x2204_0900_2004_1001,
+    0x000A_0020_0804_1002, 0x2402_0004_0801_1002, 0x0080_1510_40A2_0804, 0x0000_0048_8C00_2902,
+];
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Magic {
+    mask: Bitboard,
+    magic: u64,
+    offset: usize,
+    shift: u32,
+}
+
+impl Magic {
+    fn get_index(&self, occupancy: Bitboard) -> usize {
+        self.offset + ((occupancy & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct AttackTables {
+    between: Vec<[Bitboard; 64]>,
+    bishop_magics: [Magic; 64],
+    king: [Bitboard; 64],
+    knight: [Bitboard; 64],
+    line: Vec<[Bitboard; 64]>,
+    // Indexed by the color, black pawns first
+    pawn: [[Bitboard; 64]; 2],
+    rook_magics: [Magic; 64],
+    sliding_attacks: Vec<Bitboard>,
+}
+
+static ATTACK_TABLES: LazyLock<AttackTables> = LazyLock::new(AttackTables::new);
+
+impl AttackTables {
+    fn new() -> Self {
+        let mut tables = AttackTables {
+            between: vec![[EMPTY_BITBOARD; 64]; 64],
+            bishop_magics: [Magic::default(); 64],
+            king: [EMPTY_BITBOARD; 64],
+            knight: [EMPTY_BITBOARD; 64],
+            line: vec![[EMPTY_BITBOARD; 64]; 64],
+            pawn: [[EMPTY_BITBOARD; 64]; 2],
+            rook_magics: [Magic::default(); 64],
+            sliding_attacks: Vec::new(),
+        };
+
+        for position in 0..64 {
+            tables.king[position as usize] = get_stepping_attacks(position, &KING_OFFSETS);
+            tables.knight[position as usize] = get_stepping_attacks(position, &KNIGHT_OFFSETS);
+            tables.pawn[0][position as usize] = get_stepping_attacks(position, &[(1, -1), (1, 1)]);
+            tables.pawn[1][position as usize] = get_stepping_attacks(position, &[(-1, -1), (-1, 1)]);
+
+            tables.bishop_magics[position as usize] = init_magic(
+                position,
+                &BISHOP_DIRECTIONS,
+                BISHOP_MAGICS[position as usize],
+                &mut tables.sliding_attacks,
+            );
+            tables.rook_magics[position as usize] = init_magic(
+                position,
+                &ROOK_DIRECTIONS,
+                ROOK_MAGICS[position as usize],
+                &mut tables.sliding_attacks,
+            );
+        }
+
+        for from in 0..64 {
+            for to in 0..64 {
+                tables.set_lines(from, to);
+            }
+        }
+
+        tables
+    }
+
+    fn set_lines(&mut self, from: i8, to: i8) {
+        if from == to {
+            return;
+        }
+
+        let from_bit = get_square_bit(from);
+        let to_bit = get_square_bit(to);
+
+        for (directions, magics) in [(BISHOP_DIRECTIONS, self.bishop_magics), (ROOK_DIRECTIONS, self.rook_magics)] {
+            let from_attacks = self.sliding_attacks[magics[from as usize].get_index(EMPTY_BITBOARD)];
+
+            if from_attacks & to_bit == 0 {
+                continue;
+            }
+
+            let to_attacks = self.sliding_attacks[magics[to as usize].get_index(EMPTY_BITBOARD)];
+
+            self.line[from as usize][to as usize] = (from_attacks & to_attacks) | from_bit | to_bit;
+            self.between[from as usize][to as usize] = get_sliding_attacks(from, &directions, to_bit)
+                & get_sliding_attacks(to, &directions, from_bit);
+        }
+    }
+}
+
+pub fn get_square_bit(position: i8) -> Bitboard {
+    1 << position
+}
+
+/// Removes the lowest square of the bitboard and returns it
+pub fn pop_first_position(bitboard: &mut Bitboard) -> i8 {
+    let position = bitboard.trailing_zeros() as i8;
+
+    *bitboard &= *bitboard - 1;
+
+    position
+}
+
+pub fn get_king_attacks(position: i8) -> Bitboard {
+    ATTACK_TABLES.king[position as usize]
+}
+
+pub fn get_knight_attacks(position: i8) -> Bitboard {
+    ATTACK_TABLES.knight[position as usize]
+}
+
+/// Squares attacked by a pawn of the given color, white pawns attack towards the eighth rank
+pub fn get_pawn_attacks(position: i8, white: bool) -> Bitboard {
+    ATTACK_TABLES.pawn[white as usize][position as usize]
+}
+
+pub fn get_bishop_attacks(position: i8, occupancy: Bitboard) -> Bitboard {
+    let tables = &*ATTACK_TABLES;
+
+    tables.sliding_attacks[tables.bishop_magics[position as usize].get_index(occupancy)]
+}
+
+pub fn get_rook_attacks(position: i8, occupancy: Bitboard) -> Bitboard {
+    let tables = &*ATTACK_TABLES;
+
+    tables.sliding_attacks[tables.rook_magics[position as usize].get_index(occupancy)]
+}
+
+pub fn get_queen_attacks(position: i8, occupancy: Bitboard) -> Bitboard {
+    get_bishop_attacks(position, occupancy) | get_rook_attacks(position, occupancy)
+}
+
+/// Squares strictly between two positions on the same rank, file or diagonal
+pub fn get_between(from: i8, to: i8) -> Bitboard {
+    ATTACK_TABLES.between[from as usize][to as usize]
+}
+
+/// The whole rank, file or diagonal going through both positions, empty if they aren't aligned
+pub fn get_line(from: i8, to: i8) -> Bitboard {
+    ATTACK_TABLES.line[from as usize][to as usize]
+}
+
+fn get_offset_position(position: i8, (row_offset, column_offset): (i8, i8)) -> Option<i8> {
+    let row = position / 8 + row_offset;
+    let column = position % 8 + column_offset;
+
+    if !(0..8).contains(&row) || !(0..8).contains(&column) {
+        return None;
+    }
+
+    Some(row * 8 + column)
+}
+
+fn get_stepping_attacks(position: i8, offsets: &[(i8, i8)]) -> Bitboard {
+    offsets
+        .iter()
+        .filter_map(|&offset| get_offset_position(position, offset))
+        .fold(EMPTY_BITBOARD, |attacks, target| attacks | get_square_bit(target))
+}
+
+/// Attacks found by walking every direction until a piece of the occupancy is hit,
+/// only used to fill the lookup tables
+fn get_sliding_attacks(position: i8, directions: &[(i8, i8)], occupancy: Bitboard) -> Bitboard {
+    let mut attacks = EMPTY_BITBOARD;
+
+    for &(row_offset, column_offset) in directions {
+        let mut current = position;
+
+        while let Some(target) = get_offset_position(current, (row_offset, column_offset)) {
+            attacks |= get_square_bit(target);
+
+            if occupancy & get_square_bit(target) != 0 {
+                break;
+            }
+
+            current = target;
+        }
+    }
+
+    attacks
+}
+
+/// Squares whose occupancy changes the attacks, the last square of every ray never blocks
+fn get_relevant_mask(position: i8, directions: &[(i8, i8)]) -> Bitboard {
+    let mut mask = EMPTY_BITBOARD;
+
+    for &direction in directions {
+        let mut current = position;
+
+        while let Some(target) = get_offset_position(current, direction) {
+            if get_offset_position(target, direction).is_none() {
+                break;
+            }
+
+            mask |= get_square_bit(target);
+            current = target;
+        }
+    }
+
+    mask
+}
+
+/// Fills the attacks of every occupancy of the relevant squares at the index the magic gives it
+fn init_magic(position: i8, directions: &[(i8, i8)], magic: u64, sliding_attacks: &mut Vec<Bitboard>) -> Magic {
+    let mask = get_relevant_mask(position, directions);
+    let bits = mask.count_ones();
+
+    let offset = sliding_attacks.len();
+
+    sliding_attacks.resize(offset + (1 << bits), EMPTY_BITBOARD);
+
+    let entry = Magic { mask, magic, offset, shift: 64 - bits };
+
+    // Carry-Rippler trick to go through every subset of the mask
+    let mut occupancy = EMPTY_BITBOARD;
+
+    loop {
+        let attacks = get_sliding_attacks(position, directions, occupancy);
+        let index = entry.get_index(occupancy);
+
+        assert!(
+            sliding_attacks[index] == EMPTY_BITBOARD || sliding_attacks[index] == attacks,
+            "the magic of position {} maps different attacks to the same index",
+            position
+        );
+
+        sliding_attacks[index] = attacks;
+
+        occupancy = occupancy.wrapping_sub(mask) & mask;
+
+        if occupancy == EMPTY_BITBOARD {
+            break;
+        }
+    }
+
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::board_utils::get_position_from_notation;
+
+    use super::{
+        get_between, get_bishop_attacks, get_king_attacks, get_knight_attacks, get_line, get_pawn_attacks,
+        get_rook_attacks, get_square_bit, get_sliding_attacks, Bitboard, BISHOP_DIRECTIONS, ROOK_DIRECTIONS,
+    };
+
+    fn squares(notations: &[&str]) -> Bitboard {
+        notations
+            .iter()
+            .map(|notation| get_square_bit(get_position_from_notation(notation).unwrap()))
+            .fold(0, |bitboard, bit| bitboard | bit)
+    }
+
+    fn position(notation: &str) -> i8 {
+        get_position_from_notation(notation).unwrap()
+    }
+
+    #[test]
+    fn test_stepping_attacks() {
+        assert_eq!(get_knight_attacks(position("a8")), squares(&["b6", "c7"]));
+        assert_eq!(get_king_attacks(position("h1")), squares(&["g1", "g2", "h2"]));
+        assert_eq!(get_pawn_attacks(position("e4"), true), squares(&["d5", "f5"]));
+        assert_eq!(get_pawn_attacks(position("a7"), false), squares(&["b6"]));
+    }
+
+    #[test]
+    fn test_sliding_attacks() {
+        let occupancy = squares(&["b2", "d4", "e7", "g6", "c1"]);
+
+        assert_eq!(
+            get_rook_attacks(position("d2"), occupancy),
+            squares(&["b2", "c2", "e2", "f2", "g2", "h2", "d1", "d3", "d4"])
+        );
+        assert_eq!(
+            get_bishop_attacks(position("e5"), occupancy),
+            squares(&["d4", "d6", "c7", "b8", "f6", "g7", "h8", "f4", "g3", "h2"])
+        );
+
+        // Every square against a few pseudo random occupancies
+        let mut occupancy: Bitboard = 0x9E37_79B9_7F4A_7C15;
+
+        for _ in 0..16 {
+            for position in 0..64 {
+                assert_eq!(
+                    get_bishop_attacks(position, occupancy),
+                    get_sliding_attacks(position, &BISHOP_DIRECTIONS, occupancy)
+                );
+                assert_eq!(
+                    get_rook_attacks(position, occupancy),
+                    get_sliding_attacks(position, &ROOK_DIRECTIONS, occupancy)
+                );
+            }
+
+            occupancy ^= occupancy << 13;
+            occupancy ^= occupancy >> 7;
+            occupancy ^= occupancy << 17;
+        }
+    }
+
+    #[test]
+    fn test_lines() {
+        assert_eq!(get_between(position("a1"), position("d4")), squares(&["b2", "c3"]));
+        assert_eq!(get_between(position("e1"), position("e2")), 0);
+        assert_eq!(get_between(position("a1"), position("b3")), 0);
+        assert_eq!(get_line(position("c3"), position("d4")) & squares(&["a1", "h8", "a8"]), squares(&["a1", "h8"]));
+        assert_eq!(get_line(position("a1"), position("b3")), 0);
+    }
+}
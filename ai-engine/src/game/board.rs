@@ -1,31 +1,45 @@
 use crate::common::{
-    contants::{EMPTY_PIECE, INITIAL_FEN, INVALID_BOARD_POSITION}, enums::{PieceColor, PieceType}, piece::Piece, piece_move::PieceMove, piece_utils::{get_piece_type, is_piece_of_type, is_white_piece}
+    contants::{EMPTY_PIECE, INITIAL_FEN, INVALID_BOARD_POSITION}, enums::{DrawReason, PieceColor, PieceType}, piece::Piece, piece_move::PieceMove, piece_utils::{get_piece_type, is_piece_of_type, is_white_piece}, fen_error::FenError
 };
 
 use super::{
-    board_state::BoardState,
+    board_material_utils::{has_drawish_material, has_insufficient_material},
+    board_state::{get_en_passant_capture_position, BoardState, UndoRecord},
     contants::{
         BLACK_KING_SIDE_ROOK_POSITION, BLACK_QUEEN_SIDE_ROOK_POSITION,
         WHITE_KING_SIDE_ROOK_POSITION, WHITE_QUEEN_SIDE_ROOK_POSITION,
     },
     move_generator::MoveGenerator,
+    move_generator_helper::is_position_attacked,
+    zobrist::compute_hash,
 };
 
+// Half moves without captures or pawn moves for the 50 and 75 move rules
+const FIFTY_MOVE_RULE_HALF_MOVES: usize = 100;
+const SEVENTY_FIVE_MOVE_RULE_HALF_MOVES: usize = 150;
+
 #[derive(Debug, Clone)]
 pub struct Board {
+    initial_fen: String,
+    move_history: Vec<PieceMove>,
     state: BoardState,
-    state_history: Vec<BoardState>,
+    // One record for each move of the history
+    undo_history: Vec<UndoRecord>,
 }
 
 impl Board {
     pub fn new() -> Self {
         let mut state = BoardState::new();
 
-        state.load_position(INITIAL_FEN);
+        state
+            .load_position(INITIAL_FEN)
+            .expect("the initial position is a valid FEN");
 
         Board {
+            initial_fen: state.get_fen(),
+            move_history: Vec::new(),
             state,
-            state_history: Vec::new(),
+            undo_history: Vec::new(),
         }
     }
 
@@ -33,37 +47,119 @@ impl Board {
         &self.state
     }
 
-    fn get_move_generator(&self) -> MoveGenerator {
-        MoveGenerator::new(self.state.clone())
-    }
-
     pub fn get_pieces(&mut self) -> Vec<Piece> {
-        let mut move_generator = self.get_move_generator();
+        let white_move = self.is_white_move();
 
-        let moves = move_generator.get_available_moves(self);
-        
-        // Sorry, this is awful, just have to finish this project for now.
-        // When I get back to it in the future I'll refactor this shitty code
-        let generated_board_state = move_generator.get_board_state();
+        let (pieces, king_in_check) = MoveGenerator::new(&self.state).get_available_moves();
 
-        self.state.set_white_king_in_check(generated_board_state.is_white_king_in_check());
-        self.state.set_black_king_in_check(generated_board_state.is_black_king_in_check());
+        self.state.set_white_king_in_check(white_move && king_in_check);
+        self.state.set_black_king_in_check(!white_move && king_in_check);
+
+        let no_moves_available = pieces
+            .iter()
+            .filter(|piece| piece.is_white() == white_move)
+            .all(|piece| piece.get_moves_reference().is_empty());
+
+        if no_moves_available {
+            self.set_winner(king_in_check, white_move);
+        }
+
+        // Neither side can checkmate anymore, game ends in draw
+        if !self.is_game_finished() && self.has_insufficient_material() {
+            self.set_draw(DrawReason::InsufficientMaterial);
+        }
+
+        // Checkmate and stalemate take precedence over the draw rules
+        if !self.is_game_finished() {
+            if let Some(draw_reason) = self.get_rule_draw_reason() {
+                self.set_draw(draw_reason);
+            }
+        }
 
-        moves
+        pieces
+    }
+
+    /// Legal moves of the side to move
+    pub fn get_legal_moves(&mut self) -> Vec<PieceMove> {
+        let white_move = self.is_white_move();
+
+        self.get_pieces()
+            .iter()
+            .filter(|piece| piece.is_white() == white_move)
+            .flat_map(|piece| piece.get_moves_clone())
+            .collect()
     }
 
     pub fn set_winner(&mut self, is_king_in_check: bool, is_white_move: bool) {
-        self.state.set_winner(if is_king_in_check {
-            if is_white_move {
-                PieceColor::Black.value()
-            } else {
-                PieceColor::White.value()
-            }
+        if !is_king_in_check {
+            self.set_draw(DrawReason::Stalemate);
+
+            return;
+        }
+
+        self.state.set_winner(if is_white_move {
+            PieceColor::Black.value()
         } else {
-            PieceColor::Black.value() | PieceColor::White.value() // Draw
+            PieceColor::White.value()
         });
     }
 
+    pub fn set_draw(&mut self, draw_reason: DrawReason) {
+        self.state.set_winner(PieceColor::Black.value() | PieceColor::White.value());
+        self.state.set_draw_reason(Some(draw_reason));
+    }
+
+    pub fn get_draw_reason(&self) -> Option<DrawReason> {
+        self.state.get_draw_reason()
+    }
+
+    /// Draws by repetition or by the 50/75 move rules, both are claimed automatically
+    fn get_rule_draw_reason(&self) -> Option<DrawReason> {
+        let repetitions = self.count_repetitions();
+        let half_moves = self.state.get_half_moves();
+
+        if repetitions >= 4 {
+            Some(DrawReason::FivefoldRepetition)
+        } else if half_moves >= SEVENTY_FIVE_MOVE_RULE_HALF_MOVES {
+            Some(DrawReason::SeventyFiveMoveRule)
+        } else if repetitions >= 2 {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if half_moves >= FIFTY_MOVE_RULE_HALF_MOVES {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// How many times the current position happened before. Only positions since the last
+    /// capture or pawn move are compared, since older ones can't repeat anymore.
+    pub fn count_repetitions(&self) -> usize {
+        let hash = self.state.get_zobrist_hash();
+
+        // Every other position had the same side to move
+        self.undo_history
+            .iter()
+            .rev()
+            .take(self.state.get_half_moves())
+            .skip(1)
+            .step_by(2)
+            .filter(|record| record.get_zobrist_hash() == hash)
+            .count()
+    }
+
+    pub fn is_repeated_position(&self) -> bool {
+        self.count_repetitions() > 0
+    }
+
+    pub fn has_insufficient_material(&self) -> bool {
+        has_insufficient_material(&self.state)
+    }
+
+    /// Material that can't force a mate, like K+N+N vs K, although the game goes on
+    pub fn has_drawish_material(&self) -> bool {
+        has_drawish_material(&self.state)
+    }
+
     pub fn get_winner_fen(&self) -> char {
         match self.state.get_winner() {
             x if x == (PieceColor::White.value()) => 'w',
@@ -81,35 +177,100 @@ impl Board {
         self.state.is_white_king_in_check()
     }
 
+    /// Whether the side to move is in check, found on the squares since the check flags
+    /// are only updated by `get_pieces`
+    pub fn is_side_to_move_in_check(&self) -> bool {
+        let white_move = self.is_white_move();
+
+        let king_position = if white_move {
+            self.state.get_white_king_position()
+        } else {
+            self.state.get_black_king_position()
+        };
+
+        is_position_attacked(&self.state, king_position, !white_move)
+    }
+
     pub fn get_winner(&self) -> u8 {
         self.state.get_winner()
     }
 
+    /// Plays the move, nothing changes when it can't be played
     pub fn move_piece(&mut self, piece_move: &PieceMove) -> Result<(), &'static str> {
-        self.state_history.push(self.state.clone());
+        self.validate_move(piece_move)?;
+
+        self.undo_history.push(self.state.get_undo_record(piece_move));
+        self.move_history.push(piece_move.clone());
+
+        // The result of the new position is only known after generating its moves
+        self.state.set_winner(0);
+        self.state.set_draw_reason(None);
+
+        self._make_move(piece_move, false);
+
+        self.debug_check_hash();
 
-        self._make_move(piece_move, false)
+        Ok(())
     }
 
     pub fn undo_last_move(&mut self) {
-        if let Some(state) = self.state_history.pop() {
-            self.state = state
+        if let (Some(piece_move), Some(record)) = (self.move_history.pop(), self.undo_history.pop()) {
+            if is_null_move(&piece_move) {
+                self.state.unmake_null_move(&record);
+            } else {
+                self.state.unmake_move(&piece_move, &record);
+            }
+
+            self.debug_check_hash();
         }
     }
 
-    pub fn get_state_clone(&self) -> BoardState {
-        self.state.clone()
+    /// Passes the turn without moving, only meant for the search to see what the opponent
+    /// could do with two moves in a row. It is unmade with `undo_last_move`.
+    pub fn make_null_move(&mut self) {
+        let null_move = PieceMove::new(INVALID_BOARD_POSITION, EMPTY_PIECE, INVALID_BOARD_POSITION);
+
+        self.undo_history.push(self.state.get_undo_record(&null_move));
+        self.move_history.push(null_move);
+
+        self.state.set_winner(0);
+        self.state.set_draw_reason(None);
+
+        self.state.make_null_move();
+
+        self.debug_check_hash();
+    }
+
+    /// Whether the last move was a null move
+    pub fn is_after_null_move(&self) -> bool {
+        self.move_history.last().is_some_and(is_null_move)
+    }
+
+    /// The hash is updated incrementally, it must always match the one computed from scratch
+    fn debug_check_hash(&self) {
+        debug_assert_eq!(
+            self.state.get_zobrist_hash(),
+            compute_hash(&self.state),
+            "incremental Zobrist hash out of sync for {}",
+            self.state.get_fen()
+        );
+    }
+
+    /// Moves played since the position was loaded, in order
+    pub fn get_move_history(&self) -> &Vec<PieceMove> {
+        &self.move_history
+    }
+
+    /// FEN of the position the moves of the history were played from
+    pub fn get_initial_fen(&self) -> String {
+        self.initial_fen.clone()
     }
 
     pub fn get_zobrist_hash(&self) -> u64 {
         self.state.get_zobrist_hash()
     }
 
-    fn _make_move(
-        &mut self,
-        piece_move: &PieceMove,
-        rook_castling: bool,
-    ) -> Result<(), &'static str> {
+    fn validate_move(&self, piece_move: &PieceMove) -> Result<(), &'static str> {
         let from_index = piece_move.get_from_position();
         let to_index = piece_move.get_to_position();
 
@@ -117,20 +278,35 @@ impl Board {
             return Err("Invalid board position");
         }
 
+        if let Some(invalid_result) =
+            validate_move_pieces(self.state.get_piece(from_index), self.state.get_piece(to_index))
+        {
+            return invalid_result;
+        }
+
+        if piece_move.is_promotion() && piece_move.get_promotion_value() == EMPTY_PIECE {
+            return Err("Pawn needs promotion type.");
+        }
+
+        Ok(())
+    }
+
+    fn _make_move(&mut self, piece_move: &PieceMove, rook_castling: bool) {
+        let from_index = piece_move.get_from_position();
+        let to_index = piece_move.get_to_position();
+
         let mut moving_piece = self.state.get_piece(from_index);
         let existing_piece = self.state.get_piece(to_index);
 
-        if let Some(invalid_result) = validate_move_pieces(moving_piece, existing_piece) {
-            return invalid_result;
+        // Cleared before the pieces move, its part of the hash depends on the pawns around it
+        if !rook_castling {
+            self.state.set_white_en_passant(INVALID_BOARD_POSITION);
+            self.state.set_black_en_passant(INVALID_BOARD_POSITION);
         }
 
         if piece_move.is_en_passant() {
-            self.capture_en_passant(moving_piece);
+            self.capture_en_passant(moving_piece, to_index);
         } else if piece_move.is_promotion() {
-            if piece_move.get_promotion_value() == EMPTY_PIECE {
-                return Err("Pawn needs promotion type.");
-            }
-
             moving_piece = piece_move.get_promotion_value();
         } else if get_piece_type(moving_piece) == PieceType::King {
             self.handle_king_move(from_index, moving_piece, to_index);
@@ -141,8 +317,6 @@ impl Board {
         if !rook_castling {
             self.handle_state_update_after(from_index, moving_piece, to_index, existing_piece);
         }
-
-        Ok(())
     }
 
     fn handle_state_update_after(
@@ -176,22 +350,22 @@ impl Board {
             && ((white_piece && !self.state.has_white_king_moved())
                 || (!white_piece && !self.state.has_black_king_moved()))
         {
-            let _ = self.castle(from_position, to_position, white_piece);
+            self.castle(from_position, to_position, white_piece);
         }
 
+        // Any king move loses both castling rights, not only castling
         if white_piece {
             self.state.set_white_king_moved(true);
+            self.state.set_white_able_to_queen_side_castle(false);
+            self.state.set_white_able_to_king_side_castle(false);
         } else {
             self.state.set_black_king_moved(true);
+            self.state.set_black_able_to_queen_side_castle(false);
+            self.state.set_black_able_to_king_side_castle(false);
         }
     }
 
-    fn castle(
-        &mut self,
-        from_index: i8,
-        to_index: i8,
-        white_piece: bool,
-    ) -> Result<(), &'static str> {
+    fn castle(&mut self, from_index: i8, to_index: i8, white_piece: bool) {
         let (queen_side_rook_position, king_side_rook_position) = if white_piece {
             (
                 WHITE_QUEEN_SIDE_ROOK_POSITION,
@@ -216,47 +390,29 @@ impl Board {
             from_index + 1
         };
 
-        if white_piece {
-            self.state.set_white_able_to_queen_side_castle(false);
-            self.state.set_white_able_to_king_side_castle(false);
-        } else {
-            self.state.set_black_able_to_queen_side_castle(false);
-            self.state.set_black_able_to_king_side_castle(false);
-        }
-
         let rook_move = PieceMove::new(
             rook_position,
             self.state.get_piece(rook_position),
             new_rook_position,
         );
 
-        self._make_move(&rook_move, true)
+        self._make_move(&rook_move, true);
     }
 
-    fn capture_en_passant(&mut self, moving_piece: u8) {
-        let white_piece = is_white_piece(moving_piece);
-
-        if white_piece {
-            let position = self.state.get_black_en_passant() + 8;
-            let piece_value = self.state.get_piece(position);
+    fn capture_en_passant(&mut self, moving_piece: u8, to_position: i8) {
+        let position = get_en_passant_capture_position(moving_piece, to_position);
+        let piece_value = self.state.get_piece(position);
 
+        if is_white_piece(moving_piece) {
             self.state.append_white_capture(piece_value);
-            self.state.place_piece(position, EMPTY_PIECE);
-            self.state.set_black_en_passant(INVALID_BOARD_POSITION);
         } else {
-            let position = self.state.get_white_en_passant() - 8;
-            let piece_value = self.state.get_piece(position);
-
             self.state.append_black_capture(piece_value);
-            self.state.place_piece(position, EMPTY_PIECE);
-            self.state.set_white_en_passant(INVALID_BOARD_POSITION);
         }
+
+        self.state.place_piece(position, EMPTY_PIECE);
     }
 
     fn handle_en_passant(&mut self, from_position: i8, piece_value: u8, to_position: i8) {
-        self.state.set_white_en_passant(INVALID_BOARD_POSITION);
-        self.state.set_black_en_passant(INVALID_BOARD_POSITION);
-
         if !is_piece_of_type(piece_value, PieceType::Pawn) {
             return;
         }
@@ -276,12 +432,18 @@ impl Board {
         }
     }
 
-    pub fn load_position(&mut self, fen_position: &str) {
+    /// Replaces the current position, the board is left untouched when the FEN is invalid
+    pub fn load_position(&mut self, fen_position: &str) -> Result<(), FenError> {
         let mut state = BoardState::new();
 
-        state.load_position(fen_position);
+        state.load_position(fen_position)?;
 
+        self.initial_fen = state.get_fen();
         self.state = state;
+        self.undo_history.clear();
+        self.move_history.clear();
+
+        Ok(())
     }
 
     pub fn black_captures_to_fen(&self) -> Vec<char> {
@@ -301,6 +463,10 @@ impl Board {
     }
 }
 
+fn is_null_move(piece_move: &PieceMove) -> bool {
+    piece_move.get_from_position() == INVALID_BOARD_POSITION
+}
+
 fn validate_move_pieces(moving_piece: u8, existing_piece: u8) -> Option<Result<(), &'static str>> {
     if moving_piece == EMPTY_PIECE {
         return Some(Err("No piece at the position"));
@@ -313,3 +479,154 @@ fn validate_move_pieces(moving_piece: u8, existing_piece: u8) -> Option<Result<(
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{
+        board_utils::get_position_from_notation, contants::{BLACK_QUEEN, WHITE_QUEEN}, enums::DrawReason,
+        piece_utils::is_white_piece,
+    };
+
+    use super::Board;
+
+    fn play(board: &mut Board, from: &str, to: &str) {
+        let from_position = get_position_from_notation(from).unwrap();
+        let to_position = get_position_from_notation(to).unwrap();
+
+        let piece_move = board
+            .get_pieces()
+            .iter()
+            .flat_map(|piece| piece.get_moves_clone())
+            .find(|_move| {
+                _move.get_from_position() == from_position && _move.get_to_position() == to_position
+            })
+            .unwrap();
+
+        board.move_piece(&piece_move).unwrap();
+    }
+
+    #[test]
+    fn test_threefold_repetition() {
+        let mut board = Board::new();
+
+        for _ in 0..2 {
+            play(&mut board, "g1", "f3");
+            play(&mut board, "g8", "f6");
+            play(&mut board, "f3", "g1");
+            play(&mut board, "f6", "g8");
+        }
+
+        board.get_pieces();
+
+        assert!(board.is_game_finished());
+        assert_eq!(board.get_winner_fen(), 'd');
+        assert_eq!(board.get_draw_reason(), Some(DrawReason::ThreefoldRepetition));
+
+        board.undo_last_move();
+        board.get_pieces();
+
+        assert!(!board.is_game_finished());
+        assert!(board.is_repeated_position());
+    }
+
+    #[test]
+    fn test_fifty_move_rule() {
+        let mut board = Board::new();
+
+        board.load_position("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80").unwrap();
+
+        play(&mut board, "a1", "a2");
+
+        board.get_pieces();
+
+        assert_eq!(board.get_draw_reason(), Some(DrawReason::FiftyMoveRule));
+
+        board.load_position("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80").unwrap();
+
+        // Pawn moves reset the counter
+        play(&mut board, "e2", "e4");
+
+        board.get_pieces();
+
+        assert!(!board.is_game_finished());
+    }
+
+    fn snapshot(board: &Board) -> (String, u64, Vec<char>, Vec<char>, u64) {
+        let state = board.get_state_reference();
+
+        (
+            state.get_fen(),
+            state.get_zobrist_hash(),
+            board.white_captures_to_fen(),
+            board.black_captures_to_fen(),
+            state.get_occupancy(),
+        )
+    }
+
+    fn assert_moves_are_undone(board: &mut Board, depth: usize) {
+        if depth == 0 {
+            return;
+        }
+
+        let before = snapshot(board);
+
+        for mut piece_move in board.get_legal_moves() {
+            if piece_move.is_promotion() {
+                let white = is_white_piece(piece_move.get_piece_value());
+
+                piece_move.set_promotion_value(if white { WHITE_QUEEN } else { BLACK_QUEEN });
+            }
+
+            board.move_piece(&piece_move).unwrap();
+
+            assert_moves_are_undone(board, depth - 1);
+
+            board.undo_last_move();
+
+            assert_eq!(snapshot(board), before, "after undoing {}", piece_move.get_notation());
+        }
+    }
+
+    #[test]
+    fn test_undo_last_move() {
+        let positions = [
+            // Castling, promotions with captures and en passant
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+
+        for fen in positions {
+            let mut board = Board::new();
+
+            board.load_position(fen).unwrap();
+
+            assert_moves_are_undone(&mut board, 2);
+        }
+    }
+
+    #[test]
+    fn test_null_move() {
+        let mut board = Board::new();
+
+        board.load_position("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 4 3").unwrap();
+
+        let before = snapshot(&board);
+
+        board.make_null_move();
+
+        // Only the side to move changes, the en passant square is lost and the half moves
+        // start over
+        let mut passed = Board::new();
+
+        passed.load_position("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3").unwrap();
+
+        assert_eq!(snapshot(&board), snapshot(&passed));
+        assert!(board.is_after_null_move());
+
+        board.undo_last_move();
+
+        assert_eq!(snapshot(&board), before);
+        assert!(!board.is_after_null_move());
+    }
+}
@@ -62,6 +62,8 @@ fn get_pieces_fen(board_state: &BoardState, fen: &mut String) {
 
         fen.push(get_piece_fen(piece));
     }
+
+    append_empty_squares(fen, &mut empty_squares);
 }
 
 fn get_color_fen(board_state: &BoardState, fen: &mut String) {
@@ -0,0 +1,135 @@
+use crate::common::{enums::PieceType, piece_utils::{get_piece_type, is_white_piece}};
+
+use super::board_state::BoardState;
+
+#[derive(Debug, Default)]
+struct MaterialCount {
+    bishops_on_dark_squares: usize,
+    bishops_on_light_squares: usize,
+    knights: usize,
+    // Pawns, rooks and queens can always force a mate
+    major_or_pawns: usize,
+}
+
+impl MaterialCount {
+    fn bishops(&self) -> usize {
+        self.bishops_on_dark_squares + self.bishops_on_light_squares
+    }
+
+    fn minors(&self) -> usize {
+        self.bishops() + self.knights
+    }
+
+    fn is_bare_king(&self) -> bool {
+        self.major_or_pawns == 0 && self.minors() == 0
+    }
+}
+
+fn count_material(board_state: &BoardState) -> (MaterialCount, MaterialCount) {
+    let mut white = MaterialCount::default();
+    let mut black = MaterialCount::default();
+
+    for (position, &piece) in board_state.get_squares().iter().enumerate() {
+        let material = if is_white_piece(piece) {
+            &mut white
+        } else {
+            &mut black
+        };
+
+        match get_piece_type(piece) {
+            PieceType::Bishop => {
+                // a8 (0) is a light square
+                if ((position / 8) + (position % 8)) % 2 == 0 {
+                    material.bishops_on_light_squares += 1;
+                } else {
+                    material.bishops_on_dark_squares += 1;
+                }
+            }
+            PieceType::Knight => material.knights += 1,
+            PieceType::Pawn | PieceType::Queen | PieceType::Rook => material.major_or_pawns += 1,
+            _ => {}
+        }
+    }
+
+    (white, black)
+}
+
+/// Neither side can checkmate with any sequence of moves: K vs K, K+B vs K, K+N vs K
+/// and positions where every bishop stands on the same square color (like K+B vs K+B).
+pub fn has_insufficient_material(board_state: &BoardState) -> bool {
+    let (white, black) = count_material(board_state);
+
+    if white.major_or_pawns > 0 || black.major_or_pawns > 0 {
+        return false;
+    }
+
+    let minors = white.minors() + black.minors();
+
+    if minors <= 1 {
+        return true;
+    }
+
+    let knights = white.knights + black.knights;
+    let light_bishops = white.bishops_on_light_squares + black.bishops_on_light_squares;
+    let dark_bishops = white.bishops_on_dark_squares + black.bishops_on_dark_squares;
+
+    knights == 0 && (light_bishops == 0 || dark_bishops == 0)
+}
+
+/// Positions that can't be won against correct defence although a mate is still possible
+/// (K+N+N vs K, a single minor piece against another one). They are claimable draws in
+/// practice and are scored as such, but they don't end the game.
+pub fn has_drawish_material(board_state: &BoardState) -> bool {
+    let (white, black) = count_material(board_state);
+
+    if white.major_or_pawns > 0 || black.major_or_pawns > 0 {
+        return false;
+    }
+
+    let two_knights_against_king = |side: &MaterialCount, other: &MaterialCount| {
+        side.knights == 2 && side.bishops() == 0 && other.is_bare_king()
+    };
+
+    two_knights_against_king(&white, &black)
+        || two_knights_against_king(&black, &white)
+        || (white.minors() == 1 && black.minors() == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::board_state::BoardState;
+
+    use super::{has_drawish_material, has_insufficient_material};
+
+    fn load(fen: &str) -> BoardState {
+        let mut board_state = BoardState::new();
+
+        board_state.load_position(fen).unwrap();
+
+        board_state
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        // K vs K, K+B vs K, K+N vs K
+        assert!(has_insufficient_material(&load("8/8/4k3/8/8/3K4/8/8 w - - 0 1")));
+        assert!(has_insufficient_material(&load("8/8/4k3/8/8/3KB3/8/8 w - - 0 1")));
+        assert!(has_insufficient_material(&load("8/8/4k3/8/8/3K4/8/6n1 b - - 0 1")));
+
+        // K+B vs K+B with bishops on the same (c1 and f8 are dark) and different colors
+        assert!(has_insufficient_material(&load("5b2/8/4k3/8/8/3K4/8/2B5 w - - 0 1")));
+        assert!(!has_insufficient_material(&load("2b5/8/4k3/8/8/3K4/8/2B5 w - - 0 1")));
+
+        assert!(!has_insufficient_material(&load("8/8/4k3/8/8/3K4/8/2BN4 w - - 0 1")));
+        assert!(!has_insufficient_material(&load("8/8/4k3/8/8/3K4/4P3/8 w - - 0 1")));
+    }
+
+    #[test]
+    fn test_drawish_material() {
+        assert!(has_drawish_material(&load("8/8/4k3/8/8/3K4/8/1N4N1 w - - 0 1")));
+        assert!(has_drawish_material(&load("8/8/4k3/2n5/8/3K4/8/2B5 w - - 0 1")));
+
+        assert!(!has_drawish_material(&load("8/8/4k3/8/8/3K4/8/2B2B2 w - - 0 1")));
+        assert!(!has_drawish_material(&load("8/8/4k3/8/8/3K4/8/2BN4 w - - 0 1")));
+    }
+}
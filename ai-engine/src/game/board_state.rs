@@ -1,16 +1,51 @@
 use crate::common::{
-    contants::{EMPTY_PIECE, INVALID_BOARD_POSITION}, enums::PieceType, fen_utils::{get_fen_piece_value, translate_pieces_to_fen}, piece_utils::{is_piece_of_type, is_white_piece}
+    board_utils::{get_position_from_notation, get_position_notation, get_position_rank}, contants::{BLACK_ROOK, EMPTY_PIECE, INVALID_BOARD_POSITION, WHITE_ROOK}, enums::{DrawReason, PieceType}, fen_error::FenError, fen_utils::{get_fen_piece_value, translate_pieces_to_fen}, piece_move::PieceMove, piece_utils::{get_piece_type, is_piece_of_type, is_white_piece}
 };
 
 use super::{
     board_fen_utils::get_position_fen, contants::{
-        BLACK_KING_INITIAL_POSITION, BLACK_KING_VALUE, LETTER_A_UNICODE,
-        WHITE_KING_INITIAL_POSITION, WHITE_KING_VALUE,
-    }, zobrist::Zobrist
+        BLACK_KING_INITIAL_POSITION, BLACK_KING_SIDE_ROOK_POSITION, BLACK_KING_VALUE, BLACK_PAWN_VALUE,
+        BLACK_QUEEN_SIDE_ROOK_POSITION, WHITE_KING_INITIAL_POSITION, WHITE_KING_SIDE_ROOK_POSITION,
+        WHITE_KING_VALUE, WHITE_PAWN_VALUE, WHITE_QUEEN_SIDE_ROOK_POSITION,
+    }, bitboard::{get_square_bit, Bitboard, EMPTY_BITBOARD}, move_generator_helper::is_position_attacked,
+    zobrist::{compute_hash, get_castling_key, get_en_passant_hash, get_piece_key, get_white_to_move_key},
+    zobrist_utils::get_piece_index
 };
 
+/// What a move changes that can't be recovered from the move itself, so it can be unmade
+/// without keeping copies of the whole state
+#[derive(Debug, Clone, Copy)]
+pub struct UndoRecord {
+    black_able_to_king_side_castle: bool,
+    black_able_to_queen_side_castle: bool,
+    black_en_passant: i8,
+    black_king_in_check: bool,
+    black_king_moved: bool,
+    captured_piece: u8,
+    draw_reason: Option<DrawReason>,
+    full_moves: usize,
+    half_moves: usize,
+    moved_piece: u8,
+    white_able_to_king_side_castle: bool,
+    white_able_to_queen_side_castle: bool,
+    white_en_passant: i8,
+    white_king_in_check: bool,
+    white_king_moved: bool,
+    white_move: bool,
+    winner: u8,
+    zobrist_hash: u64,
+}
+
+impl UndoRecord {
+    pub fn get_zobrist_hash(&self) -> u64 {
+        self.zobrist_hash
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BoardState {
+    // Squares of every piece, indexed like the Zobrist table
+    bitboards: [Bitboard; 12],
     black_able_to_king_side_castle: bool,
     black_able_to_queen_side_castle: bool,
     black_captures: Vec<u8>,
@@ -18,6 +53,8 @@ pub struct BoardState {
     black_king_in_check: bool,
     black_king_moved: bool,
     black_king_position: i8,
+    black_occupancy: Bitboard,
+    draw_reason: Option<DrawReason>,
     full_moves: usize,
     half_moves: usize,
     white_move: bool,
@@ -29,15 +66,15 @@ pub struct BoardState {
     white_king_in_check: bool,
     white_king_moved: bool,
     white_king_position: i8,
+    white_occupancy: Bitboard,
     winner: u8,
-    zobrist: Zobrist,
+    zobrist_hash: u64,
 }
 
 impl BoardState {
     pub fn new() -> Self {
-        let mut zobrist = Zobrist::new();
-
         let mut board_state = BoardState {
+            bitboards: [EMPTY_BITBOARD; 12],
             black_able_to_king_side_castle: true,
             black_able_to_queen_side_castle: true,
             black_captures: Vec::with_capacity(16),
@@ -45,6 +82,8 @@ impl BoardState {
             black_king_in_check: false,
             black_king_moved: false,
             black_king_position: BLACK_KING_INITIAL_POSITION,
+            black_occupancy: EMPTY_BITBOARD,
+            draw_reason: None,
             full_moves: 0,
             half_moves: 0,
             white_move: true,
@@ -56,15 +95,12 @@ impl BoardState {
             white_king_in_check: false,
             white_king_moved: false,
             white_king_position: WHITE_KING_INITIAL_POSITION,
+            white_occupancy: EMPTY_BITBOARD,
             winner: 0,
-            zobrist,
+            zobrist_hash: 0,
         };
 
-        zobrist = Zobrist::new();
-
-        zobrist.compute_hash(&board_state);
-
-        board_state.zobrist = zobrist;
+        board_state.zobrist_hash = compute_hash(&board_state);
 
         board_state
     }
@@ -102,6 +138,23 @@ impl BoardState {
             self.white_king_position = position;
         }
 
+        let square_bit = get_square_bit(position);
+
+        for value in [self.squares[position as usize], piece] {
+            if value == EMPTY_PIECE {
+                continue;
+            }
+
+            self.bitboards[get_piece_index(value)] ^= square_bit;
+            self.zobrist_hash ^= get_piece_key(value, position);
+
+            if is_white_piece(value) {
+                self.white_occupancy ^= square_bit;
+            } else {
+                self.black_occupancy ^= square_bit;
+            }
+        }
+
         self.squares[position as usize] = piece;
     }
 
@@ -113,13 +166,6 @@ impl BoardState {
 
         self.place_piece(from_position, EMPTY_PIECE);
 
-        self.zobrist.update_hash_on_move(
-            from_position as usize,
-            to_position as usize,
-            moved_piece,
-            captured_piece,
-        );
-
         if !rook_castling {
             if !self.is_white_move() {
                 self.increment_full_moves();
@@ -149,77 +195,212 @@ impl BoardState {
         }
     }
 
+    /// Snapshot taken before playing the move, the captured piece is the pawn
+    /// next to the pawn moving for en passant captures
+    pub fn get_undo_record(&self, piece_move: &PieceMove) -> UndoRecord {
+        let from_position = piece_move.get_from_position();
+        let to_position = piece_move.get_to_position();
+
+        let moved_piece = self.get_piece(from_position);
+
+        let captured_piece = if piece_move.is_en_passant() {
+            self.get_piece(get_en_passant_capture_position(moved_piece, to_position))
+        } else {
+            self.get_piece(to_position)
+        };
+
+        UndoRecord {
+            black_able_to_king_side_castle: self.black_able_to_king_side_castle,
+            black_able_to_queen_side_castle: self.black_able_to_queen_side_castle,
+            black_en_passant: self.black_en_passant,
+            black_king_in_check: self.black_king_in_check,
+            black_king_moved: self.black_king_moved,
+            captured_piece,
+            draw_reason: self.draw_reason,
+            full_moves: self.full_moves,
+            half_moves: self.half_moves,
+            moved_piece,
+            white_able_to_king_side_castle: self.white_able_to_king_side_castle,
+            white_able_to_queen_side_castle: self.white_able_to_queen_side_castle,
+            white_en_passant: self.white_en_passant,
+            white_king_in_check: self.white_king_in_check,
+            white_king_moved: self.white_king_moved,
+            white_move: self.white_move,
+            winner: self.winner,
+            zobrist_hash: self.zobrist_hash,
+        }
+    }
+
+    /// Puts the pieces back where they were before the move and restores the record
+    pub fn unmake_move(&mut self, piece_move: &PieceMove, record: &UndoRecord) {
+        let from_position = piece_move.get_from_position();
+        let to_position = piece_move.get_to_position();
+
+        let moved_piece = record.moved_piece;
+        let white_piece = is_white_piece(moved_piece);
+
+        if is_piece_of_type(moved_piece, PieceType::King) && (to_position - from_position).abs() == 2 {
+            let (rook_position, castled_rook_position) = match (white_piece, to_position < from_position) {
+                (true, true) => (WHITE_QUEEN_SIDE_ROOK_POSITION, from_position - 1),
+                (true, false) => (WHITE_KING_SIDE_ROOK_POSITION, from_position + 1),
+                (false, true) => (BLACK_QUEEN_SIDE_ROOK_POSITION, from_position - 1),
+                (false, false) => (BLACK_KING_SIDE_ROOK_POSITION, from_position + 1),
+            };
+
+            let rook = self.get_piece(castled_rook_position);
+
+            self.place_piece(castled_rook_position, EMPTY_PIECE);
+            self.place_piece(rook_position, rook);
+        }
+
+        self.place_piece(to_position, EMPTY_PIECE);
+
+        if piece_move.is_en_passant() {
+            self.place_piece(get_en_passant_capture_position(moved_piece, to_position), record.captured_piece);
+        } else {
+            self.place_piece(to_position, record.captured_piece);
+        }
+
+        self.place_piece(from_position, moved_piece);
+
+        if record.captured_piece != EMPTY_PIECE {
+            if white_piece {
+                self.white_captures.pop();
+            } else {
+                self.black_captures.pop();
+            }
+        }
+
+        self.restore_record(record);
+    }
+
+    /// Passes the turn to the opponent without moving a piece. The positions before it
+    /// can't come back, so the half moves start over like after a capture.
+    pub fn make_null_move(&mut self) {
+        self.set_white_en_passant(INVALID_BOARD_POSITION);
+        self.set_black_en_passant(INVALID_BOARD_POSITION);
+
+        self.set_white_move(!self.white_move);
+
+        self.half_moves = 0;
+    }
+
+    /// Gives the turn back, the record is the one taken before the null move
+    pub fn unmake_null_move(&mut self, record: &UndoRecord) {
+        self.restore_record(record);
+    }
+
+    fn restore_record(&mut self, record: &UndoRecord) {
+        self.black_able_to_king_side_castle = record.black_able_to_king_side_castle;
+        self.black_able_to_queen_side_castle = record.black_able_to_queen_side_castle;
+        self.black_en_passant = record.black_en_passant;
+        self.black_king_in_check = record.black_king_in_check;
+        self.black_king_moved = record.black_king_moved;
+        self.draw_reason = record.draw_reason;
+        self.full_moves = record.full_moves;
+        self.half_moves = record.half_moves;
+        self.white_able_to_king_side_castle = record.white_able_to_king_side_castle;
+        self.white_able_to_queen_side_castle = record.white_able_to_queen_side_castle;
+        self.white_en_passant = record.white_en_passant;
+        self.white_king_in_check = record.white_king_in_check;
+        self.white_king_moved = record.white_king_moved;
+        self.white_move = record.white_move;
+        self.winner = record.winner;
+
+        self.zobrist_hash = record.zobrist_hash;
+    }
+
     pub fn is_valid_position(&self, position: i8) -> bool {
         position >= 0 && position < self.squares.len() as i8
     }
 
-    pub fn load_position(&mut self, fen_position: &str) {        
+    pub fn load_position(&mut self, fen_position: &str) -> Result<(), FenError> {
         let fields: Vec<&str> = fen_position.split_whitespace().collect();
 
-        self.load_pieces(fields[0]);
-        self.load_active_color(fields[1]);
-        self.load_castling(fields[2]);
-        self.load_en_passant(fields[3]);
+        // Ideally every fen should have all fields, but sometimes
+        // I copy some that don't have the move counters.
+        if !(4..=6).contains(&fields.len()) {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        }
+
+        // Nothing is changed unless the whole position is valid
+        let mut state = BoardState::new();
 
-        // Ideally every fen shoul have all fields, but sometimes
-        // I copy some that don't.
-        if fields.len() > 4 {
-            self.load_half_move_clock(fields[4]);
+        state.load_pieces(fields[0])?;
+        state.load_active_color(fields[1])?;
+        state.load_castling(fields[2])?;
+        state.load_en_passant(fields[3])?;
 
-            if fields.len() > 5 {
-                self.load_full_move_number(fields[5]);
-            }
+        if let Some(half_moves) = fields.get(4) {
+            state.load_half_move_clock(half_moves)?;
+        }
+
+        if let Some(full_moves) = fields.get(5) {
+            state.load_full_move_number(full_moves)?;
         }
 
-        self.zobrist.compute_hash(&self.clone());
+        state.validate_position()?;
+
+        state.zobrist_hash = compute_hash(&state);
+
+        *self = state;
+
+        Ok(())
     }
 
-    fn load_half_move_clock(&mut self, half_move: &str) {
-        if let Ok(value) = half_move.parse::<usize>() {
-            self.half_moves = value;
-        } else {
-            self.half_moves = 0;
-        }
+    fn load_half_move_clock(&mut self, half_moves: &str) -> Result<(), FenError> {
+        self.half_moves = half_moves
+            .parse::<usize>()
+            .map_err(|_| FenError::InvalidHalfMoveClock(half_moves.to_string()))?;
+
+        Ok(())
     }
 
-    fn load_full_move_number(&mut self, moves: &str) {
-        if let Ok(value) = moves.parse::<usize>() {
-            self.full_moves = value;
-        } else {
-            self.full_moves = 0;
-        }
+    fn load_full_move_number(&mut self, moves: &str) -> Result<(), FenError> {
+        self.full_moves = moves
+            .parse::<usize>()
+            .map_err(|_| FenError::InvalidFullMoveNumber(moves.to_string()))?;
+
+        Ok(())
     }
 
-    fn load_en_passant(&mut self, en_passant: &str) {
+    fn load_en_passant(&mut self, en_passant: &str) -> Result<(), FenError> {
+        self.white_en_passant = INVALID_BOARD_POSITION;
+        self.black_en_passant = INVALID_BOARD_POSITION;
+
         if en_passant == "-" {
-            self.white_en_passant = INVALID_BOARD_POSITION;
-            self.black_en_passant = INVALID_BOARD_POSITION;
-        } else {
-            let column = en_passant.chars().nth(0).unwrap();
-            let row: u8 = en_passant.chars().nth(1).unwrap().to_digit(10).unwrap() as u8;
+            return Ok(());
+        }
 
-            let mut is_white = false;
+        let position = get_position_from_notation(en_passant)
+            .ok_or_else(|| FenError::InvalidEnPassantSquare(en_passant.to_string()))?;
 
-            let row = if row == 3 {
-                is_white = true;
-                4
-            } else {
-                3
-            };
+        // The square behind a pawn that just moved two squares: rank 3 for white pawns
+        // (black to move) and rank 6 for black pawns (white to move).
+        let (rank, pawn_position, origin_position, pawn_value) = if self.white_move {
+            (6, position + 8, position - 8, BLACK_PAWN_VALUE)
+        } else {
+            (3, position - 8, position + 8, WHITE_PAWN_VALUE)
+        };
 
-            let position = (column as u8 - LETTER_A_UNICODE + (row * 8)) - 8;
+        if get_position_rank(position) != rank
+            || self.get_piece(pawn_position) != pawn_value
+            || self.get_piece(position) != EMPTY_PIECE
+            || self.get_piece(origin_position) != EMPTY_PIECE
+        {
+            return Err(FenError::ImpossibleEnPassantSquare(en_passant.to_string()));
+        }
 
-            if is_white {
-                self.white_en_passant = position as i8;
-                self.black_en_passant = INVALID_BOARD_POSITION;
-            } else {
-                self.black_en_passant = position as i8;
-                self.white_en_passant = INVALID_BOARD_POSITION;
-            }
+        if self.white_move {
+            self.black_en_passant = position;
+        } else {
+            self.white_en_passant = position;
         }
+
+        Ok(())
     }
 
-    fn load_castling(&mut self, castling: &str) {
+    fn load_castling(&mut self, castling: &str) -> Result<(), FenError> {
         if castling == "-" {
             self.black_able_to_queen_side_castle = false;
             self.black_able_to_king_side_castle = false;
@@ -227,55 +408,145 @@ impl BoardState {
             self.white_able_to_king_side_castle = false;
             self.black_king_moved = true;
             self.white_king_moved = true;
-        } else {
-            self.white_able_to_king_side_castle = castling.contains('K');
-            self.white_able_to_queen_side_castle = castling.contains('Q');
-            self.black_able_to_king_side_castle = castling.contains('k');
-            self.black_able_to_queen_side_castle = castling.contains('q');
 
-            self.white_king_moved = false;
-            self.black_king_moved = false;
+            return Ok(());
         }
+
+        if castling.is_empty() || castling.chars().any(|right| !"KQkq".contains(right)) {
+            return Err(FenError::InvalidCastlingRights(castling.to_string()));
+        }
+
+        // Right, king initial position and rook initial position
+        let rights = [
+            ('K', WHITE_KING_INITIAL_POSITION, WHITE_KING_SIDE_ROOK_POSITION, true),
+            ('Q', WHITE_KING_INITIAL_POSITION, WHITE_QUEEN_SIDE_ROOK_POSITION, true),
+            ('k', BLACK_KING_INITIAL_POSITION, BLACK_KING_SIDE_ROOK_POSITION, false),
+            ('q', BLACK_KING_INITIAL_POSITION, BLACK_QUEEN_SIDE_ROOK_POSITION, false),
+        ];
+
+        for (right, king_position, rook_position, white) in rights {
+            if !castling.contains(right) {
+                continue;
+            }
+
+            let (king_value, rook_value) = if white {
+                (WHITE_KING_VALUE, WHITE_ROOK)
+            } else {
+                (BLACK_KING_VALUE, BLACK_ROOK)
+            };
+
+            if self.get_piece(king_position) != king_value || self.get_piece(rook_position) != rook_value {
+                return Err(FenError::InconsistentCastlingRights(right));
+            }
+        }
+
+        self.white_able_to_king_side_castle = castling.contains('K');
+        self.white_able_to_queen_side_castle = castling.contains('Q');
+        self.black_able_to_king_side_castle = castling.contains('k');
+        self.black_able_to_queen_side_castle = castling.contains('q');
+
+        self.white_king_moved = false;
+        self.black_king_moved = false;
+
+        Ok(())
     }
 
-    fn load_active_color(&mut self, active_color: &str) {
+    fn load_active_color(&mut self, active_color: &str) -> Result<(), FenError> {
         match active_color {
             "w" => self.white_move = true,
             "b" => self.white_move = false,
-            _ => self.white_move = true,
+            _ => return Err(FenError::InvalidActiveColor(active_color.to_string())),
         }
+
+        Ok(())
     }
 
-    fn load_pieces(&mut self, board_rows: &str) {
+    fn load_pieces(&mut self, board_rows: &str) -> Result<(), FenError> {
         let rows: Vec<&str> = board_rows.split('/').collect();
 
-        let mut index: u8 = 0;
-
-        for row in rows.iter() {
-            self.generate_row_pieces_fen(row, &mut index);
+        if rows.len() != 8 {
+            return Err(FenError::WrongRankCount(rows.len()));
         }
-    }
 
-    fn generate_row_pieces_fen(&mut self, row: &&str, index: &mut u8) {
-        for char in row.chars() {
-            if char.is_numeric() {
-                *index += char.to_digit(10).unwrap() as u8;
-            } else {
-                self.squares[*index as usize] = get_fen_piece_value(&char);
+        let mut white_kings = 0;
+        let mut black_kings = 0;
+
+        for (row_index, row) in rows.iter().enumerate() {
+            let rank = 8 - row_index;
+            let mut index: usize = row_index * 8;
+            let row_end = index + 8;
+
+            for char in row.chars() {
+                if let Some(empty_squares) = char.to_digit(10) {
+                    if !(1..=8).contains(&empty_squares) {
+                        return Err(FenError::InvalidPieceChar(char));
+                    }
+
+                    index += empty_squares as usize;
+                } else {
+                    if get_piece_type(get_fen_piece_value(&char)) == PieceType::Empty {
+                        return Err(FenError::InvalidPieceChar(char));
+                    }
+
+                    if index >= row_end {
+                        return Err(FenError::RankOverflow(rank));
+                    }
+
+                    if char == 'k' {
+                        black_kings += 1;
+                    } else if char == 'K' {
+                        white_kings += 1;
+                    }
+
+                    self.place_piece(index as i8, get_fen_piece_value(&char));
 
-                if char == 'k' {
-                    self.black_king_position = *index as i8;
-                } else if char == 'K' {
-                    self.white_king_position = *index as i8;
+                    index += 1;
                 }
 
-                *index += 1;
+                if index > row_end {
+                    return Err(FenError::RankOverflow(rank));
+                }
+            }
+
+            if index < row_end {
+                return Err(FenError::IncompleteRank(rank));
+            }
+        }
+
+        for (kings, white) in [(white_kings, true), (black_kings, false)] {
+            if kings == 0 {
+                return Err(FenError::MissingKing { white });
+            } else if kings > 1 {
+                return Err(FenError::TooManyKings { white });
             }
         }
+
+        Ok(())
+    }
+
+    fn validate_position(&self) -> Result<(), FenError> {
+        for position in (0..8).chain(56..64) {
+            if is_piece_of_type(self.get_piece(position), PieceType::Pawn) {
+                return Err(FenError::PawnOnBackRank(get_position_notation(position)));
+            }
+        }
+
+        // The king of the side not to move could be captured
+        let (opponent_king_position, opponent_is_white) = if self.white_move {
+            (self.black_king_position, false)
+        } else {
+            (self.white_king_position, true)
+        };
+
+        if is_position_attacked(self, opponent_king_position, !opponent_is_white) {
+            return Err(FenError::SideNotToMoveInCheck);
+        }
+
+        Ok(())
     }
 
     pub fn get_fen(&self) -> String {
-        get_position_fen(&self)
+        get_position_fen(self)
     }
 
     pub fn is_black_able_to_king_side_castle(&self) -> bool {
@@ -310,6 +581,22 @@ impl BoardState {
         &self.squares
     }
 
+    pub fn get_piece_bitboard(&self, piece: u8) -> Bitboard {
+        self.bitboards[get_piece_index(piece)]
+    }
+
+    pub fn get_color_occupancy(&self, white: bool) -> Bitboard {
+        if white {
+            self.white_occupancy
+        } else {
+            self.black_occupancy
+        }
+    }
+
+    pub fn get_occupancy(&self) -> Bitboard {
+        self.white_occupancy | self.black_occupancy
+    }
+
     pub fn get_half_moves(&self) -> usize {
         self.half_moves
     }
@@ -331,7 +618,7 @@ impl BoardState {
     }
 
     pub fn get_zobrist_hash(&self) -> u64 {
-        self.zobrist.get_hash()
+        self.zobrist_hash
     }
 
     pub fn has_white_king_moved(&self) -> bool {
@@ -342,6 +629,10 @@ impl BoardState {
         self.winner
     }
 
+    pub fn get_draw_reason(&self) -> Option<DrawReason> {
+        self.draw_reason
+    }
+
     pub fn set_black_king_in_check(&mut self, black_king_in_check: bool) {
         self.black_king_in_check = black_king_in_check;
     }
@@ -362,7 +653,15 @@ impl BoardState {
         self.winner = value;
     }
 
+    pub fn set_draw_reason(&mut self, draw_reason: Option<DrawReason>) {
+        self.draw_reason = draw_reason;
+    }
+
     pub fn set_white_move(&mut self, white_move: bool) {
+        if self.white_move != white_move {
+            self.zobrist_hash ^= get_white_to_move_key();
+        }
+
         self.white_move = white_move;
     }
 
@@ -379,19 +678,19 @@ impl BoardState {
     }
 
     pub fn set_black_en_passant(&mut self, value: i8) {
-        if self.black_en_passant != value {
-            self.zobrist.update_hash_on_black_en_passant_change();
-        }
+        self.zobrist_hash ^= get_en_passant_hash(self);
 
         self.black_en_passant = value;
+
+        self.zobrist_hash ^= get_en_passant_hash(self);
     }
 
     pub fn set_white_en_passant(&mut self, value: i8) {
-        if self.white_en_passant != value {
-            self.zobrist.update_hash_on_white_en_passant_change();
-        }
+        self.zobrist_hash ^= get_en_passant_hash(self);
 
         self.white_en_passant = value;
+
+        self.zobrist_hash ^= get_en_passant_hash(self);
     }
 
     pub fn set_black_king_moved(&mut self, value: bool) {
@@ -403,56 +702,144 @@ impl BoardState {
     }
 
     pub fn set_white_able_to_king_side_castle(&mut self, value: bool) {
+        if self.white_able_to_king_side_castle != value {
+            self.zobrist_hash ^= get_castling_key(true, true);
+        }
+
         self.white_able_to_king_side_castle = value;
     }
 
     pub fn set_white_able_to_queen_side_castle(&mut self, value: bool) {
+        if self.white_able_to_queen_side_castle != value {
+            self.zobrist_hash ^= get_castling_key(true, false);
+        }
+
         self.white_able_to_queen_side_castle = value;
     }
 
     pub fn set_black_able_to_king_side_castle(&mut self, value: bool) {
+        if self.black_able_to_king_side_castle != value {
+            self.zobrist_hash ^= get_castling_key(false, true);
+        }
+
         self.black_able_to_king_side_castle = value;
     }
 
     pub fn set_black_able_to_queen_side_castle(&mut self, value: bool) {
+        if self.black_able_to_queen_side_castle != value {
+            self.zobrist_hash ^= get_castling_key(false, false);
+        }
+
         self.black_able_to_queen_side_castle = value;
     }
 
     pub fn update_castling_ability(&mut self, index: i8, is_black: bool, is_king_side: bool) {
         match (index, is_black, is_king_side) {
-            (0, true, false) => {
-                // BLACK_QUEEN_SIDE_ROOK_POSITION
-                if self.black_able_to_queen_side_castle {
-                    self.zobrist.update_hash_on_black_lose_queen_side_castle();
-                }
+            // BLACK_QUEEN_SIDE_ROOK_POSITION
+            (0, true, false) => self.set_black_able_to_queen_side_castle(false),
+            // BLACK_KING_SIDE_ROOK_POSITION
+            (7, true, true) => self.set_black_able_to_king_side_castle(false),
+            // WHITE_QUEEN_SIDE_ROOK_POSITION
+            (56, false, false) => self.set_white_able_to_queen_side_castle(false),
+            // WHITE_KING_SIDE_ROOK_POSITION
+            (63, false, true) => self.set_white_able_to_king_side_castle(false),
+            _ => {}
+        }
+    }
+}
 
-                self.black_able_to_queen_side_castle = false;
-            }
-            (7, true, true) => {
-                // BLACK_KING_SIDE_ROOK_POSITION
-                if self.black_able_to_king_side_castle {
-                    self.zobrist.update_hash_on_black_lose_rook_side_castle();
-                }
+/// Position of the pawn taken by an en passant capture landing on the given position
+pub fn get_en_passant_capture_position(pawn: u8, to_position: i8) -> i8 {
+    if is_white_piece(pawn) {
+        to_position + 8
+    } else {
+        to_position - 8
+    }
+}
 
-                self.black_able_to_king_side_castle = false;
-            }
-            (56, false, false) => {
-                // WHITE_QUEEN_SIDE_ROOK_POSITION
-                if self.white_able_to_queen_side_castle {
-                    self.zobrist.update_hash_on_white_lose_queen_side_castle()
-                }
+#[cfg(test)]
+mod tests {
+    use crate::common::{contants::INITIAL_FEN, fen_error::FenError};
 
-                self.white_able_to_queen_side_castle = false;
-            }
-            (63, false, true) => {
-                // WHITE_KING_SIDE_ROOK_POSITION
-                if self.white_able_to_king_side_castle {
-                    self.zobrist.update_hash_on_white_lose_rook_side_castle()
-                }
+    use super::BoardState;
 
-                self.white_able_to_king_side_castle = false;
-            }
-            _ => {}
-        }
+    fn load(fen: &str) -> Result<BoardState, FenError> {
+        let mut board_state = BoardState::new();
+
+        board_state.load_position(fen)?;
+
+        Ok(board_state)
+    }
+
+    fn load_error(fen: &str) -> Option<FenError> {
+        load(fen).err()
+    }
+
+    #[test]
+    fn test_load_valid_positions() {
+        assert_eq!(load(INITIAL_FEN).unwrap().get_fen(), INITIAL_FEN);
+
+        let board_state = load("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+
+        assert_eq!(board_state.get_white_en_passant(), 44);
+
+        let board_state = load("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2").unwrap();
+
+        assert_eq!(board_state.get_black_en_passant(), 19);
+
+        // Move counters are optional
+        assert!(load("8/8/4k3/8/8/3K4/8/8 w - -").is_ok());
+    }
+
+    #[test]
+    fn test_load_invalid_positions() {
+        assert_eq!(load_error(""), Some(FenError::WrongFieldCount(0)));
+        assert_eq!(load_error("8/8/8/8/8/8/8 w - - 0 1"), Some(FenError::WrongRankCount(7)));
+        assert_eq!(load_error("4k3/8/8/8/8/8/8/4K4 w - - 0 1"), Some(FenError::RankOverflow(1)));
+        assert_eq!(load_error("4k3/8/8/8/8/8/8/4K2 w - - 0 1"), Some(FenError::IncompleteRank(1)));
+        assert_eq!(load_error("4k3/8/8/8/8/8/8/4X3 w - - 0 1"), Some(FenError::InvalidPieceChar('X')));
+        assert_eq!(load_error("4k3/8/8/8/8/8/8/8 w - - 0 1"), Some(FenError::MissingKing { white: true }));
+        assert_eq!(load_error("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"), Some(FenError::TooManyKings { white: true }));
+        assert_eq!(
+            load_error("4k3/8/8/8/8/8/8/4K3 x - - 0 1"),
+            Some(FenError::InvalidActiveColor(String::from("x")))
+        );
+        assert_eq!(
+            load_error("4k3/8/8/8/8/8/8/4K3 w KX - 0 1"),
+            Some(FenError::InvalidCastlingRights(String::from("KX")))
+        );
+        assert_eq!(
+            load_error("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+            Some(FenError::InconsistentCastlingRights('K'))
+        );
+        assert_eq!(
+            load_error("4k3/8/8/8/8/8/8/4K3 w - z9 0 1"),
+            Some(FenError::InvalidEnPassantSquare(String::from("z9")))
+        );
+        assert_eq!(
+            load_error("4k3/8/8/8/8/8/8/4K3 w - e6 0 1"),
+            Some(FenError::ImpossibleEnPassantSquare(String::from("e6")))
+        );
+        assert_eq!(
+            load_error("4k3/8/8/8/8/8/8/4K3 w - - x 1"),
+            Some(FenError::InvalidHalfMoveClock(String::from("x")))
+        );
+        assert_eq!(
+            load_error("4k3/8/8/8/8/8/8/4K3 w - - 0 -1"),
+            Some(FenError::InvalidFullMoveNumber(String::from("-1")))
+        );
+        assert_eq!(
+            load_error("4k3/8/8/8/8/8/8/P3K3 w - - 0 1"),
+            Some(FenError::PawnOnBackRank(String::from("a1")))
+        );
+        assert_eq!(load_error("4k2R/8/8/8/8/8/8/4K3 w - - 0 1"), Some(FenError::SideNotToMoveInCheck));
+    }
+
+    #[test]
+    fn test_invalid_position_keeps_the_previous_state() {
+        let mut board_state = load(INITIAL_FEN).unwrap();
+
+        assert!(board_state.load_position("4k3/8/8/8/8/8/8/8 w - - 0 1").is_err());
+        assert_eq!(board_state.get_fen(), INITIAL_FEN);
     }
 }
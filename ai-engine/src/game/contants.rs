@@ -9,8 +9,6 @@ pub const BLACK_QUEEN_ROOK_POSITION: i8 = 0;
 pub const BLACK_QUEEN_SIDE_ROOK_POSITION: i8 = 0;
 pub const BLACK_KING_SIDE_ROOK_POSITION: i8 = 7;
 
-pub const LETTER_A_UNICODE: u8 = b'a';
-
 pub const WHITE_KING_INITIAL_POSITION: i8 = 60;
 pub const WHITE_KING_ROOK_POSITION: i8 = 63;
 pub const WHITE_KING_VALUE: u8 = PieceColor::White as u8 | PieceType::King as u8;
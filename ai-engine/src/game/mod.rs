@@ -1,9 +1,16 @@
 mod board_fen_utils;
+mod board_material_utils;
+pub mod bitboard;
 pub mod board;
 pub mod board_state;
 pub mod contants;
 pub mod move_generator;
 pub mod move_generator_helper;
 pub mod zobrist;
+mod zobrist_keys;
 pub mod zobrist_utils;
-pub mod moves_counter;
\ No newline at end of file
+pub mod move_parser;
+pub mod moves_counter;
+pub mod perft_suite;
+pub mod pgn;
+pub mod san;
\ No newline at end of file
@@ -1,8 +1,14 @@
 use crate::common::{
-    board_utils::{get_position_column, get_position_rank}, contants::{EMPTY_PIECE, INVALID_BOARD_POSITION}, piece::Piece, piece_move::PieceMove
+    contants::INVALID_BOARD_POSITION, enums::PieceType, piece_utils::get_piece_value
 };
 
-use super::{board_state::BoardState, move_generator::SquareOffset};
+use super::{
+    bitboard::{
+        get_bishop_attacks, get_king_attacks, get_knight_attacks, get_pawn_attacks, get_rook_attacks,
+        pop_first_position, Bitboard, EMPTY_BITBOARD,
+    },
+    board_state::BoardState,
+};
 
 pub fn is_pawn_first_move(white_piece: bool, piece_position: i8) -> bool {
     if white_piece && (48..=55).contains(&piece_position) {
@@ -16,87 +22,6 @@ pub fn is_pawn_first_move(white_piece: bool, piece_position: i8) -> bool {
     false
 }
 
-pub fn position_is_not_attacked(position: i8, opponent_moves: &[PieceMove]) -> bool {
-    !opponent_moves
-        .iter()
-        .any(|_mut| _mut.get_to_position() == position)
-}
-
-pub fn is_king_in_check(pieces: &[Piece], king_position: i8, white_move: bool) -> bool {
-    for board_piece in pieces.iter() {
-        if board_piece.get_value() == EMPTY_PIECE {
-            continue;
-        }
-
-        if board_piece.is_white() != white_move
-            && board_piece
-                .get_moves_reference()
-                .iter()
-                .any(|m| m.get_to_position() == king_position)
-        {
-            return true;
-        }
-    }
-
-    false
-}
-
-pub fn is_the_same_diagonal(position1: i8, position2: i8) -> bool {
-    let piece1_row = get_position_rank(position1);
-    let piece1_col = get_position_column(position1);
-
-    let piece2_row = get_position_rank(position2);
-    let piece2_col = get_position_column(position2);
-
-    (piece1_row as i8 - piece2_row as i8).abs() == (piece1_col as i8 - piece2_col as i8).abs()
-}
-
-pub fn is_path_clear(board_state: &BoardState, start: i8, end: i8, step: i8) -> bool {
-    let mut i = start;
-
-    while i != end {
-        if board_state.get_piece(i) != EMPTY_PIECE {
-            return false;
-        }
-        i += step;
-    }
-
-    true
-}
-
-pub fn is_king_straight_attacked_by_sliding_piece(board_state: &BoardState, king_position: i8, piece_position: i8) -> bool {
-    let piece_rank = get_position_rank(piece_position);
-    let king_rank = get_position_rank(king_position);
-
-    if piece_rank == king_rank {
-        return if piece_position < king_position {
-            is_path_clear(board_state, piece_position, king_position, SquareOffset::Right as i8)
-        } else {
-            is_path_clear(board_state, piece_position, king_position, SquareOffset::Left as i8)
-        }
-    } 
-
-    let piece_column = get_position_column(piece_position);
-    let king_column = get_position_column(king_position);
-
-    if piece_column == king_column {
-        return if piece_rank > king_rank {
-            is_path_clear(board_state, piece_position, king_position, SquareOffset::LineBelow as i8)
-        } else {
-            is_path_clear(board_state, piece_position, king_position, SquareOffset::LineAbove as i8)
-        };
-    }
-
-    false
-}
-
-pub fn is_diagonal_offset(offset: SquareOffset) -> bool {
-    (offset == SquareOffset::TopLeft) || 
-    (offset == SquareOffset::TopRight) || 
-    (offset == SquareOffset::BottomLeft) || 
-    (offset == SquareOffset::BottomRight)
-}
-
 pub fn get_adjacent_position(current_position: i8, new_position: i8) -> i8 {
     if !(0..=63).contains(&new_position) {
         return INVALID_BOARD_POSITION;
@@ -125,21 +50,37 @@ pub fn get_adjacent_position(current_position: i8, new_position: i8) -> i8 {
     new_position
 }
 
-pub fn get_knight_move(lines_apart: i8, new_position: i8, current_position: i8) -> i8 {
-    if get_line_distance_between_positions(current_position, new_position) == lines_apart {
-        return new_position;
-    }
+/// Pieces of the given color attacking the position with the given occupancy, regardless of pins.
+/// Pieces missing from the occupancy are ignored, so moves can be tried without playing them.
+pub fn get_attackers(board_state: &BoardState, position: i8, by_white: bool, occupancy: Bitboard) -> Bitboard {
+    let pieces = |piece_type: PieceType| board_state.get_piece_bitboard(get_piece_value(piece_type, by_white));
+
+    let queens = pieces(PieceType::Queen);
+
+    // A pawn attacks the position if a pawn of the other color there would attack it back
+    let attackers = (get_pawn_attacks(position, !by_white) & pieces(PieceType::Pawn))
+        | (get_knight_attacks(position) & pieces(PieceType::Knight))
+        | (get_king_attacks(position) & pieces(PieceType::King))
+        | (get_bishop_attacks(position, occupancy) & (pieces(PieceType::Bishop) | queens))
+        | (get_rook_attacks(position, occupancy) & (pieces(PieceType::Rook) | queens));
 
-    INVALID_BOARD_POSITION
+    attackers & occupancy
 }
 
-pub fn get_line_distance_between_positions(position1: i8, position2: i8) -> i8 {
-    let line_start1 = position1 - (position1 % 8);
-    let line_start2 = position2 - (position2 % 8);
+/// Whether any piece of the given color attacks the position, regardless of pins
+pub fn is_position_attacked(board_state: &BoardState, position: i8, by_white: bool) -> bool {
+    get_attackers(board_state, position, by_white, board_state.get_occupancy()) != EMPTY_BITBOARD
+}
+
+/// Positions of the pieces of the given color attacking the position, regardless of pins
+pub fn get_attacker_positions(board_state: &BoardState, position: i8, by_white: bool) -> Vec<i8> {
+    let mut attackers = get_attackers(board_state, position, by_white, board_state.get_occupancy());
+
+    let mut positions = Vec::with_capacity(attackers.count_ones() as usize);
 
-    if line_start1 > line_start2 {
-        return (line_start1 - line_start2) / 8;
+    while attackers != EMPTY_BITBOARD {
+        positions.push(pop_first_position(&mut attackers));
     }
 
-    (line_start2 - line_start1) / 8
+    positions
 }
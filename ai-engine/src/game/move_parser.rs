@@ -0,0 +1,295 @@
+use crate::common::{
+    board_utils::{get_position_column, get_position_from_notation, get_position_rank},
+    enums::PieceType,
+    fen_utils::get_fen_piece_value,
+    move_parse_error::MoveParseError,
+    piece_move::PieceMove,
+    piece_utils::{get_piece_type, is_white_piece},
+};
+
+use super::{board::Board, san::get_san_notation};
+
+/// Parses a move written either in coordinate notation (`e2e4`, `e7e8q`) or in
+/// SAN (`Nbd7`, `exd6`, `O-O`, `e8=Q+`) against the legal moves of the board
+pub fn parse_move(board: &mut Board, notation: &str) -> Result<PieceMove, MoveParseError> {
+    let notation = notation.trim();
+
+    if is_coordinate_notation(notation) {
+        parse_uci_move(board, notation)
+    } else {
+        parse_san_move(board, notation)
+    }
+}
+
+fn is_coordinate_notation(notation: &str) -> bool {
+    notation.len() >= 4
+        && notation.is_ascii()
+        && get_position_from_notation(&notation[0..2]).is_some()
+        && get_position_from_notation(&notation[2..4]).is_some()
+}
+
+/// Parses coordinate notation as sent by UCI and xboard (`e2e4`, `e7e8q`).
+/// Promotions without an explicit piece are promoted to a queen.
+pub fn parse_uci_move(board: &mut Board, notation: &str) -> Result<PieceMove, MoveParseError> {
+    if !is_coordinate_notation(notation) || notation.len() > 5 {
+        return Err(MoveParseError::InvalidNotation(notation.to_string()));
+    }
+
+    let from_position = get_position_from_notation(&notation[0..2]).unwrap();
+    let to_position = get_position_from_notation(&notation[2..4]).unwrap();
+    let promotion_char = notation[4..].chars().next();
+
+    let mut piece_move = board
+        .get_legal_moves()
+        .into_iter()
+        .find(|_move| _move.get_from_position() == from_position && _move.get_to_position() == to_position)
+        .ok_or_else(|| MoveParseError::IllegalMove(notation.to_string()))?;
+
+    if piece_move.is_promotion() {
+        let promotion_char = promotion_char.unwrap_or('q');
+
+        if !matches!(promotion_char.to_ascii_lowercase(), 'q' | 'r' | 'b' | 'n') {
+            return Err(MoveParseError::InvalidNotation(notation.to_string()));
+        }
+
+        let white = is_white_piece(piece_move.get_piece_value());
+
+        piece_move.set_promotion_value(get_promotion_value(promotion_char, white));
+    } else if promotion_char.is_some() {
+        return Err(MoveParseError::IllegalMove(notation.to_string()));
+    }
+
+    Ok(piece_move)
+}
+
+/// Parses Standard Algebraic Notation. Check, mate and annotation suffixes are ignored,
+/// as is a missing or extra capture sign.
+pub fn parse_san_move(board: &mut Board, notation: &str) -> Result<PieceMove, MoveParseError> {
+    let san = notation.trim_end_matches(['+', '#', '!', '?']);
+
+    let legal_moves = board.get_legal_moves();
+
+    let mut candidates: Vec<PieceMove> = match san {
+        "O-O" | "0-0" => get_castling_moves(&legal_moves, true),
+        "O-O-O" | "0-0-0" => get_castling_moves(&legal_moves, false),
+        _ => {
+            let san_move = SanMove::parse(san)
+                .ok_or_else(|| MoveParseError::InvalidNotation(notation.to_string()))?;
+
+            let candidates: Vec<PieceMove> = legal_moves
+                .into_iter()
+                .filter(|piece_move| san_move.matches(piece_move))
+                .collect();
+
+            if let Some(promotion_char) = san_move.promotion {
+                candidates
+                    .into_iter()
+                    .map(|mut piece_move| {
+                        let white = is_white_piece(piece_move.get_piece_value());
+
+                        piece_move.set_promotion_value(get_promotion_value(promotion_char, white));
+
+                        piece_move
+                    })
+                    .collect()
+            } else if candidates.iter().any(|piece_move| piece_move.is_promotion()) {
+                return Err(MoveParseError::MissingPromotion(notation.to_string()));
+            } else {
+                candidates
+            }
+        }
+    };
+
+    match candidates.len() {
+        0 => Err(MoveParseError::IllegalMove(notation.to_string())),
+        1 => Ok(candidates.remove(0)),
+        _ => {
+            let mut candidates: Vec<String> = candidates
+                .iter()
+                .map(|piece_move| get_san_notation(board, piece_move))
+                .collect();
+
+            candidates.sort();
+
+            Err(MoveParseError::AmbiguousMove {
+                notation: notation.to_string(),
+                candidates,
+            })
+        }
+    }
+}
+
+fn get_castling_moves(legal_moves: &[PieceMove], king_side: bool) -> Vec<PieceMove> {
+    let offset = if king_side { 2 } else { -2 };
+
+    legal_moves
+        .iter()
+        .filter(|piece_move| {
+            get_piece_type(piece_move.get_piece_value()) == PieceType::King
+                && piece_move.get_to_position() - piece_move.get_from_position() == offset
+        })
+        .cloned()
+        .collect()
+}
+
+fn get_promotion_value(promotion_char: char, white: bool) -> u8 {
+    if white {
+        get_fen_piece_value(&promotion_char.to_ascii_uppercase())
+    } else {
+        get_fen_piece_value(&promotion_char.to_ascii_lowercase())
+    }
+}
+
+#[derive(Debug)]
+struct SanMove {
+    piece_type: PieceType,
+    from_column: Option<usize>,
+    from_rank: Option<usize>,
+    to_position: i8,
+    promotion: Option<char>,
+}
+
+impl SanMove {
+    fn parse(san: &str) -> Option<SanMove> {
+        if !san.is_ascii() {
+            return None;
+        }
+
+        let mut chars: Vec<char> = san.chars().filter(|&char| char != 'x' && char != '=').collect();
+
+        let piece_type = match chars.first()? {
+            'K' => PieceType::King,
+            'Q' => PieceType::Queen,
+            'R' => PieceType::Rook,
+            'B' => PieceType::Bishop,
+            'N' => PieceType::Knight,
+            _ => PieceType::Pawn,
+        };
+
+        if piece_type != PieceType::Pawn {
+            chars.remove(0);
+        }
+
+        // Squares always end with the rank, a trailing letter is the promotion piece
+        let promotion = if chars.last()?.is_ascii_alphabetic() {
+            let promotion_char = chars.pop()?;
+
+            if piece_type != PieceType::Pawn
+                || !matches!(promotion_char.to_ascii_lowercase(), 'q' | 'r' | 'b' | 'n')
+            {
+                return None;
+            }
+
+            Some(promotion_char)
+        } else {
+            None
+        };
+
+        if chars.len() < 2 || chars.len() > 4 {
+            return None;
+        }
+
+        let to_notation: String = chars.split_off(chars.len() - 2).into_iter().collect();
+        let to_position = get_position_from_notation(&to_notation)?;
+
+        let mut from_column = None;
+        let mut from_rank = None;
+
+        for char in chars {
+            match char {
+                'a'..='h' if from_column.is_none() && from_rank.is_none() => {
+                    from_column = Some((char as u8 - b'a') as usize)
+                }
+                '1'..='8' if from_rank.is_none() => from_rank = char.to_digit(10).map(|rank| rank as usize),
+                _ => return None,
+            }
+        }
+
+        Some(SanMove {
+            piece_type,
+            from_column,
+            from_rank,
+            to_position,
+            promotion,
+        })
+    }
+
+    fn matches(&self, piece_move: &PieceMove) -> bool {
+        let from_position = piece_move.get_from_position();
+
+        get_piece_type(piece_move.get_piece_value()) == self.piece_type
+            && piece_move.get_to_position() == self.to_position
+            && self.from_column.is_none_or(|column| get_position_column(from_position) == column)
+            && self.from_rank.is_none_or(|rank| get_position_rank(from_position) == rank)
+            && (self.promotion.is_none() || piece_move.is_promotion())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        common::{
+            board_utils::get_uci_move_notation, contants::INITIAL_FEN,
+            move_parse_error::MoveParseError,
+        },
+        game::board::Board,
+    };
+
+    use super::parse_move;
+
+    fn parse(fen: &str, notation: &str) -> Result<String, MoveParseError> {
+        let mut board = Board::new();
+
+        board.load_position(fen).unwrap();
+
+        parse_move(&mut board, notation).map(|piece_move| get_uci_move_notation(&piece_move))
+    }
+
+    #[test]
+    fn test_parse_san() {
+        assert_eq!(parse(INITIAL_FEN, "e4"), Ok(String::from("e2e4")));
+        assert_eq!(parse(INITIAL_FEN, "Nf3"), Ok(String::from("g1f3")));
+        assert_eq!(parse(INITIAL_FEN, "Nf3+!?"), Ok(String::from("g1f3")));
+
+        let fen = "r3k2r/1P6/8/3pP3/8/5N2/8/RN2K2R w KQkq d6 0 1";
+
+        assert_eq!(parse(fen, "exd6"), Ok(String::from("e5d6")));
+        assert_eq!(parse(fen, "Nbd2"), Ok(String::from("b1d2")));
+        assert_eq!(parse(fen, "N1d2"), Ok(String::from("b1d2")));
+        assert_eq!(parse(fen, "O-O"), Ok(String::from("e1g1")));
+        assert_eq!(parse(fen, "0-0-0"), Err(MoveParseError::IllegalMove(String::from("0-0-0"))));
+        assert_eq!(parse(fen, "bxa8=N"), Ok(String::from("b7a8n")));
+        assert_eq!(parse(fen, "b8Q"), Ok(String::from("b7b8q")));
+    }
+
+    #[test]
+    fn test_parse_coordinate_notation() {
+        assert_eq!(parse(INITIAL_FEN, "e2e4"), Ok(String::from("e2e4")));
+
+        let fen = "8/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+
+        assert_eq!(parse(fen, "e7e8n"), Ok(String::from("e7e8n")));
+        assert_eq!(parse(fen, "e7e8"), Ok(String::from("e7e8q")));
+        assert_eq!(parse(fen, "e1e3"), Err(MoveParseError::IllegalMove(String::from("e1e3"))));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let fen = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+
+        assert_eq!(
+            parse(fen, "Nd2"),
+            Err(MoveParseError::AmbiguousMove {
+                notation: String::from("Nd2"),
+                candidates: vec![String::from("Nbd2"), String::from("Nfd2")],
+            })
+        );
+        assert_eq!(parse(fen, "Nz9"), Err(MoveParseError::InvalidNotation(String::from("Nz9"))));
+        assert_eq!(parse(fen, "hello"), Err(MoveParseError::InvalidNotation(String::from("hello"))));
+        assert_eq!(parse(fen, "Qd2"), Err(MoveParseError::IllegalMove(String::from("Qd2"))));
+
+        let fen = "8/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+
+        assert_eq!(parse(fen, "e8"), Err(MoveParseError::MissingPromotion(String::from("e8"))));
+    }
+}
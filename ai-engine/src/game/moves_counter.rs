@@ -1,84 +1,317 @@
-use std::sync::{atomic::{AtomicU64, Ordering}, Arc};
+use std::ops::AddAssign;
+
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::Serialize;
+
+use crate::common::{
+    board_utils::get_uci_move_notation, enums::PieceType, piece_move::PieceMove,
+    piece_utils::{get_piece_type, get_promotion_options, is_white_piece},
+};
+
+use super::{board::Board, move_generator_helper::get_attacker_positions};
 
-use crate::common::{board_utils::get_move_notation, contants::EMPTY_PIECE, piece_utils::get_promotion_options};
+/// Leaf node statistics, as in the perft results tables of the Chess Programming Wiki
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerftStatistics {
+    pub nodes: u64,
+    pub captures: u64,
+    pub en_passants: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    pub discovered_checks: u64,
+    pub double_checks: u64,
+    pub checkmates: u64,
+}
 
-use super::board::Board;
+impl AddAssign for PerftStatistics {
+    fn add_assign(&mut self, other: Self) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passants += other.en_passants;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.discovered_checks += other.discovered_checks;
+        self.double_checks += other.double_checks;
+        self.checkmates += other.checkmates;
+    }
+}
 
-pub fn count_moves(board: &mut Board, depth: usize, track_moves: bool) -> u64 {
-    if depth == 0 || board.is_game_finished() {
+/// Number of leaf nodes `depth` plies away from the current position
+pub fn count_moves(board: &mut Board, depth: usize) -> u64 {
+    if depth == 0 {
         return 1;
     }
 
-    let pieces = board.get_pieces();
+    perft_divide(board, depth).iter().map(|(_, nodes)| nodes).sum()
+}
 
-    let num_positions = Arc::new(AtomicU64::new(0));
+/// Leaf nodes under every legal move of the position, sorted by the move
+/// coordinate notation so it can be diffed against other engines
+pub fn perft_divide(board: &mut Board, depth: usize) -> Vec<(String, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
 
-    for piece in pieces.iter() {
-        if (piece.get_value() == EMPTY_PIECE) || (piece.is_white() != board.is_white_move()) {
-            continue;
-        }
+    let mut divide: Vec<(String, u64)> = get_perft_moves(board)
+        .par_iter()
+        .map(|piece_move| {
+            let mut board = board.clone();
+
+            let _ = board.move_piece(piece_move);
 
-        piece.get_moves_clone().par_iter().for_each(|piece_move|{
+            (get_uci_move_notation(piece_move), count_nodes(&mut board, depth - 1))
+        })
+        .collect();
+
+    divide.sort();
+
+    divide
+}
+
+/// Perft counting what kind of moves lead to the leaf nodes
+pub fn perft_statistics(board: &mut Board, depth: usize) -> PerftStatistics {
+    if depth == 0 {
+        return PerftStatistics { nodes: 1, ..Default::default() };
+    }
+
+    get_perft_moves(board)
+        .par_iter()
+        .map(|piece_move| {
             let mut board = board.clone();
-            let mut promotion_char_options = vec![piece_move.get_promotion_value()];
+            let mut statistics = PerftStatistics::default();
 
-            if piece_move.is_promotion() {
-                promotion_char_options = get_promotion_options(piece.is_white());
+            if depth == 1 {
+                add_leaf_statistics(&mut board, piece_move, &mut statistics);
+            } else {
+                let _ = board.move_piece(piece_move);
+
+                collect_statistics(&mut board, depth - 1, &mut statistics);
             }
 
-            let mut piece_move = piece_move.clone();
+            statistics
+        })
+        .reduce(PerftStatistics::default, |mut total, statistics| {
+            total += statistics;
+
+            total
+        })
+}
 
-            for promotion_option in promotion_char_options {
-                piece_move.set_promotion_value(promotion_option);
+/// Legal moves with one move per promotion piece
+fn get_perft_moves(board: &mut Board) -> Vec<PieceMove> {
+    let mut moves = Vec::new();
 
-                let _ = board.move_piece(&piece_move);
+    for piece_move in board.get_legal_moves() {
+        if !piece_move.is_promotion() {
+            moves.push(piece_move);
 
-                let moves_count = count_moves(&mut board, depth - 1, false);
-                
-                num_positions.fetch_add(moves_count, Ordering::SeqCst);
+            continue;
+        }
 
-                if track_moves {
-                    if piece_move.is_promotion() {
-                        println!(
-                            "{}{}: {}",
-                            get_move_notation(&piece_move),
-                            promotion_option.clone(),
-                            moves_count
-                        )
-                    } else {
-                        println!("{}: {}", get_move_notation(&piece_move), moves_count)
-                    }
-                }
+        for promotion_option in get_promotion_options(is_white_piece(piece_move.get_piece_value())) {
+            let mut promotion_move = piece_move.clone();
 
-                board.undo_last_move();
-            }
-        });
+            promotion_move.set_promotion_value(promotion_option);
+
+            moves.push(promotion_move);
+        }
     }
 
-    num_positions.load(Ordering::SeqCst)
+    moves
+}
+
+fn count_nodes(board: &mut Board, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = get_perft_moves(board);
+
+    // Bulk counting, the leaves don't need to be played
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+
+    for piece_move in moves {
+        let _ = board.move_piece(&piece_move);
+
+        nodes += count_nodes(board, depth - 1);
+
+        board.undo_last_move();
+    }
+
+    nodes
+}
+
+fn collect_statistics(board: &mut Board, depth: usize, statistics: &mut PerftStatistics) {
+    for piece_move in get_perft_moves(board) {
+        if depth == 1 {
+            add_leaf_statistics(board, &piece_move, statistics);
+
+            continue;
+        }
+
+        let _ = board.move_piece(&piece_move);
+
+        collect_statistics(board, depth - 1, statistics);
+
+        board.undo_last_move();
+    }
+}
+
+fn add_leaf_statistics(board: &mut Board, piece_move: &PieceMove, statistics: &mut PerftStatistics) {
+    let from_position = piece_move.get_from_position();
+    let to_position = piece_move.get_to_position();
+
+    let castle = get_piece_type(piece_move.get_piece_value()) == PieceType::King
+        && (to_position - from_position).abs() == 2;
+
+    statistics.nodes += 1;
+
+    if piece_move.is_capture() {
+        statistics.captures += 1;
+    }
+
+    if piece_move.is_en_passant() {
+        statistics.en_passants += 1;
+    }
+
+    if castle {
+        statistics.castles += 1;
+    }
+
+    if piece_move.is_promotion() {
+        statistics.promotions += 1;
+    }
+
+    let _ = board.move_piece(piece_move);
+
+    let board_state = board.get_state_reference();
+
+    let white_move = board_state.is_white_move();
+
+    let king_position = if white_move {
+        board_state.get_white_king_position()
+    } else {
+        board_state.get_black_king_position()
+    };
+
+    let checkers = get_attacker_positions(board_state, king_position, !white_move);
+
+    if !checkers.is_empty() {
+        statistics.checks += 1;
+
+        // Checks given by a piece other than the one that moved, the rook is the one
+        // that moved when castling so its checks are never discovered
+        if !castle && checkers.iter().any(|&position| position != to_position) {
+            statistics.discovered_checks += 1;
+        }
+
+        if checkers.len() > 1 {
+            statistics.double_checks += 1;
+        }
+
+        if board.get_legal_moves().is_empty() {
+            statistics.checkmates += 1;
+        }
+    }
+
+    board.undo_last_move();
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::game::{board::Board, moves_counter::count_moves};
+    use crate::game::{
+        board::Board,
+        moves_counter::{count_moves, perft_divide, perft_statistics, PerftStatistics},
+    };
 
     #[test]
     fn test_move_generation_count() {
         let mut board = Board::new();
         // Positions for initial FEN
 
-        assert_eq!(count_moves(&mut board, 1, false), 20);
-        assert_eq!(count_moves(&mut board, 2, false), 400);
-        assert_eq!(count_moves(&mut board, 3, false), 8_902);
-        assert_eq!(count_moves(&mut board, 4, false), 197_281);
+        assert_eq!(count_moves(&mut board, 1), 20);
+        assert_eq!(count_moves(&mut board, 2), 400);
+        assert_eq!(count_moves(&mut board, 3), 8_902);
+        assert_eq!(count_moves(&mut board, 4), 197_281);
 
         board
-            .load_position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+            .load_position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -")
+            .unwrap();
+
+        assert_eq!(count_moves(&mut board, 1), 48);
+        assert_eq!(count_moves(&mut board, 2), 2_039);
+        assert_eq!(count_moves(&mut board, 3), 97_862);
+        assert_eq!(count_moves(&mut board, 4), 4_085_603);
+    }
+
+    #[test]
+    fn test_perft_divide() {
+        let mut board = Board::new();
+
+        let divide = perft_divide(&mut board, 3);
+
+        assert_eq!(divide.len(), 20);
+        assert_eq!(divide[0], (String::from("a2a3"), 380));
+        assert!(divide.contains(&(String::from("e2e4"), 600)));
+        assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 8_902);
+    }
 
-        assert_eq!(count_moves(&mut board, 1, false), 48);
-        assert_eq!(count_moves(&mut board, 2, false), 2_039);
-        assert_eq!(count_moves(&mut board, 3, false), 97_862);
-        assert_eq!(count_moves(&mut board, 4, false), 4_085_603);
+    #[test]
+    fn test_perft_statistics() {
+        let mut board = Board::new();
+
+        assert_eq!(
+            perft_statistics(&mut board, 4),
+            PerftStatistics {
+                nodes: 197_281,
+                captures: 1_576,
+                checks: 469,
+                checkmates: 8,
+                ..Default::default()
+            }
+        );
+
+        board
+            .load_position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -")
+            .unwrap();
+
+        assert_eq!(
+            perft_statistics(&mut board, 3),
+            PerftStatistics {
+                nodes: 97_862,
+                captures: 17_102,
+                en_passants: 45,
+                castles: 3_162,
+                promotions: 0,
+                checks: 993,
+                discovered_checks: 0,
+                double_checks: 0,
+                checkmates: 1,
+            }
+        );
+
+        board.load_position("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+
+        assert_eq!(
+            perft_statistics(&mut board, 4),
+            PerftStatistics {
+                nodes: 43_238,
+                captures: 3_348,
+                en_passants: 123,
+                castles: 0,
+                promotions: 0,
+                checks: 1_680,
+                discovered_checks: 106,
+                double_checks: 0,
+                checkmates: 17,
+            }
+        );
     }
 }
@@ -0,0 +1,198 @@
+use std::time::{Duration, Instant};
+
+use super::{board::Board, moves_counter::perft_divide};
+
+/// A position of the suite with the expected leaf nodes for some depths
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerftSuiteEntry {
+    pub fen: String,
+    pub depths: Vec<(usize, u64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PerftMismatch {
+    pub fen: String,
+    pub depth: usize,
+    pub expected: u64,
+    pub found: u64,
+    // Leaf nodes under every root move, to compare against another engine
+    pub divide: Vec<(String, u64)>,
+}
+
+#[derive(Debug, Default)]
+pub struct PerftSuiteReport {
+    pub passed: usize,
+    pub skipped: usize,
+    pub mismatches: Vec<PerftMismatch>,
+}
+
+/// Reads perft EPD lines like `<fen> ;D1 20 ;D2 400`. Empty lines and lines
+/// starting with `#` are ignored.
+pub fn parse_epd(text: &str) -> Result<Vec<PerftSuiteEntry>, String> {
+    let mut entries = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line_number = index + 1;
+
+        let mut fields = line.split(';');
+
+        let fen = fields.next().unwrap_or("").trim().to_string();
+
+        Board::new()
+            .load_position(&fen)
+            .map_err(|err| format!("line {}: invalid fen, {}", line_number, err))?;
+
+        let mut depths = Vec::new();
+
+        for field in fields {
+            let field = field.trim();
+
+            let (depth, nodes) = field
+                .strip_prefix('D')
+                .and_then(|field| field.split_once(' '))
+                .and_then(|(depth, nodes)| {
+                    Some((depth.parse::<usize>().ok()?, nodes.trim().parse::<u64>().ok()?))
+                })
+                .ok_or_else(|| format!("line {}: invalid perft result '{}'", line_number, field))?;
+
+            depths.push((depth, nodes));
+        }
+
+        depths.sort();
+
+        entries.push(PerftSuiteEntry { fen, depths });
+    }
+
+    Ok(entries)
+}
+
+/// Runs every depth up to `max_depth` of the suite. Depths left once the time budget
+/// runs out are skipped, as are the ones deeper than a mismatch of the same position.
+pub fn run_perft_suite(
+    entries: &[PerftSuiteEntry],
+    max_depth: usize,
+    time_budget: Option<Duration>,
+) -> PerftSuiteReport {
+    let start = Instant::now();
+
+    let mut report = PerftSuiteReport::default();
+
+    for entry in entries {
+        let mut board = Board::new();
+
+        if board.load_position(&entry.fen).is_err() {
+            report.skipped += entry.depths.len();
+
+            continue;
+        }
+
+        let mut mismatch_found = false;
+
+        for &(depth, expected) in &entry.depths {
+            let out_of_time = time_budget.is_some_and(|budget| start.elapsed() >= budget);
+
+            if depth > max_depth || mismatch_found || out_of_time {
+                report.skipped += 1;
+
+                continue;
+            }
+
+            let divide = perft_divide(&mut board, depth);
+
+            let found = if depth == 0 {
+                1
+            } else {
+                divide.iter().map(|(_, nodes)| nodes).sum()
+            };
+
+            if found == expected {
+                report.passed += 1;
+
+                continue;
+            }
+
+            mismatch_found = true;
+
+            report.mismatches.push(PerftMismatch {
+                fen: entry.fen.clone(),
+                depth,
+                expected,
+                found,
+                divide,
+            });
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{parse_epd, run_perft_suite, PerftSuiteEntry};
+
+    const PERFT_SUITE: &str = include_str!("../../resources/perft_suite.epd");
+
+    #[test]
+    fn test_perft_suite() {
+        let entries = parse_epd(PERFT_SUITE).unwrap();
+
+        assert_eq!(entries.len(), 21);
+
+        let report = run_perft_suite(&entries, 3, None);
+
+        assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+        assert_eq!(report.passed, 63);
+    }
+
+    #[test]
+    fn test_report_mismatch() {
+        let entries = parse_epd("8/8/4k3/8/8/3K4/8/8 w - - 0 1 ;D2 1 ;D1 8 ;D3 1").unwrap();
+
+        assert_eq!(
+            entries[0],
+            PerftSuiteEntry {
+                fen: String::from("8/8/4k3/8/8/3K4/8/8 w - - 0 1"),
+                depths: vec![(1, 8), (2, 1), (3, 1)],
+            }
+        );
+
+        let report = run_perft_suite(&entries, 5, None);
+
+        assert_eq!(report.passed, 1);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.mismatches.len(), 1);
+
+        let mismatch = &report.mismatches[0];
+
+        assert_eq!((mismatch.depth, mismatch.expected), (2, 1));
+        assert_eq!(mismatch.divide.len(), 8);
+        assert_eq!(mismatch.divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), mismatch.found);
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let entries = parse_epd(PERFT_SUITE).unwrap();
+
+        let report = run_perft_suite(&entries, 3, Some(Duration::ZERO));
+
+        assert_eq!(report.passed, 0);
+        assert_eq!(report.skipped, entries.iter().map(|entry| entry.depths.len()).sum::<usize>());
+    }
+
+    #[test]
+    fn test_invalid_epd() {
+        assert!(parse_epd("8/8/8/8/8/8/8/8 w - - 0 1 ;D1 0").unwrap_err().starts_with("line 1: invalid fen"));
+        assert_eq!(
+            parse_epd("# comment\n\n8/8/4k3/8/8/3K4/8/8 w - - 0 1 ;D1 x").unwrap_err(),
+            "line 3: invalid perft result 'D1 x'"
+        );
+    }
+}
@@ -0,0 +1,907 @@
+use std::collections::VecDeque;
+
+use crate::common::{
+    contants::INITIAL_FEN, pgn_error::PgnError, piece_move::PieceMove,
+};
+
+use super::{board::Board, move_parser::parse_move, san::get_san_notation};
+
+pub const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+const MAX_LINE_LENGTH: usize = 79;
+
+/// A move of a game with its annotations and the alternatives to it
+#[derive(Debug, Clone)]
+pub struct PgnMove {
+    comment: Option<String>,
+    // Comment written before the move, only used at the start of variations
+    comment_before: Option<String>,
+    nags: Vec<u8>,
+    piece_move: PieceMove,
+    variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(piece_move: PieceMove) -> Self {
+        PgnMove {
+            comment: None,
+            comment_before: None,
+            nags: Vec::new(),
+            piece_move,
+            variations: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    comment: Option<String>,
+    headers: Vec<(String, String)>,
+    moves: Vec<PgnMove>,
+    // Position of the game in the text it was parsed from, errors refer to it
+    number: usize,
+    result: String,
+}
+
+impl PgnGame {
+    pub fn new() -> Self {
+        PgnGame {
+            comment: None,
+            headers: Vec::new(),
+            moves: Vec::new(),
+            number: 1,
+            result: String::from("*"),
+        }
+    }
+
+    /// Game played on the board, from the position it was loaded with
+    pub fn from_board(board: &Board) -> Self {
+        let mut game = PgnGame::new();
+
+        let initial_fen = board.get_initial_fen();
+
+        let mut replay_board = Board::new();
+
+        replay_board
+            .load_position(&initial_fen)
+            .expect("the board always holds a valid position");
+
+        for piece_move in board.get_move_history() {
+            let mut piece_move = piece_move.clone();
+
+            piece_move.set_notation(get_san_notation(&replay_board, &piece_move));
+
+            let _ = replay_board.move_piece(&piece_move);
+
+            game.moves.push(PgnMove::new(piece_move));
+        }
+
+        // Updates the winner of the current position
+        let mut final_board = board.clone();
+
+        final_board.get_pieces();
+
+        game.result = String::from(match final_board.get_winner_fen() {
+            'w' => "1-0",
+            'b' => "0-1",
+            'd' => "1/2-1/2",
+            _ => "*",
+        });
+
+        if initial_fen != INITIAL_FEN {
+            game.set_header("SetUp", "1");
+            game.set_header("FEN", &initial_fen);
+        }
+
+        let result = game.result.clone();
+
+        game.set_header("Result", &result);
+
+        game
+    }
+
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        match self.headers.iter_mut().find(|(header, _)| header == name) {
+            Some(header) => header.1 = value.to_string(),
+            None => self.headers.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn get_result(&self) -> &str {
+        &self.result
+    }
+
+    /// Moves of the main line, variations left aside
+    pub fn get_main_line(&self) -> impl Iterator<Item = &PieceMove> {
+        self.moves.iter().map(|pgn_move| &pgn_move.piece_move)
+    }
+
+    pub fn get_initial_fen(&self) -> &str {
+        self.get_header("FEN").unwrap_or(INITIAL_FEN)
+    }
+
+    /// Board after playing the main line
+    pub fn to_board(&self) -> Result<Board, PgnError> {
+        let mut board = Board::new();
+
+        board
+            .load_position(self.get_initial_fen())
+            .map_err(|error| PgnError::InvalidFen { game: self.number, error })?;
+
+        for (ply, pgn_move) in self.moves.iter().enumerate() {
+            board
+                .move_piece(&pgn_move.piece_move)
+                .map_err(|error| PgnError::UnplayableMove {
+                    game: self.number,
+                    ply: ply + 1,
+                    error,
+                })?;
+        }
+
+        Ok(board)
+    }
+
+    pub fn to_pgn(&self) -> String {
+        let mut result = String::new();
+
+        for name in SEVEN_TAG_ROSTER {
+            let value = match (name, self.get_header(name)) {
+                ("Result", _) => &self.result,
+                (_, Some(value)) => value,
+                ("Date", None) => "????.??.??",
+                (_, None) => "?",
+            };
+
+            result.push_str(&get_tag_pair(name, value));
+        }
+
+        for (name, value) in &self.headers {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) {
+                result.push_str(&get_tag_pair(name, value));
+            }
+        }
+
+        result.push('\n');
+
+        let mut tokens = Vec::new();
+
+        if let Some(comment) = &self.comment {
+            tokens.push(format!("{{{}}}", comment));
+        }
+
+        let (white_move, full_moves) = get_move_counters(self.get_initial_fen());
+
+        write_line(&mut tokens, &self.moves, white_move, full_moves);
+
+        tokens.push(self.result.clone());
+
+        result.push_str(&wrap_tokens(&tokens));
+        result.push('\n');
+
+        result
+    }
+}
+
+/// Reads every game of a PGN file, moves are validated against the board
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut parser = PgnParser::new(text);
+
+    let mut games = Vec::new();
+
+    while let Some(game) = parser.parse_game()? {
+        games.push(game);
+    }
+
+    Ok(games)
+}
+
+/// Reads every game of a PGN file like `parse_pgn`, except a malformed game only gives
+/// its error and the games after it are still read
+pub fn parse_pgn_games(text: &str) -> Vec<Result<PgnGame, PgnError>> {
+    let mut parser = PgnParser::new(text);
+
+    let mut games = Vec::new();
+
+    loop {
+        match parser.parse_game() {
+            Ok(Some(game)) => games.push(Ok(game)),
+            Ok(None) => return games,
+            Err(err) => {
+                games.push(Err(err));
+
+                parser.skip_game();
+            }
+        }
+    }
+}
+
+fn get_tag_pair(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn get_move_counters(fen: &str) -> (bool, usize) {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+
+    let white_move = fields.get(1) != Some(&"b");
+    let full_moves = fields
+        .get(5)
+        .and_then(|full_moves| full_moves.parse::<usize>().ok())
+        .unwrap_or(1);
+
+    (white_move, full_moves)
+}
+
+fn write_line(tokens: &mut Vec<String>, moves: &[PgnMove], mut white_move: bool, mut full_moves: usize) {
+    // Black moves need their number after anything that interrupts the move list
+    let mut needs_number = true;
+
+    for pgn_move in moves {
+        if let Some(comment) = &pgn_move.comment_before {
+            tokens.push(format!("{{{}}}", comment));
+
+            needs_number = true;
+        }
+
+        if white_move {
+            tokens.push(format!("{}.", full_moves));
+        } else if needs_number {
+            tokens.push(format!("{}...", full_moves));
+        }
+
+        tokens.push(pgn_move.piece_move.get_notation());
+
+        needs_number = false;
+
+        for nag in &pgn_move.nags {
+            tokens.push(format!("${}", nag));
+        }
+
+        if let Some(comment) = &pgn_move.comment {
+            tokens.push(format!("{{{}}}", comment));
+
+            needs_number = true;
+        }
+
+        for variation in &pgn_move.variations {
+            tokens.push(String::from("("));
+
+            write_line(tokens, variation, white_move, full_moves);
+
+            tokens.push(String::from(")"));
+
+            needs_number = true;
+        }
+
+        if !white_move {
+            full_moves += 1;
+        }
+
+        white_move = !white_move;
+    }
+}
+
+fn wrap_tokens(tokens: &[String]) -> String {
+    let mut result = String::new();
+    let mut line_length = 0;
+    let mut previous_token = "";
+
+    for token in tokens {
+        let separator = if line_length == 0 || previous_token == "(" || token == ")" {
+            ""
+        } else {
+            " "
+        };
+
+        if line_length > 0 && line_length + separator.len() + token.len() > MAX_LINE_LENGTH {
+            result.push('\n');
+
+            line_length = 0;
+        } else {
+            result.push_str(separator);
+
+            line_length += separator.len();
+        }
+
+        result.push_str(token);
+
+        line_length += token.len();
+        previous_token = token;
+    }
+
+    result
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Comment(String),
+    MoveNumber,
+    Nag(u8),
+    Result(String),
+    Symbol(String),
+    Tag(String, String),
+    VariationEnd,
+    VariationStart,
+}
+
+struct ParsedLine {
+    // Comment before the first move
+    comment: Option<String>,
+    moves: Vec<PgnMove>,
+    result: Option<String>,
+}
+
+struct PgnParser {
+    chars: Vec<char>,
+    game: usize,
+    index: usize,
+    line: usize,
+    lookahead: VecDeque<(Token, usize)>,
+}
+
+impl PgnParser {
+    fn new(text: &str) -> Self {
+        PgnParser {
+            chars: text.chars().collect(),
+            game: 0,
+            index: 0,
+            line: 1,
+            lookahead: VecDeque::new(),
+        }
+    }
+
+    fn parse_game(&mut self) -> Result<Option<PgnGame>, PgnError> {
+        self.game += 1;
+
+        let mut game = PgnGame::new();
+
+        game.number = self.game;
+
+        while let Some((Token::Tag(name, value), _)) = self.peek_token()? {
+            game.set_header(&name, &value);
+
+            self.next_token()?;
+        }
+
+        if game.headers.is_empty() && self.peek_token()?.is_none() {
+            return Ok(None);
+        }
+
+        let mut board = Board::new();
+
+        if let Some(fen) = game.get_header("FEN") {
+            board
+                .load_position(fen)
+                .map_err(|error| PgnError::InvalidFen { game: self.game, error })?;
+        }
+
+        let line = self.parse_line(&mut board, 0)?;
+
+        game.comment = line.comment;
+        game.moves = line.moves;
+        game.result = line.result.unwrap_or_else(|| String::from("*"));
+
+        Ok(Some(game))
+    }
+
+    /// Parses moves until the end of the variation, or until the result for the main line
+    fn parse_line(&mut self, board: &mut Board, depth: usize) -> Result<ParsedLine, PgnError> {
+        let mut comment: Option<String> = None;
+        let mut moves: Vec<PgnMove> = Vec::new();
+
+        loop {
+            let game = self.game;
+
+            let Some((token, line)) = self.next_token()? else {
+                if depth > 0 {
+                    return Err(PgnError::UnbalancedVariation { game: self.game, line: self.line });
+                }
+
+                return Ok(ParsedLine { comment, moves, result: None });
+            };
+
+            let unexpected = |token: &str| PgnError::UnexpectedToken {
+                game,
+                line,
+                token: token.to_string(),
+            };
+
+            match token {
+                Token::Comment(text) => match moves.last_mut() {
+                    Some(last_move) => append_comment(&mut last_move.comment, text),
+                    None => append_comment(&mut comment, text),
+                },
+                Token::MoveNumber => {}
+                Token::Nag(nag) => {
+                    let last_move = moves.last_mut().ok_or_else(|| unexpected(&format!("${}", nag)))?;
+
+                    last_move.nags.push(nag);
+                }
+                Token::Result(result) => {
+                    if depth > 0 {
+                        return Err(unexpected(&result));
+                    }
+
+                    return Ok(ParsedLine { comment, moves, result: Some(result) });
+                }
+                Token::Symbol(notation) => {
+                    let mut piece_move = parse_move(board, &notation).map_err(|error| {
+                        PgnError::IllegalMove { game, line, error }
+                    })?;
+
+                    piece_move.set_notation(get_san_notation(board, &piece_move));
+
+                    let _ = board.move_piece(&piece_move);
+
+                    moves.push(PgnMove::new(piece_move));
+                }
+                Token::Tag(name, value) => {
+                    if depth > 0 {
+                        return Err(unexpected(&name));
+                    }
+
+                    // Next game without a result for this one
+                    self.lookahead.push_front((Token::Tag(name, value), line));
+
+                    return Ok(ParsedLine { comment, moves, result: None });
+                }
+                Token::VariationEnd => {
+                    if depth == 0 {
+                        return Err(PgnError::UnbalancedVariation { game, line });
+                    }
+
+                    return Ok(ParsedLine { comment, moves, result: None });
+                }
+                Token::VariationStart => {
+                    if moves.is_empty() {
+                        return Err(unexpected("("));
+                    }
+
+                    // Variations replace the last move
+                    let mut variation_board = board.clone();
+
+                    variation_board.undo_last_move();
+
+                    let mut variation = self.parse_line(&mut variation_board, depth + 1)?;
+
+                    if let Some(first_move) = variation.moves.first_mut() {
+                        first_move.comment_before = variation.comment;
+
+                        moves.last_mut().unwrap().variations.push(variation.moves);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Moves past the rest of a malformed game, up to the tags after its movetext
+    fn skip_game(&mut self) {
+        self.lookahead.clear();
+
+        let line_start = self.chars[..self.index]
+            .iter()
+            .rposition(|&char| char == '\n')
+            .map_or(0, |position| position + 1);
+
+        let mut after_movetext = self.chars.get(line_start) != Some(&'[');
+
+        self.skip_line();
+
+        loop {
+            match self.peek_char() {
+                None => return,
+                Some('[') if after_movetext => return,
+                _ => {
+                    let text = self.skip_line();
+
+                    if !text.trim().is_empty() && !text.starts_with('[') {
+                        after_movetext = true;
+                    }
+                }
+            }
+        }
+    }
+
+    fn peek_token(&mut self) -> Result<Option<(Token, usize)>, PgnError> {
+        if self.lookahead.is_empty() {
+            if let Some((token, line, nag)) = self.read_token()? {
+                self.lookahead.push_back((token, line));
+
+                if let Some(nag) = nag {
+                    self.lookahead.push_back((Token::Nag(nag), line));
+                }
+            }
+        }
+
+        Ok(self.lookahead.front().cloned())
+    }
+
+    fn next_token(&mut self) -> Result<Option<(Token, usize)>, PgnError> {
+        if let Some(token) = self.lookahead.pop_front() {
+            return Ok(Some(token));
+        }
+
+        let Some((token, line, nag)) = self.read_token()? else {
+            return Ok(None);
+        };
+
+        if let Some(nag) = nag {
+            self.lookahead.push_back((Token::Nag(nag), line));
+        }
+
+        Ok(Some((token, line)))
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let char = self.peek_char()?;
+
+        self.index += 1;
+
+        if char == '\n' {
+            self.line += 1;
+        }
+
+        Some(char)
+    }
+
+    fn skip_line(&mut self) -> String {
+        let mut text = String::new();
+
+        while let Some(char) = self.next_char() {
+            if char == '\n' {
+                break;
+            }
+
+            text.push(char);
+        }
+
+        text
+    }
+
+    /// Next token and its line, along with the NAG of a move suffix annotation ("e4!")
+    fn read_token(&mut self) -> Result<Option<(Token, usize, Option<u8>)>, PgnError> {
+        loop {
+            let at_line_start = self.index == 0 || self.chars[self.index - 1] == '\n';
+
+            match self.peek_char() {
+                None => return Ok(None),
+                Some(char) if char.is_whitespace() => {
+                    self.next_char();
+                }
+                // Escaped lines are ignored
+                Some('%') if at_line_start => {
+                    self.skip_line();
+                }
+                Some(_) => break,
+            }
+        }
+
+        let line = self.line;
+        let char = self.next_char().unwrap();
+
+        let mut nag = None;
+
+        let token = match char {
+            '[' => self.read_tag(line)?,
+            '{' => self.read_comment(line)?,
+            ';' => Token::Comment(self.skip_line().trim().to_string()),
+            '(' => Token::VariationStart,
+            ')' => Token::VariationEnd,
+            '*' => Token::Result(String::from("*")),
+            '$' => {
+                let digits = self.read_while(|char| char.is_ascii_digit());
+
+                Token::Nag(digits.parse::<u8>().map_err(|_| PgnError::UnexpectedToken {
+                    game: self.game,
+                    line,
+                    token: format!("${}", digits),
+                })?)
+            }
+            _ => {
+                let mut symbol = char.to_string();
+
+                symbol.push_str(&self.read_while(|char| {
+                    !char.is_whitespace() && !"[]{}();$".contains(char)
+                }));
+
+                let (token, suffix_nag) = self.get_symbol_token(symbol);
+
+                nag = suffix_nag;
+
+                token
+            }
+        };
+
+        Ok(Some((token, line, nag)))
+    }
+
+    fn read_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let mut text = String::new();
+
+        while let Some(char) = self.peek_char() {
+            if !predicate(char) {
+                break;
+            }
+
+            text.push(char);
+
+            self.next_char();
+        }
+
+        text
+    }
+
+    fn get_symbol_token(&mut self, symbol: String) -> (Token, Option<u8>) {
+        if matches!(symbol.as_str(), "1-0" | "0-1" | "1/2-1/2") {
+            return (Token::Result(symbol), None);
+        }
+
+        // Move numbers may be glued to the move ("12.Nf3", "12...Nf6")
+        let digits = symbol.chars().take_while(|char| char.is_ascii_digit()).count();
+
+        if digits > 0 && (digits == symbol.len() || symbol[digits..].starts_with('.')) {
+            let number_length = digits + symbol[digits..].chars().take_while(|&char| char == '.').count();
+
+            // The index counts chars, the rest of the symbol may not be ASCII
+            self.index -= symbol[number_length..].chars().count();
+
+            return (Token::MoveNumber, None);
+        }
+
+        // Move suffix annotations are the same as the first NAGs
+        let annotation_start = symbol.trim_end_matches(['!', '?']).len();
+
+        let nag = match &symbol[annotation_start..] {
+            "!" => Some(1),
+            "?" => Some(2),
+            "!!" => Some(3),
+            "??" => Some(4),
+            "!?" => Some(5),
+            "?!" => Some(6),
+            _ => None,
+        };
+
+        (Token::Symbol(symbol[..annotation_start].to_string()), nag)
+    }
+
+    fn read_tag(&mut self, line: usize) -> Result<Token, PgnError> {
+        let invalid_tag = PgnError::InvalidTag { game: self.game, line };
+
+        self.read_while(|char| char.is_whitespace());
+
+        let name = self.read_while(|char| char.is_ascii_alphanumeric() || char == '_');
+
+        self.read_while(|char| char.is_whitespace());
+
+        if name.is_empty() || self.next_char() != Some('"') {
+            return Err(invalid_tag);
+        }
+
+        let mut value = String::new();
+
+        loop {
+            match self.next_char() {
+                Some('\\') => value.push(self.next_char().ok_or(invalid_tag.clone())?),
+                Some('"') => break,
+                Some('\n') | None => return Err(invalid_tag),
+                Some(char) => value.push(char),
+            }
+        }
+
+        self.read_while(|char| char.is_whitespace());
+
+        if self.next_char() != Some(']') {
+            return Err(invalid_tag);
+        }
+
+        Ok(Token::Tag(name, value))
+    }
+
+    fn read_comment(&mut self, line: usize) -> Result<Token, PgnError> {
+        let mut text = String::new();
+
+        loop {
+            match self.next_char() {
+                Some('}') => break,
+                Some(char) => text.push(char),
+                None => return Err(PgnError::UnterminatedComment { game: self.game, line }),
+            }
+        }
+
+        Ok(Token::Comment(text.split_whitespace().collect::<Vec<&str>>().join(" ")))
+    }
+}
+
+fn append_comment(comment: &mut Option<String>, text: String) {
+    match comment {
+        Some(comment) => {
+            comment.push(' ');
+            comment.push_str(&text);
+        }
+        None => *comment = Some(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        common::{move_parse_error::MoveParseError, pgn_error::PgnError},
+        game::{board::Board, move_parser::parse_move},
+    };
+
+    use super::{parse_pgn, parse_pgn_games, PgnGame};
+
+    const SAMPLE_PGN: &str = r#"[Event "Casual game"]
+[Site "Berlin GER"]
+[Date "1852.??.??"]
+[Round "?"]
+[White "Adolf Anderssen"]
+[Black "Jean Dufresne"]
+[Result "1-0"]
+
+1.e4 e5 2.Nf3 Nc6 3.Bc4 Bc5 {the Italian} 4.b4!? (4.c3 Nf6 (4...d6) 5.d4) 4...Bxb4
+; the Evans gambit
+5.c3 Ba5 $1 6.d4 exd4 7.O-O 1-0
+
+[Event "Second game"]
+[SetUp "1"]
+[FEN "6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 40"]
+
+40.Ra8# 1-0
+"#;
+
+    #[test]
+    fn test_parse_games() {
+        let games = parse_pgn(SAMPLE_PGN).unwrap();
+
+        assert_eq!(games.len(), 2);
+
+        let game = &games[0];
+
+        assert_eq!(game.get_header("White"), Some("Adolf Anderssen"));
+        assert_eq!(game.result, "1-0");
+        assert_eq!(game.moves.len(), 13);
+
+        let moves = &game.moves;
+
+        assert_eq!(moves[5].comment.as_deref(), Some("the Italian"));
+        assert_eq!(moves[6].piece_move.get_notation(), "b4");
+        assert_eq!(moves[6].nags, vec![5]);
+        assert_eq!(moves[7].comment.as_deref(), Some("the Evans gambit"));
+        assert_eq!(moves[9].nags, vec![1]);
+        assert_eq!(moves[12].piece_move.get_notation(), "O-O");
+
+        let variation = &moves[6].variations[0];
+
+        assert_eq!(variation.len(), 3);
+        assert_eq!(variation[0].piece_move.get_notation(), "c3");
+        assert_eq!(variation[1].variations[0][0].piece_move.get_notation(), "d6");
+
+        let game = &games[1];
+
+        assert_eq!(game.moves[0].piece_move.get_notation(), "Ra8#");
+
+        let mut board = game.to_board().unwrap();
+
+        board.get_pieces();
+
+        assert_eq!(board.get_winner_fen(), 'w');
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let games = parse_pgn(SAMPLE_PGN).unwrap();
+
+        let pgn = games[0].to_pgn();
+        let movetext = pgn.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+        assert!(pgn.starts_with("[Event \"Casual game\"]\n[Site \"Berlin GER\"]\n"));
+        assert!(pgn.lines().all(|line| line.len() <= 79));
+        assert!(movetext.contains("4. b4 $5 (4. c3 Nf6 (4... d6) 5. d4) 4... Bxb4"));
+        assert!(movetext.ends_with("7. O-O 1-0"));
+
+        let games_again = parse_pgn(&pgn).unwrap();
+
+        assert_eq!(games_again[0].to_pgn(), pgn);
+
+        let pgn = games[1].to_pgn();
+
+        assert!(pgn.contains("[FEN \"6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 40\"]"));
+        assert!(pgn.contains("40. Ra8# 1-0"));
+    }
+
+    #[test]
+    fn test_export_played_game() {
+        let mut board = Board::new();
+
+        for notation in ["f3", "e5", "g4", "Qh4"] {
+            let piece_move = parse_move(&mut board, notation).unwrap();
+
+            board.move_piece(&piece_move).unwrap();
+        }
+
+        let pgn = PgnGame::from_board(&board).to_pgn();
+
+        assert!(pgn.contains("[Result \"0-1\"]"));
+        assert!(pgn.contains("\n1. f3 e5 2. g4 Qh4# 0-1\n"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_pgn("1. e4 e5\n2. Ke3 *").unwrap_err(),
+            PgnError::IllegalMove {
+                game: 1,
+                line: 2,
+                error: MoveParseError::IllegalMove(String::from("Ke3")),
+            }
+        );
+        assert_eq!(
+            parse_pgn("1. e4 (1. d4 *").unwrap_err(),
+            PgnError::UnexpectedToken { game: 1, line: 1, token: String::from("*") }
+        );
+        assert_eq!(
+            parse_pgn("1. e4 {unfinished").unwrap_err(),
+            PgnError::UnterminatedComment { game: 1, line: 1 }
+        );
+        assert_eq!(
+            parse_pgn("[Event \"x\"]\n1. e4 *\n\n[Event]\n*").unwrap_err(),
+            PgnError::InvalidTag { game: 2, line: 4 }
+        );
+    }
+
+    #[test]
+    fn test_suffix_annotations_and_move_numbers() {
+        // The first move is peeked while looking for tags
+        let games = parse_pgn("e4! e5?! 2.Nf3 *").unwrap();
+
+        assert_eq!(games[0].moves[0].nags, vec![1]);
+        assert_eq!(games[0].moves[1].nags, vec![6]);
+
+        // Symbols glued to a move number are read again from the right char
+        assert_eq!(
+            parse_pgn("1.e4 e5 2.\u{2658}f3 *").unwrap_err(),
+            PgnError::IllegalMove {
+                game: 1,
+                line: 1,
+                error: MoveParseError::InvalidNotation(String::from("\u{2658}f3")),
+            }
+        );
+    }
+
+    #[test]
+    fn test_unplayable_main_line() {
+        let mut game = parse_pgn("[Event \"x\"]\n\n1. e4 e5 *").unwrap().remove(0);
+
+        game.set_header("FEN", "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+
+        assert!(matches!(
+            game.to_board(),
+            Err(PgnError::UnplayableMove { game: 1, ply: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_games_are_skipped() {
+        let pgn = "[Event \"a\"]\n\n1. e4 e5 *\n\n[Event \"b\"]\n\n1. e4 Ke7 2. Ke3 *\n\n\
+            [Event \"c\"]\n[Bad]\n\n1. d4 *\n\n[Event \"d\"]\n\n1. c4 *\n";
+
+        let games = parse_pgn_games(pgn);
+
+        assert_eq!(games.len(), 4);
+        assert_eq!(games[0].as_ref().unwrap().get_header("Event"), Some("a"));
+        assert!(matches!(games[1], Err(PgnError::IllegalMove { game: 2, .. })));
+        assert!(matches!(games[2], Err(PgnError::InvalidTag { game: 3, .. })));
+        assert_eq!(games[3].as_ref().unwrap().get_header("Event"), Some("d"));
+    }
+}
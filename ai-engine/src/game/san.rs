@@ -0,0 +1,283 @@
+use crate::common::{
+    board_utils::{get_position_column, get_position_file, get_position_notation, get_position_rank},
+    contants::{BLACK_QUEEN, EMPTY_PIECE, WHITE_QUEEN},
+    enums::PieceType,
+    fen_utils::get_piece_fen,
+    piece::Piece,
+    piece_move::PieceMove,
+    piece_utils::{get_piece_type, is_white_piece},
+};
+
+use super::board::Board;
+
+/// Standard Algebraic Notation of a legal move of the current position (`Nbd7`, `exd6`,
+/// `O-O`, `e8=Q#`). Promotions without a promotion piece are written as queen promotions.
+pub fn get_san_notation(board: &Board, piece_move: &PieceMove) -> String {
+    let mut board = board.clone();
+
+    let legal_moves = board.get_legal_moves();
+
+    get_san_notation_from_moves(&mut board, piece_move, &legal_moves)
+}
+
+/// Fills the notation of every move of the side to move in `pieces`, which must be the
+/// pieces of the current position. The opponent's moves can't be played, they are left as is.
+pub fn set_san_notations(board: &Board, pieces: &mut [Piece]) {
+    let white_move = board.is_white_move();
+
+    let legal_moves: Vec<PieceMove> = pieces
+        .iter()
+        .filter(|piece| piece.is_white() == white_move)
+        .flat_map(|piece| piece.get_moves_clone())
+        .collect();
+
+    // Every move is played and taken back on the same copy
+    let mut board = board.clone();
+
+    for piece in pieces.iter_mut().filter(|piece| piece.is_white() == white_move) {
+        let mut moves = piece.get_moves_clone();
+
+        for piece_move in moves.iter_mut() {
+            let notation = get_san_notation_from_moves(&mut board, piece_move, &legal_moves);
+
+            piece_move.set_notation(notation);
+        }
+
+        piece.set_moves(moves);
+    }
+}
+
+fn get_san_notation_from_moves(board: &mut Board, piece_move: &PieceMove, legal_moves: &[PieceMove]) -> String {
+    let mut piece_move = piece_move.clone();
+
+    if piece_move.is_promotion() && piece_move.get_promotion_value() == EMPTY_PIECE {
+        let queen = if is_white_piece(piece_move.get_piece_value()) {
+            WHITE_QUEEN
+        } else {
+            BLACK_QUEEN
+        };
+
+        piece_move.set_promotion_value(queen);
+    }
+
+    let mut result = get_move_body(&piece_move, legal_moves);
+
+    result.push_str(get_check_suffix(board, &piece_move));
+
+    result
+}
+
+fn get_move_body(piece_move: &PieceMove, legal_moves: &[PieceMove]) -> String {
+    let from_position = piece_move.get_from_position();
+    let to_position = piece_move.get_to_position();
+    let piece_type = get_piece_type(piece_move.get_piece_value());
+
+    if piece_type == PieceType::King && (from_position - to_position).abs() == 2 {
+        return String::from(if to_position > from_position { "O-O" } else { "O-O-O" });
+    }
+
+    let mut result = String::new();
+
+    if piece_type == PieceType::Pawn {
+        if piece_move.is_capture() {
+            result.push(get_position_file(from_position));
+        }
+    } else {
+        result.push(get_piece_fen(piece_move.get_piece_value()).to_ascii_uppercase());
+        result.push_str(&get_disambiguation(piece_move, legal_moves));
+    }
+
+    if piece_move.is_capture() {
+        result.push('x');
+    }
+
+    result.push_str(&get_position_notation(to_position));
+
+    if piece_move.is_promotion() {
+        result.push('=');
+        result.push(get_piece_fen(piece_move.get_promotion_value()).to_ascii_uppercase());
+    }
+
+    result
+}
+
+/// Origin file, rank or square needed to tell apart pieces of the same type
+/// that can move to the same square
+fn get_disambiguation(piece_move: &PieceMove, legal_moves: &[PieceMove]) -> String {
+    let from_position = piece_move.get_from_position();
+
+    let rivals: Vec<i8> = legal_moves
+        .iter()
+        .filter(|_move| {
+            _move.get_piece_value() == piece_move.get_piece_value()
+                && _move.get_to_position() == piece_move.get_to_position()
+                && _move.get_from_position() != from_position
+        })
+        .map(|_move| _move.get_from_position())
+        .collect();
+
+    if rivals.is_empty() {
+        return String::new();
+    }
+
+    let same_file = rivals
+        .iter()
+        .any(|&position| get_position_column(position) == get_position_column(from_position));
+
+    if !same_file {
+        return get_position_file(from_position).to_string();
+    }
+
+    let same_rank = rivals
+        .iter()
+        .any(|&position| get_position_rank(position) == get_position_rank(from_position));
+
+    if !same_rank {
+        return get_position_rank(from_position).to_string();
+    }
+
+    get_position_notation(from_position)
+}
+
+fn get_check_suffix(board: &mut Board, piece_move: &PieceMove) -> &'static str {
+    if board.move_piece(piece_move).is_err() {
+        return "";
+    }
+
+    // Updates the check flags and the winner of the new position
+    board.get_pieces();
+
+    let in_check = if board.is_white_move() {
+        board.is_white_king_in_check()
+    } else {
+        board.is_black_king_in_check()
+    };
+
+    let suffix = if !in_check {
+        ""
+    } else if matches!(board.get_winner_fen(), 'w' | 'b') {
+        "#"
+    } else {
+        "+"
+    };
+
+    board.undo_last_move();
+
+    suffix
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{common::board_utils::get_position_from_notation, game::board::Board};
+
+    use super::{get_san_notation, set_san_notations};
+
+    fn get_notation(fen: &str, from: &str, to: &str) -> String {
+        let mut board = Board::new();
+
+        board.load_position(fen).unwrap();
+
+        let from_position = get_position_from_notation(from).unwrap();
+        let to_position = get_position_from_notation(to).unwrap();
+
+        let piece_move = board
+            .get_legal_moves()
+            .into_iter()
+            .find(|_move| _move.get_from_position() == from_position && _move.get_to_position() == to_position)
+            .unwrap();
+
+        get_san_notation(&board, &piece_move)
+    }
+
+    #[test]
+    fn test_pawn_and_piece_moves() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+        assert_eq!(get_notation(fen, "e2", "e4"), "e4");
+        assert_eq!(get_notation(fen, "g1", "f3"), "Nf3");
+
+        let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+
+        assert_eq!(get_notation(fen, "e4", "d5"), "exd5");
+
+        let fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+
+        assert_eq!(get_notation(fen, "e5", "f6"), "exf6");
+    }
+
+    #[test]
+    fn test_disambiguation() {
+        // Knights on b1 and f3 can both reach d2
+        let fen = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+
+        assert_eq!(get_notation(fen, "b1", "d2"), "Nbd2");
+
+        // Rooks on the same file
+        let fen = "4k3/R7/8/8/8/8/R7/4K3 w - - 0 1";
+
+        assert_eq!(get_notation(fen, "a2", "a5"), "R2a5");
+
+        // Three queens need the full origin square
+        let fen = "8/8/k7/8/4Q2Q/8/8/K6Q w - - 0 1";
+
+        assert_eq!(get_notation(fen, "h4", "e1"), "Qh4e1");
+        assert_eq!(get_notation(fen, "h1", "e1"), "Q1e1");
+    }
+
+    #[test]
+    fn test_castling_and_promotion() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+
+        assert_eq!(get_notation(fen, "e1", "g1"), "O-O");
+        assert_eq!(get_notation(fen, "e1", "c1"), "O-O-O");
+
+        let fen = "8/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+
+        assert_eq!(get_notation(fen, "e7", "e8"), "e8=Q");
+    }
+
+    #[test]
+    fn test_check_and_mate() {
+        let fen = "6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1";
+
+        assert_eq!(get_notation(fen, "a1", "a8"), "Ra8#");
+        assert_eq!(get_notation(fen, "a1", "a7"), "Ra7");
+
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1";
+
+        assert_eq!(get_notation(fen, "a1", "a8"), "Ra8+");
+    }
+
+    #[test]
+    fn test_set_san_notations() {
+        let mut board = Board::new();
+
+        board.load_position("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+
+        let mut pieces = board.get_pieces();
+
+        let before = board.get_state_reference().get_fen();
+
+        set_san_notations(&board, &mut pieces);
+
+        let notations: Vec<String> = pieces
+            .iter()
+            .filter(|piece| piece.is_white())
+            .flat_map(|piece| piece.get_moves_clone())
+            .map(|piece_move| piece_move.get_notation())
+            .collect();
+
+        assert!(notations.contains(&String::from("O-O")));
+        assert!(notations.contains(&String::from("Ra8+")));
+
+        // The opponent's moves can't be played in this position
+        let black_king = pieces.iter().find(|piece| !piece.is_white()).unwrap();
+
+        assert!(black_king
+            .get_moves_reference()
+            .iter()
+            .all(|piece_move| !piece_move.get_notation().starts_with('K')));
+
+        assert_eq!(board.get_state_reference().get_fen(), before);
+    }
+}
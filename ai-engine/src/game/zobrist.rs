@@ -1,150 +1,251 @@
-extern crate rand;
+use crate::common::{
+    contants::{EMPTY_PIECE, INVALID_BOARD_POSITION},
+    enums::PieceType,
+    piece_utils::{get_piece_type, get_piece_value, is_white_piece},
+};
+
+use super::{bitboard::get_pawn_attacks, board_state::BoardState, zobrist_keys::POLYGLOT_RANDOM_64};
+
+const CASTLING_KEYS_OFFSET: usize = 768;
+const EN_PASSANT_KEYS_OFFSET: usize = 772;
+const WHITE_TO_MOVE_KEY_INDEX: usize = 780;
+
+/// Key of the piece on the square, Polyglot counts the squares from a1 and the kinds
+/// from the pawn, black before white
+pub fn get_piece_key(piece: u8, position: i8) -> u64 {
+    let kind = match get_piece_type(piece) {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        _ => 5,
+    };
+
+    let piece_index = kind * 2 + is_white_piece(piece) as usize;
+
+    POLYGLOT_RANDOM_64[64 * piece_index + (position ^ 56) as usize]
+}
 
-use rand::{rngs::StdRng, Rng, SeedableRng};
+pub fn get_castling_key(white: bool, king_side: bool) -> u64 {
+    POLYGLOT_RANDOM_64[CASTLING_KEYS_OFFSET + 2 * !white as usize + !king_side as usize]
+}
 
-use crate::common::contants::{EMPTY_PIECE, INVALID_BOARD_POSITION};
+/// Key of the file of the en passant square
+pub fn get_en_passant_key(position: i8) -> u64 {
+    POLYGLOT_RANDOM_64[EN_PASSANT_KEYS_OFFSET + (position % 8) as usize]
+}
 
-use super::{board_state::BoardState, zobrist_utils::get_piece_index};
+pub fn get_white_to_move_key() -> u64 {
+    POLYGLOT_RANDOM_64[WHITE_TO_MOVE_KEY_INDEX]
+}
 
-#[derive(Debug, Clone)]
-pub struct Zobrist {
-    black_can_rook_castle: u64,
-    black_can_queen_castle: u64,
-    black_pawn_en_passant: u64,
-    hash: u64,
-    table: Vec<Vec<u64>>,
-    white_can_rook_castle: u64,
-    white_can_queen_castle: u64,
-    white_pawn_en_passant: u64,
-    white_to_move: u64,
+/// The en passant file only takes part in the hash when a pawn of the side to move
+/// stands next to the pawn that just moved two squares, otherwise the positions are the same
+pub fn get_en_passant_hash(board_state: &BoardState) -> u64 {
+    let white_move = board_state.is_white_move();
+
+    let en_passant = if white_move {
+        board_state.get_black_en_passant()
+    } else {
+        board_state.get_white_en_passant()
+    };
+
+    if en_passant == INVALID_BOARD_POSITION {
+        return 0;
+    }
+
+    // The pawns able to take are the ones a pawn of the other color would attack from the square
+    let pawns = board_state.get_piece_bitboard(get_piece_value(PieceType::Pawn, white_move));
+
+    if get_pawn_attacks(en_passant, !white_move) & pawns == 0 {
+        return 0;
+    }
+
+    get_en_passant_key(en_passant)
 }
 
-impl Zobrist {
-    pub fn new() -> Self {
-        let mut rng = StdRng::seed_from_u64(222);
+/// Hash of the position from scratch, the board state keeps it up to date incrementally
+pub fn compute_hash(board_state: &BoardState) -> u64 {
+    let mut hash = 0u64;
 
-        let mut table = vec![vec![0u64; 12]; 64];
+    if board_state.is_white_move() {
+        hash ^= get_white_to_move_key();
+    }
 
-        for row in table.iter_mut() {
-            for j in 0..row.len() {
-                row[j] = rng.gen::<u64>();
-            }
-        }
+    let castling_rights = [
+        (board_state.is_white_able_to_king_side_castle(), true, true),
+        (board_state.is_white_able_to_queen_side_castle(), true, false),
+        (board_state.is_black_able_to_king_side_castle(), false, true),
+        (board_state.is_black_able_to_queen_side_castle(), false, false),
+    ];
 
-        let black_can_rook_castle = rng.gen::<u64>();
-        let black_can_queen_castle = rng.gen::<u64>();
-        let black_pawn_en_passant = rng.gen::<u64>();
-
-        let white_can_rook_castle = rng.gen::<u64>();
-        let white_can_queen_castle = rng.gen::<u64>();
-        let white_pawn_en_passant = rng.gen::<u64>();
-        let white_to_move = rng.gen::<u64>();
-
-        Self {
-            black_can_rook_castle,
-            black_can_queen_castle,
-            black_pawn_en_passant,
-            hash: 0,
-            table,
-            white_can_rook_castle,
-            white_can_queen_castle,
-            white_pawn_en_passant,
-            white_to_move,
+    for (able_to_castle, white, king_side) in castling_rights {
+        if able_to_castle {
+            hash ^= get_castling_key(white, king_side);
         }
     }
 
-    pub fn get_hash(&self) -> u64 {
-        self.hash
+    hash ^= get_en_passant_hash(board_state);
+
+    for (position, &piece_value) in board_state.get_squares().iter().enumerate() {
+        if piece_value != EMPTY_PIECE {
+            hash ^= get_piece_key(piece_value, position as i8);
+        }
     }
 
-    pub fn update_hash_on_move(
-        &mut self,
-        from_index: usize,
-        to_index: usize,
-        moved_piece: u8,
-        captured_piece: u8,
-    ) {
-        let moved_piece_index = get_piece_index(moved_piece);
+    hash
+}
 
-        // XOR out the old position of the moved piece
-        self.hash ^= self.table[from_index][moved_piece_index];
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 
-        // XOR in the new position of the moved piece
-        self.hash ^= self.table[to_index][moved_piece_index];
+    use crate::{
+        common::piece_utils::get_promotion_options,
+        game::{board::Board, move_parser::parse_move},
+    };
 
-        // If a piece was captured, XOR it out
-        if captured_piece != EMPTY_PIECE {
-            let captured_piece_index = get_piece_index(captured_piece);
+    use super::compute_hash;
 
-            self.hash ^= self.table[to_index][captured_piece_index];
-        }
+    fn play(board: &mut Board, moves: &[&str]) {
+        for notation in moves {
+            let piece_move = parse_move(board, notation).unwrap();
 
-        self.hash ^= self.white_to_move;
+            board.move_piece(&piece_move).unwrap();
+        }
     }
 
-    pub fn update_hash_on_black_en_passant_change(&mut self) {
-        self.hash ^= self.black_pawn_en_passant;
-    }
+    fn hash_of(fen: &str) -> u64 {
+        let mut board = Board::new();
 
-    pub fn update_hash_on_white_en_passant_change(&mut self) {
-        self.hash ^= self.white_pawn_en_passant;
-    }
+        board.load_position(fen).unwrap();
 
-    pub fn update_hash_on_black_lose_rook_side_castle(&mut self) {
-        self.hash ^= self.black_can_rook_castle;
+        board.get_zobrist_hash()
     }
 
-    pub fn update_hash_on_black_lose_queen_side_castle(&mut self) {
-        self.hash ^= self.black_can_queen_castle;
-    }
+    #[test]
+    fn test_polyglot_keys() {
+        // Reference keys of the Polyglot book format specification
+        let mut board = Board::new();
+
+        assert_eq!(board.get_zobrist_hash(), 0x463b_9618_1691_fc9c);
+
+        let expected_hashes = [
+            ("e4", 0x823c_9b50_fd11_4196),
+            ("d5", 0x0756_b944_61c5_0fb0),
+            ("e5", 0x662f_afb9_65db_29d4),
+            ("f5", 0x22a4_8b5a_8e47_ff78),
+            ("Ke2", 0x652a_607c_a3f2_42c1),
+            ("Kf7", 0x00fd_d303_c946_bdd9),
+        ];
+
+        for (notation, expected_hash) in expected_hashes {
+            play(&mut board, &[notation]);
+
+            assert_eq!(board.get_zobrist_hash(), expected_hash, "after {notation}");
+        }
 
-    pub fn update_hash_on_white_lose_rook_side_castle(&mut self) {
-        self.hash ^= self.white_can_rook_castle;
+        // The en passant file only counts when the capture is possible
+        assert_eq!(
+            hash_of("rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3"),
+            0x3c81_23ea_7b06_7637
+        );
+        assert_eq!(
+            hash_of("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2"),
+            0x0756_b944_61c5_0fb0
+        );
     }
 
-    pub fn update_hash_on_white_lose_queen_side_castle(&mut self) {
-        self.hash ^= self.white_can_queen_castle;
+    #[test]
+    fn test_transpositions_share_the_hash() {
+        let mut board = Board::new();
+        let mut transposed_board = Board::new();
+
+        play(&mut board, &["Nf3", "Nf6", "Nc3", "Nc6"]);
+        play(&mut transposed_board, &["Nc3", "Nc6", "Nf3", "Nf6"]);
+
+        assert_eq!(board.get_zobrist_hash(), transposed_board.get_zobrist_hash());
+        assert_eq!(
+            board.get_zobrist_hash(),
+            hash_of("r1bqkb1r/pppppppp/2n2n2/8/8/2N2N2/PPPPPPPP/R1BQKB1R w KQkq - 4 3")
+        );
     }
 
-    pub fn compute_hash(&mut self, board_state: &BoardState) -> u64 {
-        let mut hash = 0u64;
+    #[test]
+    fn test_hash_follows_the_position() {
+        let mut board = Board::new();
 
-        if board_state.is_white_move() {
-            hash ^= self.white_to_move;
-        }
+        board
+            .load_position("r3k2r/pppq1ppp/8/8/3pP3/8/PPPQ1PPP/R3K2R b KQkq e3 0 1")
+            .unwrap();
 
-        if board_state.is_black_able_to_king_side_castle() {
-            hash ^= self.black_can_rook_castle;
-        }
+        let initial_hash = board.get_zobrist_hash();
 
-        if board_state.is_black_able_to_queen_side_castle() {
-            hash ^= self.black_can_queen_castle;
-        }
+        // En passant, castling on both sides and a rook losing its castling right
+        play(&mut board, &["dxe3", "Rb1", "O-O-O", "O-O", "Rhe8"]);
 
-        if board_state.get_black_en_passant() != INVALID_BOARD_POSITION {
-            hash ^= self.black_pawn_en_passant;
-        }
+        assert_eq!(board.get_zobrist_hash(), compute_hash(board.get_state_reference()));
+        assert_eq!(board.get_zobrist_hash(), hash_of(&board.get_state_reference().get_fen()));
 
-        if board_state.is_white_able_to_king_side_castle() {
-            hash ^= self.white_can_rook_castle;
+        for _ in 0..5 {
+            board.undo_last_move();
         }
 
-        if board_state.is_white_able_to_queen_side_castle() {
-            hash ^= self.white_can_queen_castle;
-        }
+        assert_eq!(board.get_zobrist_hash(), initial_hash);
+    }
 
-        if board_state.get_white_en_passant() != INVALID_BOARD_POSITION {
-            hash ^= self.white_pawn_en_passant;
-        }
+    #[test]
+    fn test_incremental_hash_over_random_games() {
+        // Positions with castling, en passant and promotions close at hand
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ];
 
-        for (i, piece_value) in board_state.get_squares().iter().enumerate() {
-            if *piece_value != EMPTY_PIECE {
-                hash ^= self.table[i][get_piece_index(*piece_value)];
-            }
-        }
+        let mut rng = StdRng::seed_from_u64(14);
+
+        for fen in fens {
+            for _ in 0..8 {
+                let mut board = Board::new();
 
-        self.hash = hash;
+                board.load_position(fen).unwrap();
 
-        hash
+                let mut hashes = vec![board.get_zobrist_hash()];
+
+                for _ in 0..120 {
+                    let moves = board.get_legal_moves();
+
+                    let Some(mut piece_move) = moves.choose(&mut rng).cloned() else {
+                        break;
+                    };
+
+                    if piece_move.is_promotion() {
+                        let options = get_promotion_options(board.is_white_move());
+
+                        piece_move.set_promotion_value(*options.choose(&mut rng).unwrap());
+                    }
+
+                    board.move_piece(&piece_move).unwrap();
+
+                    let state = board.get_state_reference();
+
+                    assert_eq!(board.get_zobrist_hash(), compute_hash(state), "{}", state.get_fen());
+                    assert_eq!(board.get_zobrist_hash(), hash_of(&state.get_fen()), "{}", state.get_fen());
+
+                    hashes.push(board.get_zobrist_hash());
+                }
+
+                while hashes.len() > 1 {
+                    hashes.pop();
+
+                    board.undo_last_move();
+
+                    assert_eq!(board.get_zobrist_hash(), *hashes.last().unwrap());
+                }
+            }
+        }
     }
 }
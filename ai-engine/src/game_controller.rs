@@ -5,10 +5,15 @@ use actix_web::{get, post, web, HttpResponse, Responder};
 use serde_json::json;
 
 use crate::{
-    ai::ai_utils::get_board_value,
+    ai::{ai_utils::get_board_value, endgame_tables::EndgameTables, opening_book::OpeningBook, syzygy::Tablebase},
     common::piece_move::PieceMove,
-    dto::dtos::{AIDepthDTO, FenDTO, MovesCountDTO},
-    game::moves_counter::count_moves,
+    dto::dtos::{AIDepthDTO, BookDTO, EndgameTablesDTO, FenDTO, MoveDTO, MovesCountDTO, PerftDTO, PgnDTO, TablebaseDTO},
+    game::{
+        move_parser::parse_move,
+        moves_counter::{count_moves, perft_divide, perft_statistics},
+        pgn::{parse_pgn, PgnGame},
+        san::{get_san_notation, set_san_notations},
+    },
     global_state::GlobalState,
 };
 
@@ -22,11 +27,11 @@ pub async fn get_move_generation_count(
     piece_move: web::Json<MovesCountDTO>,
     global_state: web::Data<Mutex<GlobalState>>,
 ) -> impl Responder {
-    let mut board = &mut global_state.lock().unwrap().board;
+    let board = &mut global_state.lock().unwrap().board;
 
     let start = Instant::now();
 
-    let nodes_searched = count_moves(&mut board, piece_move.depth, false);
+    let nodes_searched = count_moves(board, piece_move.depth);
 
     HttpResponse::Ok().json(json!({
         "moves": nodes_searched,
@@ -34,16 +39,61 @@ pub async fn get_move_generation_count(
     }))
 }
 
+#[post("/board/perft")]
+pub async fn perft(
+    perft_dto: web::Json<PerftDTO>,
+    global_state: web::Data<Mutex<GlobalState>>,
+) -> impl Responder {
+    let mut board = global_state.lock().unwrap().board.clone();
+
+    let start = Instant::now();
+
+    let divide = perft_divide(&mut board, perft_dto.depth);
+
+    let nodes: u64 = if perft_dto.depth == 0 {
+        1
+    } else {
+        divide.iter().map(|(_, nodes)| nodes).sum()
+    };
+
+    let statistics = if perft_dto.statistics {
+        Some(perft_statistics(&mut board, perft_dto.depth))
+    } else {
+        None
+    };
+
+    let divide: Vec<_> = divide
+        .iter()
+        .map(|(notation, nodes)| json!({ "move": notation, "nodes": nodes }))
+        .collect();
+
+    HttpResponse::Ok().json(json!({
+        "nodes": nodes,
+        "divide": divide,
+        "statistics": statistics,
+        "elapsedTime": start.elapsed().as_millis(),
+    }))
+}
+
 #[post("/board/move/piece")]
 pub async fn move_piece(
-    piece_move: web::Json<PieceMove>,
+    move_dto: web::Json<MoveDTO>,
     global_state: web::Data<Mutex<GlobalState>>,
 ) -> impl Responder {
-    // println!("Req: {:?}", req);
     let mut _global_state = global_state.lock().unwrap();
 
     let board = &mut _global_state.board;
 
+    let piece_move = match move_dto.into_inner() {
+        MoveDTO::Piece(piece_move) => piece_move,
+        MoveDTO::Notation { notation } => match parse_move(board, &notation) {
+            Ok(piece_move) => piece_move,
+            Err(err) => {
+                return HttpResponse::BadRequest().json(json!({ "error": err.to_string() }));
+            }
+        },
+    };
+
     let _ = board.move_piece(&piece_move);
 
     drop(_global_state);
@@ -59,13 +109,51 @@ pub async fn load_fen(
     let mut _global_state = global_state.lock().unwrap();
     let board = &mut _global_state.board;
 
-    board.load_position(&fen_dto.fen);
+    if let Err(err) = board.load_position(&fen_dto.fen) {
+        return HttpResponse::BadRequest().json(json!({ "error": err.to_string() }));
+    }
 
     drop(_global_state);
 
     get_board_response(global_state)
 }
 
+/// Loads the main line of the first game of the PGN
+#[post("/board/load/pgn")]
+pub async fn load_pgn(
+    pgn_dto: web::Json<PgnDTO>,
+    global_state: web::Data<Mutex<GlobalState>>,
+) -> impl Responder {
+    let games = match parse_pgn(&pgn_dto.pgn) {
+        Ok(games) => games,
+        Err(err) => return HttpResponse::BadRequest().json(json!({ "error": err.to_string() })),
+    };
+
+    let Some(game) = games.first() else {
+        return HttpResponse::BadRequest().json(json!({ "error": "no game found in the PGN" }));
+    };
+
+    let board = match game.to_board() {
+        Ok(board) => board,
+        Err(err) => return HttpResponse::BadRequest().json(json!({ "error": err.to_string() })),
+    };
+
+    global_state.lock().unwrap().board = board;
+
+    get_board_response(global_state)
+}
+
+#[get("/board/pgn")]
+pub async fn get_pgn(global_state: web::Data<Mutex<GlobalState>>) -> impl Responder {
+    let board = &global_state.lock().unwrap().board;
+
+    let mut game = PgnGame::from_board(board);
+
+    game.set_header("Event", "RustMate game");
+
+    HttpResponse::Ok().json(json!({ "pgn": game.to_pgn() }))
+}
+
 #[post("/ai/time_to_think")]
 pub async fn set_ai_depth(
     depth: web::Json<AIDepthDTO>,
@@ -76,6 +164,73 @@ pub async fn set_ai_depth(
     HttpResponse::Ok()
 }
 
+#[post("/ai/book")]
+pub async fn set_ai_book(
+    book_dto: web::Json<BookDTO>,
+    global_state: web::Data<Mutex<GlobalState>>,
+) -> impl Responder {
+    let book_dto = book_dto.into_inner();
+
+    let mut _global_state = global_state.lock().unwrap();
+
+    if let Some(path) = &book_dto.path {
+        match OpeningBook::load(path) {
+            Ok(book) => _global_state.ai.set_book(Some(book)),
+            Err(err) => return HttpResponse::BadRequest().json(json!({ "error": err.to_string() })),
+        }
+    }
+
+    _global_state.ai.set_book_mode(book_dto.mode);
+
+    HttpResponse::Ok().finish()
+}
+
+#[post("/ai/tablebase")]
+pub async fn set_ai_tablebase(
+    tablebase_dto: web::Json<TablebaseDTO>,
+    global_state: web::Data<Mutex<GlobalState>>,
+) -> impl Responder {
+    let tablebase_dto = tablebase_dto.into_inner();
+
+    let mut _global_state = global_state.lock().unwrap();
+
+    match tablebase_dto.path.as_deref() {
+        Some("") => _global_state.ai.set_tablebase(None),
+        Some(path) => match Tablebase::load(path) {
+            Ok(tablebase) => _global_state.ai.set_tablebase(Some(tablebase)),
+            Err(err) => return HttpResponse::BadRequest().json(json!({ "error": err.to_string() })),
+        },
+        None => {}
+    }
+
+    if let Some(probe_depth) = tablebase_dto.probe_depth {
+        _global_state.ai.set_probe_depth(probe_depth.max(1));
+    }
+
+    HttpResponse::Ok().finish()
+}
+
+#[post("/ai/endgame_tables")]
+pub async fn set_ai_endgame_tables(
+    tables_dto: web::Json<EndgameTablesDTO>,
+    global_state: web::Data<Mutex<GlobalState>>,
+) -> impl Responder {
+    let mut _global_state = global_state.lock().unwrap();
+
+    if tables_dto.path.is_empty() {
+        _global_state.ai.set_endgame_tables(None);
+
+        return HttpResponse::Ok().finish();
+    }
+
+    match EndgameTables::load(&tables_dto.path) {
+        Ok(tables) => _global_state.ai.set_endgame_tables(Some(tables)),
+        Err(err) => return HttpResponse::BadRequest().json(json!({ "error": err.to_string() })),
+    }
+
+    HttpResponse::Ok().finish()
+}
+
 #[post("/ai/move")]
 pub async fn ai_move(global_state: web::Data<Mutex<GlobalState>>) -> impl Responder {
     let mut _global_state = global_state.lock().unwrap();
@@ -93,12 +248,14 @@ pub async fn ai_move(global_state: web::Data<Mutex<GlobalState>>) -> impl Respon
 
     let mut board = _global_state.board.to_owned();
     
-    let ai_player = &mut _global_state.ai;
+    let ai_player = &_global_state.ai;
 
-    let (duration, depth, evaluation, ai_move) = ai_player.get_move(&mut board, time_to_think);
+    let (duration, depth, evaluation, mut ai_move) = ai_player.get_move(&mut board, time_to_think);
 
     let board = &mut _global_state.board;
 
+    ai_move.set_notation(get_san_notation(board, &ai_move));
+
     let _ = board.move_piece(&ai_move);
 
     HttpResponse::Ok().json(json!({
@@ -109,7 +266,7 @@ pub async fn ai_move(global_state: web::Data<Mutex<GlobalState>>) -> impl Respon
     }))
 }
 
-pub fn get_board_response(global_state: web::Data<Mutex<GlobalState>>) -> impl Responder {
+pub fn get_board_response(global_state: web::Data<Mutex<GlobalState>>) -> HttpResponse {
     let mut _global_state = global_state.lock().unwrap();
     let board = &mut _global_state.board;
 
@@ -120,7 +277,9 @@ pub fn get_board_response(global_state: web::Data<Mutex<GlobalState>>) -> impl R
 
     let board_fen = board_state.get_fen();
 
-    let pieces = board.get_pieces();
+    let mut pieces = board.get_pieces();
+
+    set_san_notations(board, &mut pieces);
 
     let black_king_in_check = board.is_black_king_in_check();
     let white_king_in_check = board.is_white_king_in_check();
@@ -130,6 +289,7 @@ pub fn get_board_response(global_state: web::Data<Mutex<GlobalState>>) -> impl R
         "blackEnPassant": black_en_passant,
         "blackKingInCheck": black_king_in_check,
         "boardFen": board_fen,
+        "drawReason": board.get_draw_reason().map(|draw_reason| draw_reason.value()),
         "whiteCaptures": board.white_captures_to_fen(),
         "whiteMove": board.is_white_move(),
         "winner": board.get_winner_fen(),
@@ -1,4 +1,4 @@
-use crate::{ai::ai_player::AIPlayer, common::contants::INITIAL_FEN, game::board::Board};
+use crate::{ai::ai_player::AIPlayer, game::board::Board};
 
 pub struct GlobalState {
     pub ai: AIPlayer,
@@ -8,13 +8,9 @@ pub struct GlobalState {
 
 impl GlobalState {
     pub fn new() -> GlobalState {
-        let mut board: Board = Board::new();
-
-        board.load_position(INITIAL_FEN);
-
         GlobalState {
             ai: AIPlayer::new(),
-            board,
+            board: Board::new(),
             time_to_think: 2,
         }
     }
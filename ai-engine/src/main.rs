@@ -1,9 +1,11 @@
 mod ai;
+mod cli;
 mod common;
 mod dto;
 mod game;
 mod game_controller;
 mod global_state;
+mod protocol;
 
 use std::sync::Mutex;
 
@@ -11,12 +13,71 @@ use actix_cors::Cors;
 use actix_web::{http::header, middleware::Logger, web, App, HttpServer};
 use global_state::GlobalState;
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
+fn main() -> std::io::Result<()> {
     rayon::ThreadPoolBuilder::new()
         .num_threads(24)
         .build_global()
         .unwrap();
+
+    if std::env::args().any(|arg| arg == "--uci") {
+        protocol::uci::run();
+
+        return Ok(());
+    }
+
+    if std::env::args().any(|arg| arg == "--xboard") {
+        protocol::xboard::run();
+
+        return Ok(());
+    }
+
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.iter().any(|arg| arg == "--perft") {
+        if let Err(err) = cli::run_perft(&args) {
+            eprintln!("{}", err);
+
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--perft-suite") {
+        if let Err(err) = cli::run_perft_suite_file(&args) {
+            eprintln!("{}", err);
+
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--generate-tables") {
+        if let Err(err) = cli::run_generate_tables(&args) {
+            eprintln!("{}", err);
+
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    if args.iter().any(|arg| arg == "--build-book") {
+        if let Err(err) = cli::run_build_book(&args) {
+            eprintln!("{}", err);
+
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    start_server()
+}
+
+#[actix_web::main]
+async fn start_server() -> std::io::Result<()> {
     println!("Server started successfully 🚀!");
 
     let state = web::Data::new(Mutex::new(GlobalState::new()));
@@ -36,9 +97,15 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::clone(&state))
             .service(game_controller::get_board)
             .service(game_controller::get_move_generation_count)
+            .service(game_controller::perft)
             .service(game_controller::load_fen)
+            .service(game_controller::load_pgn)
+            .service(game_controller::get_pgn)
             .service(game_controller::move_piece)
             .service(game_controller::set_ai_depth)
+            .service(game_controller::set_ai_book)
+            .service(game_controller::set_ai_tablebase)
+            .service(game_controller::set_ai_endgame_tables)
             .service(game_controller::ai_move)
             // .configure(config)
             .wrap(cors)
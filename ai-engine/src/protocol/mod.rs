@@ -0,0 +1,19 @@
+pub mod uci;
+pub mod xboard;
+
+pub const ENGINE_NAME: &str = "RustMate";
+
+// Time kept aside to send the move back before the clock runs out
+pub const MOVE_OVERHEAD_MS: u64 = 50;
+pub const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+/// Splits the remaining clock time between the moves left until the next time control
+pub fn get_move_time_budget(time_left_ms: u64, increment_ms: u64, moves_to_go: Option<u64>) -> u64 {
+    let moves_to_go = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+
+    let budget = time_left_ms / moves_to_go + increment_ms * 3 / 4;
+
+    budget
+        .min(time_left_ms.saturating_sub(MOVE_OVERHEAD_MS))
+        .max(1)
+}
@@ -0,0 +1,439 @@
+use std::{
+    io::{self, BufRead},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    ai::{
+        ai_player::{AIPlayer, SearchInfo, SearchLimits},
+        endgame_tables::EndgameTables,
+        opening_book::{BookMode, OpeningBook},
+        syzygy::{Tablebase, DEFAULT_PROBE_DEPTH},
+        transposition_table::Bound,
+    },
+    common::{
+        board_utils::get_uci_move_notation, contants::INVALID_BOARD_POSITION,
+        piece_move::PieceMove,
+    },
+    game::{board::Board, move_parser::parse_uci_move},
+};
+
+use super::{get_move_time_budget, ENGINE_NAME, MOVE_OVERHEAD_MS};
+
+const ENGINE_AUTHOR: &str = "Thiago Buarque";
+
+#[derive(Debug, Default)]
+struct GoParameters {
+    binc: Option<u64>,
+    btime: Option<u64>,
+    depth: Option<u8>,
+    infinite: bool,
+    movestogo: Option<u64>,
+    movetime: Option<u64>,
+    nodes: Option<u64>,
+    winc: Option<u64>,
+    wtime: Option<u64>,
+}
+
+impl GoParameters {
+    fn parse(tokens: &[&str]) -> Self {
+        let mut parameters = GoParameters::default();
+
+        let mut iter = tokens.iter();
+
+        while let Some(&token) = iter.next() {
+            if token == "infinite" {
+                parameters.infinite = true;
+
+                continue;
+            }
+
+            let value = iter.next().and_then(|value| value.parse::<u64>().ok());
+
+            match token {
+                "binc" => parameters.binc = value,
+                "btime" => parameters.btime = value,
+                "depth" => parameters.depth = value.map(|depth| depth.min(u8::MAX as u64) as u8),
+                "movestogo" => parameters.movestogo = value,
+                "movetime" => parameters.movetime = value,
+                "nodes" => parameters.nodes = value,
+                "winc" => parameters.winc = value,
+                "wtime" => parameters.wtime = value,
+                _ => {}
+            }
+        }
+
+        parameters
+    }
+
+    fn to_search_limits(&self, white_move: bool) -> SearchLimits {
+        let (time_left, increment) = if white_move {
+            (self.wtime, self.winc.unwrap_or(0))
+        } else {
+            (self.btime, self.binc.unwrap_or(0))
+        };
+
+        let move_time = if let Some(move_time) = self.movetime {
+            Some(move_time.saturating_sub(MOVE_OVERHEAD_MS).max(1))
+        } else {
+            time_left.map(|time_left| get_move_time_budget(time_left, increment, self.movestogo))
+        };
+
+        SearchLimits {
+            depth: self.depth,
+            infinite: self.infinite,
+            move_time: move_time.map(Duration::from_millis),
+            nodes: self.nodes,
+        }
+    }
+}
+
+pub struct UciEngine {
+    ai: Arc<AIPlayer>,
+    board: Board,
+    search_thread: Option<JoinHandle<()>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl UciEngine {
+    pub fn new() -> Self {
+        let mut ai = AIPlayer::new();
+        let stop = ai.get_stop_handle();
+
+        // Books are only used once the GUI enables OwnBook
+        ai.set_book_mode(BookMode::Off);
+
+        UciEngine {
+            ai: Arc::new(ai),
+            board: Board::new(),
+            search_thread: None,
+            stop,
+        }
+    }
+
+    /// Handles a single command, returns false once the engine should quit
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        match tokens.first() {
+            Some(&"uci") => {
+                println!("id name {}", ENGINE_NAME);
+                println!("id author {}", ENGINE_AUTHOR);
+                println!("option name OwnBook type check default false");
+                println!("option name BookFile type string default <empty>");
+                println!("option name EndgameTablesPath type string default <empty>");
+                println!("option name NullMoveVerification type check default true");
+                println!("option name QuiescenceChecks type check default true");
+                println!("option name SyzygyPath type string default <empty>");
+                println!(
+                    "option name SyzygyProbeDepth type spin default {} min 1 max 100",
+                    DEFAULT_PROBE_DEPTH
+                );
+                println!("uciok");
+            }
+            Some(&"isready") => println!("readyok"),
+            Some(&"setoption") => {
+                self.stop_search();
+
+                self.set_option(&tokens[1..]);
+            }
+            Some(&"ucinewgame") => {
+                self.stop_search();
+
+                self.board = Board::new();
+            }
+            Some(&"position") => {
+                self.stop_search();
+
+                self.set_position(&tokens[1..]);
+            }
+            Some(&"go") => self.go(&tokens[1..]),
+            Some(&"stop") => self.stop_search(),
+            Some(&"quit") => {
+                self.stop_search();
+
+                return false;
+            }
+            _ => {}
+        }
+
+        true
+    }
+
+    fn set_option(&mut self, tokens: &[&str]) {
+        let value_index = tokens.iter().position(|&token| token == "value");
+
+        let name = tokens[..value_index.unwrap_or(tokens.len())]
+            .iter()
+            .skip_while(|&&token| token == "name")
+            .copied()
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        let value = value_index
+            .map(|value_index| tokens[value_index + 1..].join(" "))
+            .unwrap_or_default();
+
+        // The search thread was joined, so the engine holds the only reference
+        let Some(ai) = Arc::get_mut(&mut self.ai) else {
+            return;
+        };
+
+        match name.to_ascii_lowercase().as_str() {
+            "ownbook" => ai.set_book_mode(if value == "true" {
+                BookMode::WeightedRandom
+            } else {
+                BookMode::Off
+            }),
+            "bookfile" => {
+                if value.is_empty() || value == "<empty>" {
+                    ai.set_book(None);
+
+                    return;
+                }
+
+                match OpeningBook::load(&value) {
+                    Ok(book) => ai.set_book(Some(book)),
+                    Err(err) => println!("info string {}", err),
+                }
+            }
+            "endgametablespath" => {
+                if value.is_empty() || value == "<empty>" {
+                    ai.set_endgame_tables(None);
+
+                    return;
+                }
+
+                match EndgameTables::load(&value) {
+                    Ok(tables) => {
+                        println!("info string found endgame tables of up to {} pieces", tables.get_max_pieces());
+
+                        ai.set_endgame_tables(Some(tables));
+                    }
+                    Err(err) => println!("info string {}", err),
+                }
+            }
+            "nullmoveverification" => ai.set_null_move_verification(value == "true"),
+            "quiescencechecks" => ai.set_quiescence_checks(value == "true"),
+            "syzygypath" => {
+                if value.is_empty() || value == "<empty>" {
+                    ai.set_tablebase(None);
+
+                    return;
+                }
+
+                match Tablebase::load(&value) {
+                    Ok(tablebase) => {
+                        println!("info string found Syzygy tables of up to {} pieces", tablebase.get_max_pieces());
+
+                        ai.set_tablebase(Some(tablebase));
+                    }
+                    Err(err) => println!("info string {}", err),
+                }
+            }
+            "syzygyprobedepth" => match value.parse::<u8>() {
+                Ok(probe_depth) => ai.set_probe_depth(probe_depth.clamp(1, 100)),
+                Err(_) => println!("info string invalid probe depth {}", value),
+            },
+            _ => println!("info string unknown option {}", name),
+        }
+    }
+
+    fn set_position(&mut self, tokens: &[&str]) {
+        let moves_index = tokens.iter().position(|&token| token == "moves");
+
+        let position_tokens = &tokens[..moves_index.unwrap_or(tokens.len())];
+
+        let mut board = Board::new();
+
+        match position_tokens.first() {
+            Some(&"startpos") => {}
+            Some(&"fen") => {
+                if let Err(err) = board.load_position(&position_tokens[1..].join(" ")) {
+                    println!("info string invalid fen: {}", err);
+
+                    return;
+                }
+            }
+            _ => {
+                println!("info string invalid position command");
+
+                return;
+            }
+        }
+
+        if let Some(moves_index) = moves_index {
+            for notation in &tokens[moves_index + 1..] {
+                let piece_move = match parse_uci_move(&mut board, notation) {
+                    Ok(piece_move) => piece_move,
+                    Err(err) => {
+                        println!("info string {}", err);
+
+                        break;
+                    }
+                };
+
+                let _ = board.move_piece(&piece_move);
+            }
+        }
+
+        self.board = board;
+    }
+
+    fn go(&mut self, tokens: &[&str]) {
+        self.stop_search();
+
+        self.stop.store(false, Ordering::SeqCst);
+
+        let parameters = GoParameters::parse(tokens);
+        let limits = parameters.to_search_limits(self.board.is_white_move());
+
+        let ai = self.ai.clone();
+        let stop = self.stop.clone();
+        let mut board = self.board.clone();
+
+        self.search_thread = Some(thread::spawn(move || {
+            let best_move = match ai.get_book_move(&mut board) {
+                Some(book_move) => book_move,
+                None => ai.search(&mut board, &limits, print_search_info).2,
+            };
+
+            // In infinite mode the best move may only be sent after "stop"
+            while limits.infinite && !stop.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(5));
+            }
+
+            println!("bestmove {}", get_best_move_notation(&best_move));
+        }));
+    }
+
+    fn stop_search(&mut self) {
+        if let Some(search_thread) = self.search_thread.take() {
+            self.stop.store(true, Ordering::SeqCst);
+
+            let _ = search_thread.join();
+        }
+    }
+}
+
+fn print_search_info(info: &SearchInfo) {
+    let pv: Vec<String> = info.pv.iter().map(get_uci_move_notation).collect();
+
+    let bound = match info.bound {
+        Bound::Exact => "",
+        Bound::Lower => " lowerbound",
+        Bound::Upper => " upperbound",
+    };
+
+    let score = match info.get_mate_moves() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", info.score as i32),
+    };
+
+    println!(
+        "info depth {} score {}{} nodes {} nps {} time {} pv {}",
+        info.depth,
+        score,
+        bound,
+        info.nodes,
+        info.get_nps(),
+        info.elapsed.as_millis(),
+        pv.join(" ")
+    );
+}
+
+fn get_best_move_notation(best_move: &PieceMove) -> String {
+    if best_move.get_from_position() == INVALID_BOARD_POSITION {
+        // Null move, there is nothing to play
+        return String::from("0000");
+    }
+
+    get_uci_move_notation(best_move)
+}
+
+pub fn run() {
+    let mut engine = UciEngine::new();
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        if !engine.handle_command(&line) {
+            return;
+        }
+    }
+
+    engine.stop_search();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::protocol::MOVE_OVERHEAD_MS;
+
+    use super::{GoParameters, UciEngine};
+
+    fn get_fen(engine: &UciEngine) -> String {
+        engine.board.get_state_reference().get_fen()
+    }
+
+    #[test]
+    fn test_go_parameters() {
+        let parameters = GoParameters::parse(&["wtime", "60000", "btime", "30000", "winc", "1000", "movestogo", "20"]);
+
+        assert_eq!(parameters.wtime, Some(60000));
+        assert_eq!(parameters.btime, Some(30000));
+        assert_eq!(parameters.winc, Some(1000));
+        assert_eq!(parameters.movestogo, Some(20));
+
+        // Each side only spends its own clock
+        let white_limits = parameters.to_search_limits(true);
+        let black_limits = parameters.to_search_limits(false);
+
+        assert_eq!(white_limits.move_time, Some(Duration::from_millis(60000 / 20 + 750)));
+        assert_eq!(black_limits.move_time, Some(Duration::from_millis(30000 / 20)));
+
+        let parameters = GoParameters::parse(&["movetime", "500", "depth", "300", "nodes", "1000"]);
+        let limits = parameters.to_search_limits(true);
+
+        assert_eq!(limits.move_time, Some(Duration::from_millis(500 - MOVE_OVERHEAD_MS)));
+        assert_eq!(limits.depth, Some(u8::MAX));
+        assert_eq!(limits.nodes, Some(1000));
+        assert!(!limits.infinite);
+
+        let limits = GoParameters::parse(&["infinite"]).to_search_limits(true);
+
+        assert!(limits.infinite);
+        assert_eq!(limits.move_time, None);
+    }
+
+    #[test]
+    fn test_position() {
+        let mut engine = UciEngine::new();
+
+        engine.handle_command("position startpos moves e2e4 e7e5 g1f3");
+
+        assert_eq!(get_fen(&engine), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+
+        engine.handle_command("position fen 4k3/P7/8/8/8/8/8/4K3 w - - 0 1 moves a7a8q e8d7");
+
+        assert_eq!(get_fen(&engine), "Q7/3k4/8/8/8/8/8/4K3 w - - 1 2");
+
+        // Moves after an illegal one are ignored, the ones before it are kept
+        engine.handle_command("position startpos moves e2e4 e2e4 e7e5");
+
+        assert_eq!(get_fen(&engine), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+
+        // An invalid position keeps the previous one
+        engine.handle_command("position fen not a fen");
+
+        assert_eq!(get_fen(&engine), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    }
+}
@@ -0,0 +1,474 @@
+use std::{
+    io::{self, BufRead},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    ai::{
+        ai_player::{AIPlayer, SearchInfo, SearchLimits},
+        transposition_table::Bound,
+    },
+    common::{board_utils::get_uci_move_notation, contants::INVALID_BOARD_POSITION},
+    game::{board::Board, move_parser::parse_uci_move},
+};
+
+use super::{get_move_time_budget, ENGINE_NAME, MOVE_OVERHEAD_MS};
+
+#[derive(Debug, Clone, Default)]
+struct TimeControl {
+    // Centiseconds, as sent by "time" and "otim", both start from the "level" base time
+    engine_clock: Option<u64>,
+    opponent_clock: Option<u64>,
+    increment_ms: u64,
+    moves_per_session: u64,
+    depth: Option<u8>,
+    seconds_per_move: Option<u64>,
+}
+
+impl TimeControl {
+    /// The clocks belong to the colors, they follow the engine when it changes sides
+    fn set_engine_side(&mut self, engine_white: bool, previous_engine_white: bool) {
+        if engine_white != previous_engine_white {
+            std::mem::swap(&mut self.engine_clock, &mut self.opponent_clock);
+        }
+    }
+
+    fn to_search_limits(&self, full_moves: usize) -> SearchLimits {
+        let move_time = if let Some(seconds) = self.seconds_per_move {
+            Some((seconds * 1000).saturating_sub(MOVE_OVERHEAD_MS).max(1))
+        } else {
+            self.engine_clock.map(|centiseconds| {
+                let moves_to_go = if self.moves_per_session > 0 {
+                    let moves_played = full_moves.saturating_sub(1) as u64;
+
+                    Some(self.moves_per_session - (moves_played % self.moves_per_session))
+                } else {
+                    None
+                };
+
+                get_move_time_budget(centiseconds * 10, self.increment_ms, moves_to_go)
+            })
+        };
+
+        SearchLimits {
+            depth: self.depth,
+            move_time: move_time.map(Duration::from_millis),
+            ..Default::default()
+        }
+    }
+}
+
+pub struct XboardEngine {
+    ai: Arc<AIPlayer>,
+    board: Arc<Mutex<Board>>,
+    // Set when the move being searched must not be played (force, undo, new...)
+    discard_search: Arc<AtomicBool>,
+    engine_white: bool,
+    force_mode: bool,
+    post_thinking: Arc<AtomicBool>,
+    search_thread: Option<JoinHandle<()>>,
+    stop: Arc<AtomicBool>,
+    time_control: TimeControl,
+}
+
+impl XboardEngine {
+    pub fn new() -> Self {
+        let ai = AIPlayer::new();
+        let stop = ai.get_stop_handle();
+
+        XboardEngine {
+            ai: Arc::new(ai),
+            board: Arc::new(Mutex::new(Board::new())),
+            discard_search: Arc::new(AtomicBool::new(false)),
+            engine_white: false,
+            force_mode: false,
+            post_thinking: Arc::new(AtomicBool::new(false)),
+            search_thread: None,
+            stop,
+            time_control: TimeControl::default(),
+        }
+    }
+
+    /// Handles a single command, returns false once the engine should quit
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        let Some(&command) = tokens.first() else {
+            return true;
+        };
+
+        let argument = tokens.get(1).copied().unwrap_or("");
+
+        match command {
+            "protover" => println!(
+                "feature myname=\"{}\" ping=1 setboard=1 usermove=1 playother=1 time=1 \
+                 san=0 draw=0 sigint=0 sigterm=0 reuse=1 analyze=0 colors=0 done=1",
+                ENGINE_NAME
+            ),
+            "ping" => {
+                // Pong must only be sent after the moves received before the ping
+                self.wait_search();
+
+                println!("pong {}", argument);
+            }
+            "new" => {
+                self.abandon_search();
+
+                *self.board.lock().unwrap() = Board::new();
+
+                self.force_mode = false;
+                self.set_engine_white(false);
+                self.time_control.depth = None;
+            }
+            "setboard" => {
+                self.abandon_search();
+
+                let mut board = Board::new();
+
+                match board.load_position(&tokens[1..].join(" ")) {
+                    Ok(()) => *self.board.lock().unwrap() = board,
+                    Err(err) => println!("tellusererror Illegal position: {}", err),
+                }
+            }
+            "usermove" => self.user_move(argument),
+            "go" => {
+                self.wait_search();
+
+                self.force_mode = false;
+                let white_move = self.board.lock().unwrap().is_white_move();
+
+                self.set_engine_white(white_move);
+
+                self.think();
+            }
+            "playother" => {
+                self.wait_search();
+
+                self.force_mode = false;
+                let white_move = self.board.lock().unwrap().is_white_move();
+
+                self.set_engine_white(!white_move);
+            }
+            "force" | "result" => {
+                self.abandon_search();
+
+                self.force_mode = true;
+            }
+            "?" => self.wait_search_with_stop(),
+            "level" => self.set_level(&tokens[1..]),
+            "st" => {
+                self.time_control.seconds_per_move = argument.parse::<u64>().ok();
+            }
+            "sd" => {
+                self.time_control.depth = argument.parse::<u8>().ok();
+            }
+            "time" => {
+                self.time_control.engine_clock = argument.parse::<u64>().ok();
+            }
+            "otim" => {
+                self.time_control.opponent_clock = argument.parse::<u64>().ok();
+            }
+            "undo" => {
+                self.abandon_search();
+
+                self.board.lock().unwrap().undo_last_move();
+            }
+            "remove" => {
+                self.abandon_search();
+
+                let mut board = self.board.lock().unwrap();
+
+                board.undo_last_move();
+                board.undo_last_move();
+            }
+            "post" => self.post_thinking.store(true, Ordering::SeqCst),
+            "nopost" => self.post_thinking.store(false, Ordering::SeqCst),
+            "quit" => {
+                self.abandon_search();
+
+                return false;
+            }
+            // xboard, accepted, rejected, random, hard, easy, computer, name...
+            _ => {}
+        }
+
+        true
+    }
+
+    fn set_level(&mut self, tokens: &[&str]) {
+        if tokens.len() < 3 {
+            return;
+        }
+
+        self.time_control.moves_per_session = tokens[0].parse::<u64>().unwrap_or(0);
+        self.time_control.seconds_per_move = None;
+
+        // Base time is either "minutes" or "minutes:seconds". The GUI usually sends the
+        // remaining time with "time" before every move, until then both clocks start full.
+        let base_clock = parse_base_time(tokens[1]);
+
+        self.time_control.engine_clock = base_clock;
+        self.time_control.opponent_clock = base_clock;
+
+        self.time_control.increment_ms = tokens[2]
+            .parse::<f64>()
+            .map(|increment| (increment * 1000.0) as u64)
+            .unwrap_or(0);
+    }
+
+    fn set_engine_white(&mut self, engine_white: bool) {
+        self.time_control.set_engine_side(engine_white, self.engine_white);
+
+        self.engine_white = engine_white;
+    }
+
+    fn user_move(&mut self, notation: &str) {
+        self.wait_search();
+
+        let mut board = self.board.lock().unwrap();
+
+        let piece_move = match parse_uci_move(&mut board, notation) {
+            Ok(piece_move) => piece_move,
+            Err(err) => {
+                println!("Illegal move ({}): {}", err, notation);
+
+                return;
+            }
+        };
+
+        let _ = board.move_piece(&piece_move);
+
+        let finished = print_result_if_finished(&mut board);
+        let engine_turn = board.is_white_move() == self.engine_white;
+
+        drop(board);
+
+        if !finished && !self.force_mode && engine_turn {
+            self.think();
+        }
+    }
+
+    fn think(&mut self) {
+        self.stop.store(false, Ordering::SeqCst);
+        self.discard_search.store(false, Ordering::SeqCst);
+
+        let mut search_board = self.board.lock().unwrap().clone();
+
+        if search_board.is_game_finished() {
+            return;
+        }
+
+        let limits = self
+            .time_control
+            .to_search_limits(search_board.get_state_reference().get_full_moves());
+
+        let ai = self.ai.clone();
+        let board = self.board.clone();
+        let discard_search = self.discard_search.clone();
+        let post_thinking = self.post_thinking.clone();
+
+        self.search_thread = Some(thread::spawn(move || {
+            let (_, _, best_move) = ai.search(&mut search_board, &limits, |info| {
+                if post_thinking.load(Ordering::SeqCst) {
+                    print_thinking_output(info);
+                }
+            });
+
+            if discard_search.load(Ordering::SeqCst)
+                || best_move.get_from_position() == INVALID_BOARD_POSITION
+            {
+                return;
+            }
+
+            let mut board = board.lock().unwrap();
+
+            let _ = board.move_piece(&best_move);
+
+            println!("move {}", get_uci_move_notation(&best_move));
+
+            print_result_if_finished(&mut board);
+        }));
+    }
+
+    fn wait_search(&mut self) {
+        if let Some(search_thread) = self.search_thread.take() {
+            let _ = search_thread.join();
+        }
+    }
+
+    fn wait_search_with_stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        self.wait_search();
+    }
+
+    fn abandon_search(&mut self) {
+        self.discard_search.store(true, Ordering::SeqCst);
+
+        self.wait_search_with_stop();
+    }
+}
+
+/// Centiseconds of a "minutes" or "minutes:seconds" base time
+fn parse_base_time(base_time: &str) -> Option<u64> {
+    let (minutes, seconds) = base_time.split_once(':').unwrap_or((base_time, "0"));
+
+    let seconds = minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?;
+
+    Some(seconds * 100)
+}
+
+fn print_thinking_output(info: &SearchInfo) {
+    // CECP has no way to tell a bound from a score, only complete iterations are shown
+    if info.bound != Bound::Exact {
+        return;
+    }
+
+    let pv: Vec<String> = info.pv.iter().map(get_uci_move_notation).collect();
+
+    // ply score time(centiseconds) nodes pv
+    println!(
+        "{} {} {} {} {}",
+        info.depth,
+        info.score as i32,
+        info.elapsed.as_millis() / 10,
+        info.nodes,
+        pv.join(" ")
+    );
+}
+
+fn print_result_if_finished(board: &mut Board) -> bool {
+    // Updates the winner of the current position
+    board.get_pieces();
+
+    match board.get_winner_fen() {
+        'w' => println!("1-0 {{White mates}}"),
+        'b' => println!("0-1 {{Black mates}}"),
+        'd' => println!("1/2-1/2 {{Draw}}"),
+        _ => return false,
+    }
+
+    true
+}
+
+pub fn run() {
+    let mut engine = XboardEngine::new();
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        if !engine.handle_command(&line) {
+            return;
+        }
+    }
+
+    engine.abandon_search();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::protocol::MOVE_OVERHEAD_MS;
+
+    use super::{TimeControl, XboardEngine};
+
+    fn get_fen(engine: &XboardEngine) -> String {
+        engine.board.lock().unwrap().get_state_reference().get_fen()
+    }
+
+    #[test]
+    fn test_time_control_limits() {
+        let time_control = TimeControl {
+            engine_clock: Some(6000),
+            moves_per_session: 40,
+            ..Default::default()
+        };
+
+        // 60 seconds left for the 30 moves until the next session
+        assert_eq!(time_control.to_search_limits(11).move_time, Some(Duration::from_millis(2000)));
+
+        let time_control = TimeControl {
+            engine_clock: Some(6000),
+            seconds_per_move: Some(3),
+            depth: Some(5),
+            ..Default::default()
+        };
+
+        let limits = time_control.to_search_limits(1);
+
+        assert_eq!(limits.move_time, Some(Duration::from_millis(3000 - MOVE_OVERHEAD_MS)));
+        assert_eq!(limits.depth, Some(5));
+
+        assert_eq!(TimeControl::default().to_search_limits(1).move_time, None);
+    }
+
+    #[test]
+    fn test_level_st_and_sd() {
+        let mut engine = XboardEngine::new();
+
+        engine.handle_command("level 40 5:30 2.5");
+
+        assert_eq!(engine.time_control.moves_per_session, 40);
+        assert_eq!(engine.time_control.engine_clock, Some(33000));
+        assert_eq!(engine.time_control.opponent_clock, Some(33000));
+        assert_eq!(engine.time_control.increment_ms, 2500);
+
+        engine.handle_command("time 1000");
+        engine.handle_command("otim 2000");
+
+        assert_eq!(engine.time_control.engine_clock, Some(1000));
+        assert_eq!(engine.time_control.opponent_clock, Some(2000));
+
+        // The clocks follow the colors when the engine switches sides
+        engine.handle_command("force");
+        engine.handle_command("usermove e2e4");
+        engine.handle_command("playother");
+
+        assert_eq!(engine.time_control.engine_clock, Some(2000));
+        assert_eq!(engine.time_control.opponent_clock, Some(1000));
+
+        engine.handle_command("level 0 2 0");
+
+        assert_eq!(engine.time_control.moves_per_session, 0);
+        assert_eq!(engine.time_control.engine_clock, Some(12000));
+
+        engine.handle_command("st 10");
+        engine.handle_command("sd 6");
+
+        assert_eq!(engine.time_control.seconds_per_move, Some(10));
+        assert_eq!(engine.time_control.depth, Some(6));
+    }
+
+    #[test]
+    fn test_user_moves_and_takebacks() {
+        let mut engine = XboardEngine::new();
+
+        engine.handle_command("force");
+        engine.handle_command("usermove e2e4");
+        engine.handle_command("usermove e7e5");
+        engine.handle_command("usermove g1f3");
+
+        assert_eq!(get_fen(&engine), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+
+        // Illegal moves are rejected without changing the position
+        engine.handle_command("usermove e4e6");
+
+        assert_eq!(get_fen(&engine), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+
+        engine.handle_command("undo");
+
+        assert_eq!(get_fen(&engine), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+
+        engine.handle_command("remove");
+
+        assert_eq!(get_fen(&engine), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    }
+}
@@ -47,6 +47,7 @@ const Board = () => {
 		blackKingInCheck: false,
 		boardEvaluation: 0,
 		boardFen: INITIAL_FEN,
+		drawReason: null,
 		whiteCaptures: [],
 		pieces: [],
 		whiteKingInCheck: false,
@@ -209,6 +210,10 @@ const Board = () => {
 				}
 
 				setLastAIResponse(undefined);
+			})
+			.catch((err) => {
+				console.error(err);
+				alert(`Invalid FEN: ${err.response?.data?.error ?? err.message}`);
 			});
 	};
 
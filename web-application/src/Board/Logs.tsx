@@ -15,6 +15,7 @@ const Logs: React.FC<IProps> = ({aiResponse, board, isWaitingForAI}) => {
         <div id="board-info">
                 <h1>Board:</h1>
                 <span className='log'><strong>Evaluation:</strong> {board.boardEvaluation.toFixed(1)} ({getBoardEvaluationMessage(board.boardEvaluation, board.winner)})</span>
+                {board.drawReason && <span className='log'><strong>Draw reason:</strong> {board.drawReason}</span>}
                 <span className='log'><strong>Position hash:</strong> {getZobritBinary(board.zobrit)}</span>
                 <span className='log'><strong>Black in check:</strong> {board.blackKingInCheck.toString()}</span>
                 <span className='log'><strong>White in check:</strong> {board.whiteKingInCheck.toString()}</span>
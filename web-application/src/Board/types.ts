@@ -10,6 +10,7 @@ export type TBoard = {
   blackKingInCheck: boolean;
   boardEvaluation: number;
   boardFen: string;
+  drawReason: string | null;
   pieces: TPiece[];
   whiteCaptures: string[];
   whiteKingInCheck: boolean;